@@ -0,0 +1,121 @@
+//! Compares the structure-of-arrays force kernel against the previous
+//! array-of-structures implementation.
+//!
+//! Run with `cargo bench -p untitled_lib --bench gravity [-- <num bodies>...]`.
+
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+use untitled_lib::{
+    consts,
+    sim::{
+        resources::{CelestialBody, Galaxy},
+        SimVec,
+    },
+};
+
+const DEFAULT_SIZES: [usize; 5] = [1_000, 5_000, 10_000, 20_000, 50_000];
+/// Roughly how many pair interactions to evaluate per measurement.
+const INTERACTION_BUDGET: usize = 20_000_000;
+
+#[derive(Clone, Copy)]
+struct AosBody {
+    pos: SimVec,
+    mass: f64,
+    vel: SimVec,
+    acc: SimVec,
+}
+
+fn aos_step(bodies: &mut [AosBody], dt: f64) {
+    bodies.par_iter_mut().for_each(|body| {
+        body.acc = SimVec::ZERO;
+    });
+
+    for i_lhs in 0..bodies.len() {
+        let lhs = bodies[i_lhs];
+        bodies.par_iter_mut().enumerate().for_each(|(i_rhs, rhs)| {
+            if i_lhs == i_rhs {
+                return;
+            }
+            let dist = (rhs.pos - lhs.pos).length_squared();
+            rhs.acc += consts::G * lhs.mass / dist * (lhs.pos - rhs.pos).normalize();
+        });
+    }
+
+    bodies.par_iter_mut().for_each(|body| {
+        body.vel += body.acc * dt;
+        body.pos += body.vel * dt;
+    });
+}
+
+fn gen_bodies(num: usize) -> Vec<CelestialBody> {
+    let mut rng = StdRng::seed_from_u64(num as u64);
+    (0..num)
+        .map(|_| {
+            CelestialBody::new(
+                SimVec::from_array(std::array::from_fn(|_| rng.gen_range(-1e4..1e4))),
+                1.,
+                rng.gen_range(1e10..1e15),
+                SimVec::from_array(std::array::from_fn(|_| rng.gen_range(-1.0..1.0))),
+            )
+        })
+        .collect()
+}
+
+fn measure(iterations: usize, mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations as u32
+}
+
+fn main() {
+    let sizes = std::env::args()
+        .skip(1)
+        .filter_map(|arg| arg.parse().ok())
+        .collect::<Vec<usize>>();
+    let sizes = if sizes.is_empty() {
+        DEFAULT_SIZES.to_vec()
+    } else {
+        sizes
+    };
+
+    println!(
+        "{:>8} {:>14} {:>14} {:>8}",
+        "bodies", "aos (ms/step)", "soa (ms/step)", "speedup"
+    );
+    for num in sizes {
+        let bodies = gen_bodies(num);
+        let iterations = (INTERACTION_BUDGET / (num * num)).max(1);
+
+        let mut aos = bodies
+            .iter()
+            .map(|body| AosBody {
+                pos: body.pos(),
+                mass: body.mass(),
+                vel: body.vel(),
+                acc: SimVec::ZERO,
+            })
+            .collect::<Vec<_>>();
+        let aos_time = measure(iterations, || {
+            aos_step(&mut aos, consts::CELESTIAL_SIM_STEP)
+        });
+
+        let mut galaxy = Galaxy::default();
+        bodies.into_iter().for_each(|body| {
+            galaxy.add_body(body);
+        });
+        let soa_time = measure(iterations, || galaxy.step());
+
+        println!(
+            "{:>8} {:>14.3} {:>14.3} {:>7.2}x",
+            num,
+            aos_time.as_secs_f64() * 1e3,
+            soa_time.as_secs_f64() * 1e3,
+            aos_time.as_secs_f64() / soa_time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Searches seeds for galaxies that meet the predicates of a search file, without
+//! opening a window.
+//!
+//! Run from the workspace root with
+//! `cargo run -p untitled_lib --example seed_search -- [<search file> [<output file>]]`.
+//! The search file defaults to `cosmos/assets/config/seed_search.json`. Matching seeds
+//! are printed, and saved as JSON to the output file if one is given.
+
+use std::{fmt::Display, process};
+
+use untitled_lib::{
+    assets::{
+        settings::{ConstellationNames, StarProperties},
+        SubstanceAssets,
+    },
+    consts,
+    gen::search::SearchConfig,
+};
+
+fn or_exit<T, E: Display>(result: Result<T, E>, what: &str) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("Failed to {}: {}", what, err);
+        process::exit(1);
+    })
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or_else(|| consts::SEED_SEARCH.to_string());
+    let search = or_exit(SearchConfig::load(&path), "load the search");
+    let constellation_names = or_exit(
+        ConstellationNames::load(consts::STAR_NAMES),
+        "load star names",
+    );
+    let star_props = or_exit(
+        StarProperties::load(consts::STAR_PROPERTIES),
+        "load star properties",
+    );
+    let substance_assets = or_exit(
+        SubstanceAssets::load(consts::SUBSTANCE_ASSETS),
+        "load substances",
+    );
+
+    let result = or_exit(
+        search.run(&constellation_names, &star_props, &substance_assets),
+        "run the search",
+    );
+    for m in &result.matches {
+        println!("{}", m.seed);
+        for predicate in &m.matches {
+            let systems = predicate
+                .systems
+                .iter()
+                .map(|system| system.name.as_str())
+                .collect::<Vec<_>>();
+            println!("  {}: {}", predicate.predicate, systems.join(", "));
+        }
+    }
+    println!(
+        "{} of {} seeds matched in {:.0}ms",
+        result.matches.len(),
+        result.searched,
+        result.elapsed_ms
+    );
+
+    if let Some(output) = args.next() {
+        let json = or_exit(result.to_json(), "serialize the result");
+        or_exit(std::fs::write(&output, json), "save the result");
+        println!("Saved to {}", output);
+    }
+}
@@ -7,20 +7,25 @@ use bevy::{
     },
     render::{
         color::Color,
-        mesh::{shape::Circle, Mesh},
+        mesh::{shape::Circle, Indices, Mesh},
+        render_resource::PrimitiveTopology,
     },
     sprite::ColorMaterial,
     text::Font,
     utils::HashMap,
 };
 use serde::{Deserialize, Serialize};
+use std::{io::Error, path::Path};
 
 use crate::{
     consts,
     input::{camera::CameraController, KeyMapping},
     math::HexRgbaColor,
     sci::chemistry::{Substance, SubstanceProperty},
-    sim::components::{CelestialBodyId, PlanetType},
+    sim::{
+        components::{CelestialBodyId, PlanetType},
+        resources::{EjectionPolicy, SimulationScheduler, StellarClock},
+    },
     utils,
 };
 
@@ -93,6 +98,7 @@ impl FromWorld for FontAssets {
 #[derive(Resource)]
 pub struct MeshAssets {
     assets: HashMap<CelestialBodyId, Handle<Mesh>>,
+    rings: HashMap<CelestialBodyId, Handle<Mesh>>,
     config: MeshConfig,
 }
 impl_asset!(MeshAssets, Mesh);
@@ -102,6 +108,7 @@ impl FromWorld for MeshAssets {
         let config = world.resource::<GlobalConfig>();
         Self {
             assets: HashMap::default(),
+            rings: HashMap::default(),
             config: config.mesh_config.clone(),
         }
     }
@@ -131,11 +138,91 @@ impl MeshAssets {
         self.insert(id, handle.clone());
         handle
     }
+
+    /// Generates the mesh of the rings around the body `id`.
+    pub fn generate_ring(
+        &mut self,
+        assets: &mut Assets<Mesh>,
+        id: CelestialBodyId,
+        inner_radius_px: f64,
+        outer_radius_px: f64,
+    ) -> Handle<Mesh> {
+        let handle = assets.add(
+            Annulus {
+                inner_radius: inner_radius_px as f32,
+                outer_radius: outer_radius_px as f32,
+                vertices: self.config.segments
+                    * (outer_radius_px / self.config.base_radius).ceil() as usize,
+            }
+            .into(),
+        );
+        self.rings.insert(id, handle.clone());
+        handle
+    }
+
+    #[inline]
+    pub fn get_ring(&self, id: CelestialBodyId) -> Option<Handle<Mesh>> {
+        self.rings.get(&id).map(|h| h.clone_weak())
+    }
+
+    /// Drops the meshes of the body `id` and its rings, so they are freed once no entity
+    /// uses them.
+    #[inline]
+    pub fn remove(&mut self, id: CelestialBodyId) {
+        self.assets.remove(&id);
+        self.rings.remove(&id);
+    }
+}
+
+/// A flat ring between two circles.
+#[derive(Clone, Copy)]
+pub struct Annulus {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    /// Number of vertices on each circle.
+    pub vertices: usize,
+}
+
+impl From<Annulus> for Mesh {
+    fn from(annulus: Annulus) -> Self {
+        let Annulus {
+            inner_radius,
+            outer_radius,
+            vertices,
+        } = annulus;
+        let vertices = vertices.max(3);
+
+        let mut positions = Vec::with_capacity(vertices * 2);
+        let mut uvs = Vec::with_capacity(vertices * 2);
+        let mut indices = Vec::with_capacity(vertices * 6);
+        for i in 0..vertices {
+            let (sin, cos) = (i as f32 / vertices as f32 * std::f32::consts::TAU).sin_cos();
+            for radius in [inner_radius, outer_radius] {
+                positions.push([cos * radius, sin * radius, 0.]);
+                uvs.push([
+                    0.5 + cos * radius / outer_radius * 0.5,
+                    0.5 - sin * radius / outer_radius * 0.5,
+                ]);
+            }
+
+            let (inner, outer) = (i as u32 * 2, i as u32 * 2 + 1);
+            let next = (i as u32 + 1) % vertices as u32 * 2;
+            indices.extend([inner, outer, next + 1, inner, next + 1, next]);
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; vertices * 2]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
 }
 
 #[derive(Resource, Default)]
 pub struct MaterialAssets {
     assets: HashMap<CelestialBodyId, Handle<ColorMaterial>>,
+    rings: HashMap<CelestialBodyId, Handle<ColorMaterial>>,
 }
 impl_asset!(MaterialAssets, ColorMaterial);
 
@@ -153,6 +240,34 @@ impl MaterialAssets {
         self.insert(id, handle.clone());
         handle
     }
+
+    /// Generates the material of the rings around the body `id`.
+    pub fn generate_ring(
+        &mut self,
+        assets: &mut Assets<ColorMaterial>,
+        id: CelestialBodyId,
+        color: Color,
+    ) -> Handle<ColorMaterial> {
+        let handle = assets.add(ColorMaterial {
+            color,
+            ..Default::default()
+        });
+        self.rings.insert(id, handle.clone());
+        handle
+    }
+
+    #[inline]
+    pub fn get_ring(&self, id: CelestialBodyId) -> Option<Handle<ColorMaterial>> {
+        self.rings.get(&id).map(|h| h.clone_weak())
+    }
+
+    /// Drops the materials of the body `id` and its rings, so they are freed once no
+    /// entity uses them.
+    #[inline]
+    pub fn remove(&mut self, id: CelestialBodyId) {
+        self.assets.remove(&id);
+        self.rings.remove(&id);
+    }
 }
 
 #[derive(Resource, Serialize, Deserialize)]
@@ -160,6 +275,9 @@ pub struct GlobalConfig {
     pub key_mapping: KeyMapping,
     pub camera_controller: CameraController,
     pub mesh_config: MeshConfig,
+    pub sim_scheduler: SimulationScheduler,
+    pub ejection_policy: EjectionPolicy,
+    pub stellar_clock: StellarClock,
 }
 
 impl Default for GlobalConfig {
@@ -175,13 +293,15 @@ pub struct SubstanceAssets {
 
 impl Default for SubstanceAssets {
     fn default() -> Self {
-        Self {
-            assets: utils::deser(consts::SUBSTANCE_ASSETS).unwrap(),
-        }
+        Self::load(consts::SUBSTANCE_ASSETS).unwrap()
     }
 }
 
 impl SubstanceAssets {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        utils::deser(path).map(|assets| Self { assets })
+    }
+
     #[inline]
     pub fn get(&self, substance: Substance) -> &SubstanceProperty {
         &self.assets[substance as usize]
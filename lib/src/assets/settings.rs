@@ -1,14 +1,15 @@
 use bevy::{ecs::system::Resource, utils::HashMap};
 use serde::Deserialize;
+use std::{io::Error, path::Path};
 
 use crate::{
     consts,
     math::{self, HexRgbaColor, Unit, UnitClass},
-    sim::components::StarClass,
+    sim::components::{LuminosityClass, StarClass},
     utils,
 };
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct PackedStarInfo {
     pub class: StarClass,
     pub mass: f64,
@@ -31,36 +32,87 @@ impl PackedStarInfo {
     }
 }
 
+/// Tables of star properties, one per luminosity class and one of brown dwarfs. Each
+/// lists the tabulated classes in order, from the hottest down.
 #[derive(Resource)]
-pub struct StarProperties(Vec<PackedStarInfo>);
+pub struct StarProperties {
+    tables: HashMap<LuminosityClass, Vec<PackedStarInfo>>,
+    brown_dwarfs: Vec<PackedStarInfo>,
+}
 
 impl Default for StarProperties {
     fn default() -> Self {
-        Self(utils::deser(consts::STAR_PROPERTIES).unwrap())
+        Self::load(consts::STAR_PROPERTIES).unwrap()
     }
 }
 
 impl StarProperties {
+    pub fn new(rows: Vec<PackedStarInfo>) -> Self {
+        let mut tables = HashMap::<_, Vec<_>>::new();
+        let mut brown_dwarfs = Vec::new();
+        for info in rows {
+            if info.class.ty.is_substellar() {
+                brown_dwarfs.push(info);
+            } else {
+                tables.entry(info.class.lum).or_default().push(info);
+            }
+        }
+        for table in tables.values_mut().chain([&mut brown_dwarfs]) {
+            table.sort_by_key(|info| info.class);
+        }
+        Self {
+            tables,
+            brown_dwarfs,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        utils::deser(path).map(Self::new)
+    }
+
+    /// The rows of `lum`, which are empty if none are tabulated.
+    #[inline]
+    pub fn table(&self, lum: LuminosityClass) -> &[PackedStarInfo] {
+        self.tables.get(&lum).map_or(&[], |table| table.as_slice())
+    }
+
+    /// The rows of L, T and Y dwarfs, which aren't part of the main sequence.
     #[inline]
-    pub fn get(&self, class: StarClass) -> &PackedStarInfo {
-        self.0.get(class.to_index()).unwrap()
+    pub fn brown_dwarfs(&self) -> &[PackedStarInfo] {
+        &self.brown_dwarfs
     }
 
+    /// The row of `class`, if it is tabulated.
+    #[inline]
+    pub fn get(&self, class: StarClass) -> Option<&PackedStarInfo> {
+        let table = match class.ty.is_substellar() {
+            true => self.brown_dwarfs(),
+            false => self.table(class.lum),
+        };
+        table
+            .binary_search_by(|info| info.class.cmp(&class))
+            .ok()
+            .map(|index| &table[index])
+    }
+
+    /// Row `index` of the main sequence.
     #[inline]
     pub fn get_at(&self, index: usize) -> &PackedStarInfo {
-        self.0.get(index).unwrap()
+        self.table(LuminosityClass::Dwarf).get(index).unwrap()
     }
 
+    /// Main-sequence rows around `value` of `key`.
     #[inline]
     pub fn find_bound(
         &self,
         value: f64,
         key: impl Fn(&PackedStarInfo) -> f64,
     ) -> (&PackedStarInfo, &PackedStarInfo) {
-        for i in 0..self.0.len() - 1 {
-            let min = self.0.get(i).unwrap();
+        let table = self.table(LuminosityClass::Dwarf);
+        for i in 0..table.len() - 1 {
+            let min = table.get(i).unwrap();
             if key(min) <= value {
-                let max = self.0.get(i - 1).unwrap();
+                let max = table.get(i - 1).unwrap();
                 return (min, max);
             }
         }
@@ -68,22 +120,78 @@ impl StarProperties {
         panic!("Invalid value: {}", value)
     }
 
+    /// Properties of `lum` interpolated at `value` of `key`, which decreases down the
+    /// table. Values past either end of the table get the row at that end.
+    pub fn interpolate(
+        &self,
+        lum: LuminosityClass,
+        value: f64,
+        key: impl Fn(&PackedStarInfo) -> f64,
+    ) -> PackedStarInfo {
+        interpolate(self.table(lum), value, key)
+    }
+
+    /// Properties of `lum` `position` of the way down its table, between 0 and 1.
+    #[inline]
+    pub fn along(&self, lum: LuminosityClass, position: f64) -> PackedStarInfo {
+        along(self.table(lum), position)
+    }
+
+    /// Properties of a brown dwarf `position` of the way down their table, between 0
+    /// and 1.
+    #[inline]
+    pub fn brown_dwarf_along(&self, position: f64) -> PackedStarInfo {
+        along(self.brown_dwarfs(), position)
+    }
+
+    /// The main sequence.
     #[inline]
-    pub fn raw(&self) -> &Vec<PackedStarInfo> {
-        &self.0
+    pub fn raw(&self) -> &[PackedStarInfo] {
+        self.table(LuminosityClass::Dwarf)
     }
 }
 
+fn interpolate(
+    table: &[PackedStarInfo],
+    value: f64,
+    key: impl Fn(&PackedStarInfo) -> f64,
+) -> PackedStarInfo {
+    let (first, last) = (&table[0], table.last().unwrap());
+    if value >= key(first) {
+        return first.clone();
+    }
+    if value <= key(last) {
+        return last.clone();
+    }
+
+    let index = table.iter().position(|info| key(info) <= value).unwrap();
+    let (floor, ceil) = (&table[index], &table[index - 1]);
+    floor.lerp(ceil, (value - key(floor)) / (key(ceil) - key(floor)))
+}
+
+fn along(table: &[PackedStarInfo], position: f64) -> PackedStarInfo {
+    let x = position.clamp(0., 1.) * (table.len() - 1) as f64;
+    let index = x.ceil() as usize;
+    if index == 0 {
+        return table[0].clone();
+    }
+    table[index].lerp(&table[index - 1], index as f64 - x)
+}
+
 #[derive(Resource)]
 pub struct ConstellationNames(Vec<String>);
 
 impl Default for ConstellationNames {
     fn default() -> Self {
-        Self(utils::deser(consts::STAR_NAMES).unwrap())
+        Self::load(consts::STAR_NAMES).unwrap()
     }
 }
 
 impl ConstellationNames {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        utils::deser(path).map(Self)
+    }
+
     #[inline]
     pub fn get(&self, index: usize) -> &str {
         &self.0[index]
@@ -94,6 +202,11 @@ impl ConstellationNames {
         self.0[index].clone()
     }
 
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|name| name.as_str())
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.0.len()
@@ -118,6 +231,8 @@ impl UnitsInfo {
 
 #[cfg(test)]
 mod test {
+    use crate::sim::components::SpectralType;
+
     use super::*;
 
     #[test]
@@ -125,9 +240,84 @@ mod test {
         let props =
             utils::deser::<Vec<PackedStarInfo>>("../cosmos/assets/config/star_properties.json")
                 .unwrap();
-        let props = StarProperties(props);
+        let props = StarProperties::new(props);
         let (min, max) = props.find_bound(2.5, |info| info.mass);
         assert_eq!(min.mass, 2.18);
         assert_eq!(max.mass, 2.75);
+
+        let dwarf = LuminosityClass::Dwarf;
+        let info = props.interpolate(dwarf, 2.5, |info| info.mass);
+        assert!((info.mass - 2.5).abs() < 1e-9 && info.class == min.class);
+        assert!(
+            props
+                .interpolate(dwarf, 1e6, |info| info.effective_temp)
+                .class
+                == props.get_at(0).class
+        );
+        assert_eq!(props.interpolate(dwarf, 0., |info| info.mass).mass, 0.079);
+    }
+
+    #[test]
+    fn test_luminosity_classes() {
+        let props = StarProperties::load("../cosmos/assets/config/star_properties.json").unwrap();
+        let class = |ty, sub_ty, lum| StarClass { ty, sub_ty, lum };
+
+        // Indices point into the main sequence for every class.
+        for (index, info) in props.raw().iter().enumerate() {
+            assert_eq!(info.class.to_index(), index);
+            assert!(props.get(info.class).unwrap().mass == info.mass);
+        }
+        let sun = class(SpectralType::G, 2, LuminosityClass::Dwarf);
+        let sun_giant = StarClass {
+            lum: LuminosityClass::Giant,
+            ..sun
+        };
+        assert_eq!(sun_giant.to_index(), sun.to_index());
+        assert!(sun_giant < sun && sun < class(SpectralType::G, 3, LuminosityClass::Giant));
+        assert_eq!(sun_giant.to_string(), "G2III");
+
+        let giant = props
+            .get(class(SpectralType::K, 0, LuminosityClass::Giant))
+            .unwrap();
+        let dwarf = props
+            .get(class(SpectralType::K, 0, LuminosityClass::Dwarf))
+            .unwrap();
+        assert!(giant.radius > 10. * dwarf.radius && giant.luminosity > 100. * dwarf.luminosity);
+        assert!(props.get(sun_giant).is_none());
+
+        for lum in [
+            LuminosityClass::Supergiant,
+            LuminosityClass::BrightGiant,
+            LuminosityClass::Giant,
+            LuminosityClass::Subgiant,
+            LuminosityClass::WhiteDwarf,
+        ] {
+            let table = props.table(lum);
+            assert!(table.len() > 5 && table.iter().all(|info| info.class.lum == lum));
+            assert!(table
+                .windows(2)
+                .all(|w| w[0].effective_temp > w[1].effective_temp));
+            assert!(props.along(lum, 0.).class == table[0].class);
+            assert!(props.along(lum, 1.).class == table.last().unwrap().class);
+        }
+        assert!(props
+            .table(LuminosityClass::WhiteDwarf)
+            .iter()
+            .all(|info| info.radius < 0.02));
+
+        // Brown dwarfs stay out of the main sequence.
+        let brown_dwarfs = props.brown_dwarfs();
+        assert!(brown_dwarfs.len() > 5);
+        assert!(brown_dwarfs
+            .iter()
+            .all(|info| info.class.ty.is_substellar()
+                && info.mass < props.raw().last().unwrap().mass));
+        assert!(props
+            .raw()
+            .iter()
+            .all(|info| !info.class.ty.is_substellar()));
+        let l0 = class(SpectralType::L, 0, LuminosityClass::Dwarf);
+        assert!(props.get(l0).unwrap().class == l0);
+        assert!(props.brown_dwarf_along(0.).class == l0);
     }
 }
@@ -7,6 +7,8 @@ use crate::sci::chemistry::Substance;
 pub mod greek_alphabets;
 
 pub const GLOBAL_CONFIG: &str = "cosmos/assets/config/global_config.json";
+pub const GALAXY_PRESETS: &str = "cosmos/assets/config/galaxy_presets.json";
+pub const SEED_SEARCH: &str = "cosmos/assets/config/seed_search.json";
 pub const STAR_PROPERTIES: &str = "cosmos/assets/config/star_properties.json";
 pub const SUBSTANCE_ASSETS: &str = "cosmos/assets/config/substance_properties.json";
 pub const SUBSTANCE_VISUAL_ASSETS: &str = "cosmos/assets/config/substance_visuals.json";
@@ -20,8 +22,12 @@ pub const DEFAULT_BODY_VEL_DIR: DVec2 = DVec2::X;
 pub const SUN_MASS: f64 = 1.989e30;
 pub const SUN_RADIUS: f64 = 6.96342e8;
 pub const SUN_LUMINOSITY: f64 = 3.828e26;
+pub const SUN_EFFECTIVE_TEMP: f64 = 5772.;
+/// In years.
+pub const SUN_MAIN_SEQUENCE_LIFETIME: f64 = 1e10;
 
 pub const LY: f64 = 9.4605284e15;
+pub const AU: f64 = 1.495978707e11;
 
 pub const EARTH_MASS: f64 = 5.972e24;
 pub const EARTH_RADIUS: f64 = 6.371e6;
@@ -43,10 +49,14 @@ pub const MIN_MOON_DIST_TO_PLANET_COEFF: f64 = 1.2;
 pub const MAX_MOON_DIST_TO_PLANET_COEFF: f64 = 10.;
 
 pub const CELESTIAL_SIM_STEP: f64 = 0.01;
+/// Simulation steps between two scans for escaping bodies, as every scan finds the
+/// dominant attractor of every body.
+pub const ESCAPE_CHECK_INTERVAL: u64 = 50;
 
 pub const SQART_2_PI: f64 = 2.5066282746310005024157652848110452530069867406099383166299235763;
 
 pub const G: f64 = 6.67430e-11;
+pub const SPEED_OF_LIGHT: f64 = 299792458.;
 pub const STEFAN_BOLTZMANN: f64 = 5.670374419e-8;
 pub const IDEAL_GAS_CONST: f64 = 8.31446261815324;
 
@@ -64,6 +74,16 @@ pub const MOON_MASS_SCALE: f64 = 1e-15;
 pub const STAR_RADIUS_SCALE: f64 = 1e-5;
 pub const PLANET_RADIUS_SCALE: f64 = 2e-11;
 pub const MOON_RADIUS_SCALE: f64 = 1e-4;
+/// Smallest radius compact objects are drawn with, in solar radii, as most are far too
+/// small to see.
+pub const COMPACT_OBJECT_MIN_RADIUS: f64 = 0.05;
+/// Extent of the accretion disks of black holes, in drawn radii of the hole.
+pub const ACCRETION_DISK_RADII: Range<f64> = 1.5..4.;
+/// Radius of the coma of an active comet, in radii of the comet.
+pub const COMET_COMA_RADIUS_COEFF: f64 = 100.;
+/// Length of the tail of a comet at its sublimation point, in radii of its coma. It grows
+/// with the square of the surface temperature over the sublimation point.
+pub const COMET_TAIL_LENGTH_COEFF: f64 = 20.;
 
 pub const PLANET_EFFCETIVE_TEMP_SCALE: f64 = 0.2;
 
@@ -95,6 +115,20 @@ pub const ROCKY_PLANET_CRUST: [(Substance, Range<f64>); 7] = [
     (Substance::AmorphousIce, 0f64..0.2),
     (Substance::SulfuricAcid, 0f64..0.3),
 ];
+pub const ICY_BODY_CRUST: [(Substance, Range<f64>); 6] = [
+    (Substance::Water, 0.3..0.8),
+    (Substance::AmorphousIce, 0f64..0.4),
+    (Substance::Methane, 0f64..0.2),
+    (Substance::Ammonia, 0f64..0.2),
+    (Substance::SiliconDioxide, 0f64..0.3),
+    (Substance::Coal, 0f64..0.1),
+];
+pub const COMET_ICES: [(Substance, Range<f64>); 4] = [
+    (Substance::Water, 0.4..0.9),
+    (Substance::AmorphousIce, 0f64..0.4),
+    (Substance::Methane, 0f64..0.2),
+    (Substance::Ammonia, 0f64..0.1),
+];
 pub const ROCKY_PLANET_ATMO: [(Substance, Range<f64>); 6] = [
     (Substance::Nitrogen, 0.1..0.9),
     (Substance::Oxygen, 0f64..0.4),
@@ -103,5 +137,3 @@ pub const ROCKY_PLANET_ATMO: [(Substance, Range<f64>); 6] = [
     (Substance::SulfurDioxide, 0f64..0.5),
     (Substance::Phosphine, 0f64..0.1),
 ];
-
-pub const PRE_SIM_STEPS: usize = 10000;
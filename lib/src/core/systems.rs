@@ -3,8 +3,12 @@ use bevy::{
     ecs::system::{Commands, Res},
 };
 
-use crate::assets::GlobalConfig;
+use crate::{assets::GlobalConfig, gen::stream::StreamingAnchor};
 
 pub fn init(mut commands: Commands, config: Res<GlobalConfig>) {
-    commands.spawn((Camera2dBundle::default(), config.camera_controller.clone()));
+    commands.spawn((
+        Camera2dBundle::default(),
+        config.camera_controller.clone(),
+        StreamingAnchor,
+    ));
 }
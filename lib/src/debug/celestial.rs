@@ -1,13 +1,19 @@
 use bevy::{
     asset::Assets,
     ecs::{
+        entity::Entity,
+        event::EventReader,
+        query::With,
         reflect::ReflectResource,
-        system::{Commands, Res, ResMut, Resource},
+        system::{Commands, Query, Res, ResMut, Resource},
     },
-    math::DVec2,
+    hierarchy::{BuildChildren, DespawnRecursiveExt},
+    log::error,
     reflect::Reflect,
     render::mesh::Mesh,
-    sprite::ColorMaterial,
+    sprite::{ColorMaterial, MaterialMesh2dBundle},
+    transform::components::Transform,
+    utils::HashSet,
 };
 
 use crate::{
@@ -15,9 +21,15 @@ use crate::{
         settings::{ConstellationNames, StarProperties},
         MaterialAssets, MeshAssets, SubstanceAssets,
     },
-    gen::{GalaxyGenerator, GalaxyGeneratorConfig},
+    gen::{
+        stream::StreamingAnchor, GalaxyGenerator, GalaxyGeneratorConfig, GalaxyLayout,
+        RegenerateSystem,
+    },
     sim::{
-        bundles::CelestialBodyBundle, components::CelestialBodyId, resources::{CelestialBody, Galaxy}
+        bundles::CelestialBodyBundle,
+        components::CelestialBodyId,
+        resources::{CelestialBody, Galaxy, OrbitPredictor, ViewPlane},
+        SimVec,
     },
 };
 
@@ -30,7 +42,7 @@ pub struct BodyGenerator {
 impl Default for BodyGenerator {
     fn default() -> Self {
         Self {
-            body: CelestialBody::new(DVec2::ZERO, 0., 0., DVec2::ZERO),
+            body: CelestialBody::new(SimVec::ZERO, 0., 0., SimVec::ZERO),
         }
     }
 }
@@ -45,7 +57,10 @@ pub fn generate_galaxy(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    let config = GalaxyGeneratorConfig::new_debug();
+    let config = GalaxyGeneratorConfig::preset("debug").unwrap_or_else(|err| {
+        error!("{}", err);
+        GalaxyGeneratorConfig::new_debug()
+    });
     let mut generator = GalaxyGenerator::new(
         config,
         &constellation_names,
@@ -56,38 +71,241 @@ pub fn generate_galaxy(
         &mut meshes,
         &mut materials,
     );
-    generator.generate();
+    let report = generator.generate();
     let mut galaxy = Galaxy::default();
     let mut bundles = Vec::new();
-    generator.transfer_result(&mut galaxy, &mut bundles);
+    let layout = generator.transfer_result(&mut galaxy, &mut bundles);
     commands.insert_resource(galaxy);
-    bundles.into_iter().for_each(|(cb, mb)| {
-        let mut commands = match cb {
-            CelestialBodyBundle::Star(b) => commands.spawn(b),
-            CelestialBodyBundle::Planet {
-                planet,
-                crust,
-                atmo,
-            } => {
-                let mut entity = commands.spawn(planet.clone());
-                if let Some(crust) = crust {
-                    entity.insert(crust);
-                }
-                if let Some(atmo) = atmo {
-                    entity.insert(atmo);
-                }
-                entity
+    commands.insert_resource(layout);
+    commands.insert_resource(report);
+    bundles
+        .into_iter()
+        .for_each(|(cb, mb)| spawn_body(&mut commands, cb, mb));
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn regenerate_system(
+    mut commands: Commands,
+    mut events: EventReader<RegenerateSystem>,
+    mut galaxy: ResMut<Galaxy>,
+    mut layout: ResMut<GalaxyLayout>,
+    mut predictor: ResMut<OrbitPredictor>,
+    bodies: Query<(Entity, &CelestialBodyId)>,
+    properties: Res<StarProperties>,
+    constellation_names: Res<ConstellationNames>,
+    substance_assets: Res<SubstanceAssets>,
+    mut mesh_assets: ResMut<MeshAssets>,
+    mut material_assets: ResMut<MaterialAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if events.is_empty() {
+        return;
+    }
+
+    let mut generator = GalaxyGenerator::new(
+        layout.config.clone(),
+        &constellation_names,
+        &properties,
+        &substance_assets,
+        &mut mesh_assets,
+        &mut material_assets,
+        &mut meshes,
+        &mut materials,
+    );
+    for event in events.read() {
+        match generator.regenerate_system(&mut galaxy, &mut layout, event.index, &event.overrides) {
+            Ok(system) => {
+                bodies
+                    .iter()
+                    .filter(|&(_, id)| system.removed.contains(id))
+                    .for_each(|(entity, _)| commands.entity(entity).despawn_recursive());
+                system
+                    .bundles
+                    .into_iter()
+                    .for_each(|(cb, mb)| spawn_body(&mut commands, cb, mb));
+            }
+            Err(err) => error!("System regeneration failed: {}", err),
+        }
+    }
+
+    let iterations = predictor.iterations();
+    predictor.update_state(iterations, &galaxy);
+}
+
+/// Loads the systems near a [`StreamingAnchor`] and unloads the ones far from all.
+#[allow(clippy::too_many_arguments)]
+pub fn stream_systems(
+    mut commands: Commands,
+    mut galaxy: ResMut<Galaxy>,
+    mut layout: ResMut<GalaxyLayout>,
+    mut predictor: ResMut<OrbitPredictor>,
+    view: Res<ViewPlane>,
+    anchors: Query<&Transform, With<StreamingAnchor>>,
+    bodies: Query<(Entity, &CelestialBodyId)>,
+    properties: Res<StarProperties>,
+    constellation_names: Res<ConstellationNames>,
+    substance_assets: Res<SubstanceAssets>,
+    mut mesh_assets: ResMut<MeshAssets>,
+    mut material_assets: ResMut<MaterialAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let anchors = anchors
+        .iter()
+        .map(|transform| transform.translation.truncate().as_dvec2())
+        .collect::<Vec<_>>();
+    let plan = layout.plan_streaming(&galaxy, &view, &anchors);
+    if plan.load.is_empty() && plan.unload.is_empty() {
+        return;
+    }
+
+    let removed = plan
+        .unload
+        .iter()
+        .flat_map(|&index| layout.unload_system(&mut galaxy, index))
+        .collect::<HashSet<_>>();
+    for &id in &removed {
+        mesh_assets.remove(id);
+        material_assets.remove(id);
+    }
+    bodies
+        .iter()
+        .filter(|&(_, id)| removed.contains(id))
+        .for_each(|(entity, _)| commands.entity(entity).despawn_recursive());
+
+    let mut generator = GalaxyGenerator::new(
+        layout.config.clone(),
+        &constellation_names,
+        &properties,
+        &substance_assets,
+        &mut mesh_assets,
+        &mut material_assets,
+        &mut meshes,
+        &mut materials,
+    );
+    for index in plan.load {
+        generator
+            .load_system(&mut galaxy, &mut layout, index)
+            .into_iter()
+            .for_each(|(cb, mb)| spawn_body(&mut commands, cb, mb));
+    }
+
+    let iterations = predictor.iterations();
+    predictor.update_state(iterations, &galaxy);
+}
+
+/// Sends an interstellar object through the galaxy whenever one is due.
+#[allow(clippy::too_many_arguments)]
+pub fn inject_interstellar_objects(
+    mut commands: Commands,
+    mut galaxy: ResMut<Galaxy>,
+    mut layout: ResMut<GalaxyLayout>,
+    mut predictor: ResMut<OrbitPredictor>,
+    properties: Res<StarProperties>,
+    constellation_names: Res<ConstellationNames>,
+    substance_assets: Res<SubstanceAssets>,
+    mut mesh_assets: ResMut<MeshAssets>,
+    mut material_assets: ResMut<MaterialAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !layout.interstellar_due(galaxy.steps()) {
+        return;
+    }
+
+    let mut generator = GalaxyGenerator::new(
+        layout.config.clone(),
+        &constellation_names,
+        &properties,
+        &substance_assets,
+        &mut mesh_assets,
+        &mut material_assets,
+        &mut meshes,
+        &mut materials,
+    );
+    if let Some((cb, mb)) = generator.inject_interstellar(&mut galaxy, &mut layout) {
+        spawn_body(&mut commands, cb, mb);
+        let iterations = predictor.iterations();
+        predictor.update_state(iterations, &galaxy);
+    }
+}
+
+fn spawn_body(
+    commands: &mut Commands,
+    cb: CelestialBodyBundle,
+    mb: MaterialMesh2dBundle<ColorMaterial>,
+) {
+    let mut commands = match cb {
+        CelestialBodyBundle::Star(b) => commands.spawn(b),
+        CelestialBodyBundle::BrownDwarf(b) => commands.spawn(b),
+        CelestialBodyBundle::CompactObject { object, disk } => {
+            let mut entity = commands.spawn(object);
+            if let Some(disk) = disk {
+                entity.with_children(|parent| {
+                    parent.spawn(*disk);
+                });
             }
-            CelestialBodyBundle::Moon { moon, crust, atmo } => {
-                let mut entity = commands.spawn((moon, crust));
-                if let Some(atmo) = atmo {
-                    entity.insert(atmo);
-                }
-                entity
+            entity
+        }
+        CelestialBodyBundle::Planet {
+            planet,
+            crust,
+            atmo,
+            ring,
+            habitability,
+        } => {
+            let mut entity = commands.spawn(planet.clone());
+            if let Some(crust) = crust {
+                entity.insert(crust);
             }
-        };
-        commands.insert(mb);
-    });
+            if let Some(atmo) = atmo {
+                entity.insert(atmo);
+            }
+            if let Some(habitability) = habitability {
+                entity.insert(habitability);
+            }
+            if let Some(ring) = ring {
+                entity.with_children(|parent| {
+                    parent.spawn(*ring);
+                });
+            }
+            entity
+        }
+        CelestialBodyBundle::RoguePlanet {
+            planet,
+            crust,
+            atmo,
+            interstellar,
+        } => {
+            let mut entity = commands.spawn(planet);
+            if let Some(crust) = crust {
+                entity.insert(crust);
+            }
+            if let Some(atmo) = atmo {
+                entity.insert(atmo);
+            }
+            if let Some(interstellar) = interstellar {
+                entity.insert(interstellar);
+            }
+            entity
+        }
+        CelestialBodyBundle::Moon {
+            moon,
+            crust,
+            atmo,
+            habitability,
+        } => {
+            let mut entity = commands.spawn((moon, crust, habitability));
+            if let Some(atmo) = atmo {
+                entity.insert(atmo);
+            }
+            entity
+        }
+        CelestialBodyBundle::BeltBody { body, crust } => commands.spawn((body, crust)),
+        CelestialBodyBundle::Comet { comet, crust } => commands.spawn((comet, crust)),
+    };
+    commands.insert(mb);
 }
 
 // pub fn body_removal_test(mut galaxy: ResMut<Galaxy>) {
@@ -101,17 +319,22 @@ pub fn generate_galaxy(
 
 #[cfg(test)]
 mod test {
+    use rand::{rngs::StdRng, SeedableRng};
     use rand_distr::Distribution;
 
-    use crate::gen::distr::StarMassDistribution;
+    use crate::gen::distr::{Distributions, DistributionsConfig};
 
     #[test]
     fn test_mass_distr() {
+        let distrs = Distributions::new(&DistributionsConfig::default())
+            .ok()
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
         let mut records = vec![0; 120];
         for _ in 0..64 {
-            let mut rng = rand::thread_rng();
-            let mass = StarMassDistribution.sample(&mut rng);
-            records[mass as usize] += 1;
+            let mass = distrs.star_mass.sample(&mut rng);
+            let bucket = (mass as usize).min(records.len() - 1);
+            records[bucket] += 1;
         }
         println!("{:?}", records);
     }
@@ -14,6 +14,7 @@ use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
 use crate::{
     assets::FontAssets,
+    gen::RegenerateSystem,
     sim::resources::{Galaxy, OrbitPredictor},
 };
 
@@ -38,7 +39,16 @@ impl Plugin for CosmosDebugPlugin {
             .add_systems(Update, update_ui);
 
         if self.auto_generate {
-            app.add_systems(Startup, celestial::generate_galaxy);
+            app.add_event::<RegenerateSystem>()
+                .add_systems(Startup, celestial::generate_galaxy)
+                .add_systems(
+                    Update,
+                    (
+                        celestial::regenerate_system,
+                        celestial::stream_systems,
+                        celestial::inject_interstellar_objects,
+                    ),
+                );
             // app.add_systems(PostStartup, celestial::body_removal_test);
         } else {
             app.init_resource::<Galaxy>();
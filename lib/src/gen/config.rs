@@ -0,0 +1,519 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use rand::Rng;
+use rand_distr::{
+    uniform::{SampleUniform, Uniform},
+    Distribution,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{consts, sim::resources::GravityModel, utils};
+
+use super::{
+    distr,
+    pdf::{PdfDistribution, PdfError},
+    GalaxyGeneratorConfig, GalaxyMorphology, LuminosityClassPsb, PrimaryKindPsb,
+};
+
+/// Half-open range sampled uniformly, written as `[min, max]` in config files.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[T; 2]", into = "[T; 2]")]
+pub struct UniformRange<T: Copy> {
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: Copy> UniformRange<T> {
+    pub fn new(min: T, max: T) -> Self {
+        Self { min, max }
+    }
+}
+
+impl<T: Copy> From<[T; 2]> for UniformRange<T> {
+    fn from([min, max]: [T; 2]) -> Self {
+        Self { min, max }
+    }
+}
+
+impl<T: Copy> From<UniformRange<T>> for [T; 2] {
+    fn from(range: UniformRange<T>) -> Self {
+        [range.min, range.max]
+    }
+}
+
+impl<T: Copy + SampleUniform> Distribution<T> for UniformRange<T> {
+    /// Panics if the range is empty, which [`GalaxyGeneratorConfig::validate`] rules out.
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> T {
+        Uniform::new(self.min, self.max).sample(rng)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum FieldErrorKind {
+    EmptyRange,
+    NotPositive,
+    Negative,
+    OutOfBounds { min: f64, max: f64 },
+    InvalidPdf(PdfError),
+}
+
+/// A config field holding an invalid value, named by its path like `pln_cfg.num_coeff`.
+#[derive(Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub kind: FieldErrorKind,
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            FieldErrorKind::EmptyRange => write!(f, "`{}` is an empty range", self.field),
+            FieldErrorKind::NotPositive => write!(f, "`{}` must be positive", self.field),
+            FieldErrorKind::Negative => write!(f, "`{}` must not be negative", self.field),
+            FieldErrorKind::OutOfBounds { min, max } => {
+                write!(f, "`{}` must be within [{}, {}]", self.field, min, max)
+            }
+            FieldErrorKind::InvalidPdf(err) => write!(f, "`{}` is invalid: {}", self.field, err),
+        }
+    }
+}
+
+pub enum ConfigError {
+    Io(std::io::Error),
+    UnknownPreset(String),
+    Invalid(Vec<FieldError>),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "Failed to load config: {}", err),
+            ConfigError::UnknownPreset(name) => write!(f, "Unknown preset `{}`", name),
+            ConfigError::Invalid(errors) => {
+                write!(f, "Invalid config:")?;
+                errors.iter().try_for_each(|err| write!(f, "\n  {}", err))
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+/// An invalid distribution, as reported by [`Distributions::new`](distr::Distributions::new).
+impl From<(&str, PdfError)> for ConfigError {
+    fn from((name, err): (&str, PdfError)) -> Self {
+        ConfigError::Invalid(vec![FieldError {
+            field: format!("distrs.{}", name),
+            kind: FieldErrorKind::InvalidPdf(err),
+        }])
+    }
+}
+
+/// Named generator configs, stored as a JSON object of configs.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GalaxyPresets(BTreeMap<String, GalaxyGeneratorConfig>);
+
+impl GalaxyPresets {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Ok(utils::deser(path)?)
+    }
+
+    #[inline]
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(|name| name.as_str())
+    }
+
+    /// Returns a validated copy of the preset `name`.
+    pub fn get(&self, name: &str) -> Result<GalaxyGeneratorConfig, ConfigError> {
+        let config = self
+            .0
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownPreset(name.to_string()))?;
+        config.validate()?;
+        Ok(config.clone())
+    }
+}
+
+impl GalaxyGeneratorConfig {
+    /// Loads and validates a single config from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let config: Self = utils::deser(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads the preset `name` from [`consts::GALAXY_PRESETS`].
+    pub fn preset(name: &str) -> Result<Self, ConfigError> {
+        GalaxyPresets::load(consts::GALAXY_PRESETS)?.get(name)
+    }
+
+    /// Checks every field and reports all invalid ones at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut v = Validator::default();
+
+        v.positive("galaxy_radius", self.galaxy_radius);
+        v.positive("num_stars", self.num_stars as f64);
+        v.within("rev_revol_psb", self.rev_revol_psb, 0. ..=1.);
+        v.within("stylish", self.stylish, 0. ..=1.);
+
+        match self.morphology {
+            GalaxyMorphology::Disc => {}
+            GalaxyMorphology::Spiral {
+                num_arms,
+                pitch,
+                arm_width,
+            } => {
+                v.positive("morphology.num_arms", num_arms as f64);
+                v.within("morphology.pitch", pitch, 1e-3..=1.5);
+                v.positive("morphology.arm_width", arm_width);
+            }
+            GalaxyMorphology::BarredSpiral {
+                num_arms,
+                pitch,
+                arm_width,
+                bar_length,
+            } => {
+                v.positive("morphology.num_arms", num_arms as f64);
+                v.within("morphology.pitch", pitch, 1e-3..=1.5);
+                v.positive("morphology.arm_width", arm_width);
+                v.within("morphology.bar_length", bar_length, 1e-3..=1.);
+            }
+            GalaxyMorphology::Elliptical {
+                axis_ratio,
+                effective_radius,
+            } => {
+                v.within("morphology.axis_ratio", axis_ratio, 1e-3..=1.);
+                v.positive("morphology.effective_radius", effective_radius);
+            }
+            GalaxyMorphology::Irregular {
+                num_clumps,
+                clump_radius,
+            } => {
+                v.positive("morphology.num_clumps", num_clumps as f64);
+                v.within("morphology.clump_radius", clump_radius, 1e-3..=1.);
+            }
+        }
+
+        let star_cfg = &self.star_cfg;
+        v.range("star_cfg.ecc", star_cfg.ecc, 0. ..=1.);
+        v.positive("star_cfg.min_sep_coeff", star_cfg.min_sep_coeff);
+        v.within("star_cfg.p_type_psb", star_cfg.p_type_psb, 0. ..=1.);
+        v.non_negative("star_cfg.inclination_sigma", star_cfg.inclination_sigma);
+        v.range("star_cfg.age", star_cfg.age, 0. ..=1.);
+        v.psbs(
+            "star_cfg.lum_class_psb",
+            LuminosityClassPsb::FIELDS,
+            star_cfg.lum_class_psb.entries(),
+        );
+        v.psbs(
+            "star_cfg.kind_psb",
+            PrimaryKindPsb::FIELDS,
+            star_cfg.kind_psb.entries(),
+        );
+
+        let pln_cfg = &self.pln_cfg;
+        v.range("pln_cfg.num_coeff", pln_cfg.num_coeff, 0. ..=f64::INFINITY);
+        v.positive("pln_cfg.min_revl_spd", pln_cfg.min_revl_spd);
+        v.range(
+            "pln_cfg.sma_smi_ratio",
+            pln_cfg.sma_smi_ratio,
+            1. ..=f64::INFINITY,
+        );
+        v.non_negative("pln_cfg.inclination_sigma", pln_cfg.inclination_sigma);
+
+        let moon_cfg = &self.moon_cfg;
+        v.range(
+            "moon_cfg.num_coeff",
+            moon_cfg.num_coeff,
+            0. ..=f64::INFINITY,
+        );
+        v.range(
+            "moon_cfg.sma_smi_ratio",
+            moon_cfg.sma_smi_ratio,
+            1. ..=f64::INFINITY,
+        );
+        v.non_negative("moon_cfg.inclination_sigma", moon_cfg.inclination_sigma);
+
+        let ring_cfg = &self.ring_cfg;
+        v.within("ring_cfg.psb", ring_cfg.psb, 0. ..=1.);
+        v.range(
+            "ring_cfg.inner_coeff",
+            ring_cfg.inner_coeff,
+            1. ..=f64::INFINITY,
+        );
+        v.range(
+            "ring_cfg.outer_coeff",
+            ring_cfg.outer_coeff,
+            0. ..=f64::INFINITY,
+        );
+        v.range(
+            "ring_cfg.optical_depth",
+            ring_cfg.optical_depth,
+            0. ..=f64::INFINITY,
+        );
+
+        let belt_cfg = &self.belt_cfg;
+        v.within("belt_cfg.asteroid_psb", belt_cfg.asteroid_psb, 0. ..=1.);
+        v.within("belt_cfg.kuiper_psb", belt_cfg.kuiper_psb, 0. ..=1.);
+        v.positive("belt_cfg.num_bodies", belt_cfg.num_bodies as f64);
+        v.range("belt_cfg.mass", belt_cfg.mass, 0. ..=f64::INFINITY);
+        v.range("belt_cfg.width", belt_cfg.width, 0. ..=f64::INFINITY);
+        v.within(
+            "belt_cfg.kuiper_dist_coeff",
+            belt_cfg.kuiper_dist_coeff,
+            1. ..=f64::INFINITY,
+        );
+        v.range("belt_cfg.ecc", belt_cfg.ecc, 0. ..=1.);
+        v.non_negative("belt_cfg.inclination_sigma", belt_cfg.inclination_sigma);
+
+        let comet_cfg = &self.comet_cfg;
+        v.within("comet_cfg.psb", comet_cfg.psb, 0. ..=1.);
+        v.range("comet_cfg.mass", comet_cfg.mass, 0. ..=f64::INFINITY);
+        v.range(
+            "comet_cfg.aphelion_coeff",
+            comet_cfg.aphelion_coeff,
+            1. ..=f64::INFINITY,
+        );
+        // Comets stay bound to their host.
+        v.range("comet_cfg.ecc", comet_cfg.ecc, 0. ..=1.);
+        v.non_negative("comet_cfg.inclination_sigma", comet_cfg.inclination_sigma);
+        v.non_negative("comet_cfg.mass_loss_rate", comet_cfg.mass_loss_rate);
+
+        let rogue_cfg = &self.rogue_cfg;
+        v.within(
+            "rogue_cfg.per_system",
+            rogue_cfg.per_system,
+            0. ..=f64::INFINITY,
+        );
+        if let Some(interstellar) = rogue_cfg.interstellar {
+            v.positive(
+                "rogue_cfg.interstellar.interval",
+                interstellar.interval as f64,
+            );
+            v.range(
+                "rogue_cfg.interstellar.excess_spd",
+                interstellar.excess_spd,
+                0. ..=f64::INFINITY,
+            );
+            v.range(
+                "rogue_cfg.interstellar.impact",
+                interstellar.impact,
+                0. ..=1.,
+            );
+        }
+
+        let stability = &self.stability;
+        v.non_negative("stability.min_hill_spacing", stability.min_hill_spacing);
+        if let Some(megno) = stability.megno {
+            v.positive("stability.megno.num_orbits", megno.num_orbits);
+            v.positive(
+                "stability.megno.steps_per_orbit",
+                megno.steps_per_orbit as f64,
+            );
+            v.within(
+                "stability.megno.max_steps",
+                megno.max_steps as f64,
+                megno.steps_per_orbit as f64..=f64::INFINITY,
+            );
+            v.within("stability.megno.max", megno.max, 2. ..=f64::INFINITY);
+        }
+
+        if let GravityModel::PostNewtonian { speed_of_light } = self.gravity {
+            v.positive("gravity.speed_of_light", speed_of_light);
+        }
+
+        if let Some(streaming) = self.streaming {
+            v.positive("streaming.load_dist", streaming.load_dist);
+            v.within(
+                "streaming.unload_dist",
+                streaming.unload_dist,
+                streaming.load_dist..=f64::INFINITY,
+            );
+        }
+
+        if let Some(central_black_hole) = self.central_black_hole {
+            // Heavier ones pull on every system within half the galaxy radius harder than
+            // systems may pull on each other, which leaves no room to place them.
+            let max_mass = (self.galaxy_radius / 2.).powi(2) * consts::STAR_ACC_THRESHOLD
+                / (consts::G * consts::SUN_MASS * consts::STAR_MASS_SCALE);
+            v.positive("central_black_hole.mass", central_black_hole.mass);
+            v.within(
+                "central_black_hole.mass",
+                central_black_hole.mass,
+                0. ..=max_mass,
+            );
+        }
+
+        for (name, pdf) in self.distrs.iter() {
+            if let Err(err) = PdfDistribution::new(pdf) {
+                v.push(&format!("distrs.{}", name), FieldErrorKind::InvalidPdf(err));
+            }
+        }
+        let (min, max) = distr::STAR_MASS_BOUNDS;
+        v.range(
+            "distrs.star_mass.range",
+            self.distrs.star_mass.range,
+            min..=max,
+        );
+
+        v.finish()
+    }
+}
+
+#[derive(Default)]
+struct Validator(Vec<FieldError>);
+
+impl Validator {
+    fn push(&mut self, field: &str, kind: FieldErrorKind) {
+        self.0.push(FieldError {
+            field: field.to_string(),
+            kind,
+        });
+    }
+
+    // NaNs fail every check below.
+    fn positive(&mut self, field: &str, value: f64) {
+        if value <= 0. || value.is_nan() {
+            self.push(field, FieldErrorKind::NotPositive);
+        }
+    }
+
+    fn non_negative(&mut self, field: &str, value: f64) {
+        if value < 0. || value.is_nan() {
+            self.push(field, FieldErrorKind::Negative);
+        }
+    }
+
+    fn within(&mut self, field: &str, value: impl Into<f64>, bounds: RangeInclusive<f64>) {
+        if !bounds.contains(&value.into()) {
+            self.out_of_bounds(field, bounds);
+        }
+    }
+
+    /// Checks that the range is not empty and lies within `bounds`.
+    fn range<T: Copy + Into<f64>>(
+        &mut self,
+        field: &str,
+        range: UniformRange<T>,
+        bounds: RangeInclusive<f64>,
+    ) {
+        let (min, max) = (range.min.into(), range.max.into());
+        if min >= max || min.is_nan() || max.is_nan() {
+            self.push(field, FieldErrorKind::EmptyRange);
+        } else if !bounds.contains(&min) || !bounds.contains(&max) {
+            self.out_of_bounds(field, bounds);
+        }
+    }
+
+    /// Chances of exclusive outcomes, each and all together between 0 and 1.
+    fn psbs<T, const N: usize>(&mut self, field: &str, names: [&str; N], entries: [(T, f32); N]) {
+        for (name, &(_, psb)) in names.iter().zip(&entries) {
+            self.within(&format!("{}.{}", field, name), psb, 0. ..=1.);
+        }
+        let total = entries.iter().map(|&(_, psb)| psb).sum::<f32>();
+        self.within(field, total, 0. ..=1.);
+    }
+
+    fn out_of_bounds(&mut self, field: &str, bounds: RangeInclusive<f64>) {
+        self.push(
+            field,
+            FieldErrorKind::OutOfBounds {
+                min: *bounds.start(),
+                max: *bounds.end(),
+            },
+        );
+    }
+
+    fn finish(self) -> Result<(), ConfigError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(self.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gen::pdf::PdfShape;
+
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        let presets = GalaxyPresets::load(format!("../{}", consts::GALAXY_PRESETS))
+            .unwrap_or_else(|err| panic!("{}", err));
+        assert!(presets.names().any(|name| name == "debug"));
+        for name in presets.names() {
+            if let Err(err) = presets.get(name) {
+                panic!("{}: {}", name, err);
+            }
+        }
+        assert!(matches!(
+            presets.get("nonexistent"),
+            Err(ConfigError::UnknownPreset(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = GalaxyGeneratorConfig::new_debug();
+        assert!(config.validate().is_ok());
+
+        config.galaxy_radius = -1.;
+        config.pln_cfg.num_coeff = UniformRange::new(1.1, 0.6);
+        config.belt_cfg.num_bodies = 0;
+        config.star_cfg.ecc = UniformRange::new(0.5, 1.5);
+        config.distrs.moon_mass.pdf = PdfShape::Expr("x - 0.5".to_string());
+        let Err(ConfigError::Invalid(errors)) = config.validate() else {
+            panic!("invalid config passed validation");
+        };
+        let fields = errors
+            .iter()
+            .map(|err| (err.field.as_str(), err.kind.clone()))
+            .collect::<Vec<_>>();
+        assert!(
+            fields
+                == [
+                    ("galaxy_radius", FieldErrorKind::NotPositive),
+                    (
+                        "star_cfg.ecc",
+                        FieldErrorKind::OutOfBounds { min: 0., max: 1. }
+                    ),
+                    ("pln_cfg.num_coeff", FieldErrorKind::EmptyRange),
+                    ("belt_cfg.num_bodies", FieldErrorKind::NotPositive),
+                    (
+                        "distrs.moon_mass",
+                        FieldErrorKind::InvalidPdf(PdfError::Negative(0.))
+                    ),
+                ],
+            "{}",
+            ConfigError::Invalid(errors)
+        );
+    }
+
+    #[test]
+    fn test_config_without_comets() {
+        let mut value = serde_json::to_value(GalaxyGeneratorConfig::new_debug()).unwrap();
+        value.as_object_mut().unwrap().remove("comet_cfg");
+        let config: GalaxyGeneratorConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.comet_cfg.psb, 0.);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_uniform_range_serde() {
+        let range: UniformRange<f64> = serde_json::from_str("[0.5, 2.0]").unwrap();
+        assert!(range == UniformRange::new(0.5, 2.));
+        assert_eq!(serde_json::to_string(&range).unwrap(), "[0.5,2.0]");
+    }
+}
@@ -1,47 +1,22 @@
+use std::f64::consts::TAU;
+
 use bevy::{math::DVec2, utils::HashMap};
 use rand::Rng;
 use rand_distr::{Distribution, Normal, Uniform};
+use serde::{Deserialize, Serialize};
 
-use crate::{consts, sci::chemistry::SubstanceContent};
+use crate::{consts, sci::chemistry::SubstanceContent, sim::components::LuminosityClass};
 
-#[inline]
-pub fn univ_pdf() -> impl Fn(f64) -> f64 {
-    move |x| (1. / (500. * x)).min(1.)
-}
+use super::{
+    pdf::{PdfConfig, PdfDistribution, PdfError},
+    GalaxyMorphology, LuminosityClassPsb, PrimaryKind, PrimaryKindPsb,
+};
 
 #[inline]
 pub fn stellar_density_pdf(galaxy_radius: f64) -> impl Fn(f64) -> f64 {
     move |sqr_radius| 1. - (sqr_radius / galaxy_radius).powi(5)
 }
 
-#[inline]
-pub fn planet_smi_pdf() -> impl Fn(f64) -> f64 {
-    move |x| (x.powf(-x) - 1. / (100. * x)).powi(15)
-}
-
-#[inline]
-pub fn planet_mass_pdf() -> impl Fn(f64) -> f64 {
-    move |x| {
-        2. * (-(150. * x).powi(5) + 5.).max(0.)
-            + ((300. * x - 20.).tanh() - (3. * x - 1.).tanh()) * 0.2
-    }
-}
-
-#[inline]
-pub fn moon_mass_pdf() -> impl Fn(f64) -> f64 {
-    move |x| 26f64.powf(-50. * x) + 21f64.powf(-1.5 * (x + 1.))
-}
-
-#[inline]
-pub fn moon_smi_dist_incre_coeff_pdf() -> impl Fn(f64) -> f64 {
-    move |x| -(2. * (x - 0.5)).powi(2) + 1.
-}
-
-#[inline]
-pub fn rocky_body_atmo_density_pdf() -> impl Fn(f64) -> f64 {
-    move |x| (-x * x * x * x + 1.).powi(2)
-}
-
 #[inline]
 pub fn rocky_body_atmo_density_to_opacity(mut density: f64) -> f64 {
     density *= 1000.;
@@ -49,243 +24,362 @@ pub fn rocky_body_atmo_density_to_opacity(mut density: f64) -> f64 {
 }
 
 #[inline]
-pub fn max_num_planets(spectral_index: usize, distr: Uniform<f32>, rng: &mut impl Rng) -> usize {
+pub fn max_num_planets(
+    spectral_index: usize,
+    distr: impl Distribution<f32>,
+    rng: &mut impl Rng,
+) -> usize {
     let x = spectral_index as f32;
     let t = (8. / (1. + ((x - 33.) / 12.).exp()) + 3.).floor();
     (rng.sample(distr) * t) as usize
 }
 
 #[inline]
-pub fn max_num_moons(mass: f64, distr: Uniform<f32>, rng: &mut impl Rng) -> usize {
+pub fn max_num_moons(mass: f64, distr: impl Distribution<f32>, rng: &mut impl Rng) -> usize {
     let mass = (mass / consts::EARTH_MASS / consts::PLANET_MASS_SCALE) as f32;
     let num_coeff = rng.sample(distr);
     let ty_coeff = (0.5 * mass).sqrt() / 3. + 0.9;
     (num_coeff * ty_coeff).round() as usize
 }
 
-#[derive(Clone, Copy)]
-pub struct StarMassDistribution;
+/// The lightest and heaviest star masses `StarProperties::find_bound` can look up, in
+/// solar masses.
+pub const STAR_MASS_BOUNDS: (f64, f64) = (0.085, 120.);
 
-impl StarMassDistribution {
-    const MIN: f64 = 0.078;
-    const MAX: f64 = 120.;
-    const PDF_MAX: f64 = 4.;
-}
+macro_rules! distributions {
+    ($($(#[$meta:meta])* $field:ident: $default:expr,)*) => {
+        /// Densities of the randomly sampled properties of bodies.
+        #[derive(Clone, Serialize, Deserialize)]
+        #[serde(default)]
+        pub struct DistributionsConfig {
+            $($(#[$meta])* pub $field: PdfConfig,)*
+        }
 
-impl Distribution<f64> for StarMassDistribution {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x_distr = Uniform::new(0., 1.);
-        let y_distr = Uniform::new(0., Self::PDF_MAX);
+        impl Default for DistributionsConfig {
+            fn default() -> Self {
+                Self {
+                    $($field: {
+                        let (expr, min, max) = $default;
+                        PdfConfig::expr(expr, min, max)
+                    },)*
+                }
+            }
+        }
 
-        loop {
-            let x = rng.sample(x_distr);
-            let y = rng.sample(y_distr);
-            if y < univ_pdf()(x) {
-                return x * (Self::MAX - Self::MIN) + Self::MIN;
+        impl DistributionsConfig {
+            /// Every distribution with its field name.
+            pub fn iter(&self) -> impl Iterator<Item = (&'static str, &PdfConfig)> {
+                [$((stringify!($field), &self.$field),)*].into_iter()
             }
         }
-    }
+
+        /// Compiled [`DistributionsConfig`].
+        #[derive(Clone)]
+        pub struct Distributions {
+            $(pub $field: PdfDistribution,)*
+        }
+
+        impl Distributions {
+            /// Fails with the name of the first invalid distribution.
+            pub fn new(config: &DistributionsConfig) -> Result<Self, (&'static str, PdfError)> {
+                Ok(Self {
+                    $($field: PdfDistribution::new(&config.$field)
+                        .map_err(|err| (stringify!($field), err))?,)*
+                })
+            }
+        }
+    };
 }
 
-#[derive(Clone, Copy)]
+distributions! {
+    /// In solar masses.
+    star_mass: ("min(1 / (500 * x), 1)", STAR_MASS_BOUNDS.0, STAR_MASS_BOUNDS.1),
+    /// In Earth masses. The tail of the peak of light planets dips below zero.
+    planet_mass: (
+        "max(2 * max(5 - (150 * x)^5, 0) + (tanh(300 * x - 20) - tanh(3 * x - 1)) * 0.2, 0)",
+        0.02,
+        300.,
+    ),
+    /// Periapsis distance of a planet. The range is replaced by the bounds each planet
+    /// has to keep to.
+    planet_smi_dist: ("max(x^(-x) - 1 / (100 * x), 0)^15", 0., 1.),
+    /// In g/cm³, like the other densities.
+    giant_planet_density: ("exp(-8 * (x - 0.5)^2)", 0.3, 1.8),
+    rocky_crust_density: ("exp(-8 * (x - 0.5)^2)", 2.5, 5.5),
+    icy_crust_density: ("exp(-8 * (x - 0.5)^2)", 0.5, 2.),
+    rocky_atmo_density: ("(1 - x^4)^2", 0.2e-3, 2.5e-3),
+    /// In kilograms.
+    moon_mass: ("26^(-50 * x) + 21^(-1.5 * (x + 1))", 1e10, 1e20),
+    moon_density: ("exp(-8 * (x - 0.5)^2)", 1.5, 3.5),
+    /// Gap between neighbouring moons in planet radii.
+    moon_smi_dist_incre_coeff: ("1 - (2 * (x - 0.5))^2", 1.2, 2.),
+}
+
+/// Samples star positions within `galaxy_radius` of the galactic centre, following the
+/// stellar density of a [`GalaxyMorphology`].
+#[derive(Clone)]
 pub struct StarPosDistribution {
     galaxy_radius: f64,
+    morphology: GalaxyMorphology,
+    /// Centres and radii of the clumps of an irregular galaxy, relative to its radius.
+    clumps: Vec<(DVec2, f64)>,
 }
 
 impl StarPosDistribution {
     const PDF_MAX: f64 = 1.;
-
-    pub fn new(galaxy_radius: f64) -> Self {
-        Self { galaxy_radius }
+    /// Density between spiral arms relative to the arms.
+    const INTERARM_DENSITY: f64 = 0.1;
+    /// Radius at which logarithmic arms have turned by zero, relative to the galaxy radius.
+    const ARM_ORIGIN: f64 = 0.05;
+    /// Half thickness of a bar relative to its half length.
+    const BAR_THICKNESS: f64 = 0.15;
+    /// Constant of the de Vaucouleurs profile.
+    const DE_VAUCOULEURS_B: f64 = 7.669;
+
+    /// Irregular galaxies draw their clumps from `rng`.
+    pub fn new(galaxy_radius: f64, morphology: GalaxyMorphology, rng: &mut impl Rng) -> Self {
+        let clumps = match morphology {
+            GalaxyMorphology::Irregular {
+                num_clumps,
+                clump_radius,
+            } => (0..num_clumps)
+                .map(|_| {
+                    let dist = rng.gen_range(0f64..1.).sqrt() * (1. - clump_radius);
+                    let angle = rng.gen_range(0. ..TAU);
+                    let radius = clump_radius * rng.gen_range(0.5..1.5);
+                    (DVec2::from_angle(angle) * dist, radius)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self {
+            galaxy_radius,
+            morphology,
+            clumps,
+        }
     }
-}
 
-impl Distribution<DVec2> for StarPosDistribution {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> DVec2 {
-        let x_distr = Uniform::new(0., 1.);
-        let y_distr = Uniform::new(0., Self::PDF_MAX);
-
-        loop {
-            let x = rng.sample(x_distr);
-            let y = rng.sample(y_distr);
-            let sqr_radius = x * x + y * y;
-            if sqr_radius < 1. {
-                return DVec2::new(x, y) * self.galaxy_radius;
+    /// Stellar density at `pos`, relative to the galaxy radius, between 0 and `PDF_MAX`.
+    pub fn density(&self, pos: DVec2) -> f64 {
+        let sqr_radius = pos.length_squared();
+        if sqr_radius >= 1. {
+            return 0.;
+        }
+        let disc = stellar_density_pdf(1.)(sqr_radius);
+
+        match self.morphology {
+            GalaxyMorphology::Disc => disc,
+            GalaxyMorphology::Spiral {
+                num_arms,
+                pitch,
+                arm_width,
+            } => disc * Self::arm_density(pos, num_arms, pitch, arm_width, Self::ARM_ORIGIN),
+            GalaxyMorphology::BarredSpiral {
+                num_arms,
+                pitch,
+                arm_width,
+                bar_length,
+            } => {
+                let bar = if pos.x.abs() < bar_length {
+                    let thickness = bar_length * Self::BAR_THICKNESS;
+                    (-(pos.y / thickness).powi(2) / 2.).exp()
+                } else {
+                    0.
+                };
+                let arms = if pos.length() > bar_length {
+                    Self::arm_density(pos, num_arms, pitch, arm_width, bar_length)
+                } else {
+                    Self::INTERARM_DENSITY
+                };
+                disc * bar.max(arms)
+            }
+            GalaxyMorphology::Elliptical {
+                axis_ratio,
+                effective_radius,
+            } => {
+                let radius = DVec2::new(pos.x, pos.y / axis_ratio).length();
+                (-Self::DE_VAUCOULEURS_B * (radius / effective_radius).powf(0.25)).exp()
+            }
+            GalaxyMorphology::Irregular { .. } => {
+                let clumps = self
+                    .clumps
+                    .iter()
+                    .map(|(center, radius)| {
+                        (-pos.distance_squared(*center) / (2. * radius * radius)).exp()
+                    })
+                    .sum::<f64>();
+                disc * clumps.min(1.)
             }
         }
     }
-}
-
-#[derive(Clone, Copy)]
-pub struct PlanetMassDistribution;
 
-impl PlanetMassDistribution {
-    pub const MIN: f64 = 0.02;
-    pub const MAX: f64 = 300.;
-    pub const PDF_MAX: f64 = 9.952;
+    /// Density of logarithmic spiral arms that turn by zero at `origin`, with a floor of
+    /// `INTERARM_DENSITY`.
+    fn arm_density(pos: DVec2, num_arms: usize, pitch: f64, arm_width: f64, origin: f64) -> f64 {
+        let (radius, angle) = (pos.length(), pos.y.atan2(pos.x));
+        let arm_angle = (radius / origin).ln() / pitch.tan();
+        let spacing = TAU / num_arms.max(1) as f64;
+        // Angle to the nearest arm, in [-spacing / 2, spacing / 2).
+        let offset = (angle - arm_angle + spacing / 2.).rem_euclid(spacing) - spacing / 2.;
+        (-(offset / arm_width).powi(2) / 2.)
+            .exp()
+            .max(Self::INTERARM_DENSITY)
+    }
 }
 
-impl Distribution<f64> for PlanetMassDistribution {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x_distr = Uniform::new(0., 1.);
+impl Distribution<DVec2> for StarPosDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> DVec2 {
+        let x_distr = Uniform::new(-1., 1.);
         let y_distr = Uniform::new(0., Self::PDF_MAX);
 
         loop {
-            let x = rng.sample(x_distr);
-            let y = rng.sample(y_distr);
-            if y < planet_mass_pdf()(x) {
-                return x * (Self::MAX - Self::MIN) + Self::MIN;
+            let pos = DVec2::new(rng.sample(x_distr), rng.sample(x_distr));
+            if rng.sample(y_distr) < self.density(pos) {
+                return pos * self.galaxy_radius;
             }
         }
     }
 }
 
+/// Samples `(inclination, longitude of the ascending node)` of an orbit in radians.
+///
+/// Inclinations are half-normal around the reference plane.
 #[derive(Clone, Copy)]
-pub struct PlanetSmiDistDistribution {
-    pub min: f64,
-    pub max: f64,
+pub struct OrbitOrientationDistribution {
+    pub inclination_sigma: f64,
 }
 
-impl PlanetSmiDistDistribution {
-    pub const PDF_MAX: f64 = 0.0693;
+impl OrbitOrientationDistribution {
+    pub fn new(inclination_sigma: f64) -> Self {
+        Self { inclination_sigma }
+    }
 }
 
-impl Distribution<f64> for PlanetSmiDistDistribution {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x_distr = Uniform::new(0., 1.);
-        let y_distr = Uniform::new(0., Self::PDF_MAX);
+impl Distribution<(f64, f64)> for OrbitOrientationDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> (f64, f64) {
+        let inclination = rng
+            .sample(Normal::<f64>::new(0., self.inclination_sigma).unwrap())
+            .abs()
+            .min(std::f64::consts::PI);
+        let node = rng.gen_range(0f64..std::f64::consts::TAU);
+        (inclination, node)
+    }
+}
 
-        loop {
-            let x = rng.sample(x_distr);
-            let y = rng.sample(y_distr);
-            if y < planet_smi_pdf()(x) {
-                return x * (self.max - self.min) + self.min;
-            }
+/// Fraction of systems with at least one companion, by primary mass in solar masses.
+///
+/// Roughly follows the surveys summarized by Duchêne & Kraus (2013).
+const MULTIPLICITY_FRACTIONS: [(f64, f64); 6] = [
+    (0.1, 0.22),
+    (0.3, 0.26),
+    (1., 0.44),
+    (3., 0.5),
+    (10., 0.6),
+    (30., 0.8),
+];
+/// Fraction of multiple systems with at least two companions, by primary mass in solar masses.
+const HIGHER_ORDER_FRACTIONS: [(f64, f64); 3] = [(0.1, 0.1), (1., 0.25), (30., 0.45)];
+
+/// Interpolates a table sorted by mass in log space, clamping at both ends.
+fn lerp_by_log_mass(table: &[(f64, f64)], mass: f64) -> f64 {
+    let x = mass.ln();
+    match table.iter().position(|(m, _)| m.ln() >= x) {
+        Some(0) => table[0].1,
+        Some(i) => {
+            let ((m0, f0), (m1, f1)) = (table[i - 1], table[i]);
+            f0 + (f1 - f0) * (x - m0.ln()) / (m1.ln() - m0.ln())
         }
+        None => table[table.len() - 1].1,
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct RockyBodyCrustDensityDistribution;
-
-impl RockyBodyCrustDensityDistribution {
-    pub const MIN: f64 = 2.5;
-    pub const MAX: f64 = 5.5;
+#[inline]
+pub fn multiplicity_fraction(primary_mass: f64) -> f64 {
+    lerp_by_log_mass(&MULTIPLICITY_FRACTIONS, primary_mass)
 }
 
-impl Distribution<f64> for RockyBodyCrustDensityDistribution {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x = rng
-            .sample(Normal::<f64>::new(0.5, 0.25).unwrap())
-            .clamp(0., 1.);
-        x * (Self::MAX - Self::MIN) + Self::MIN
-    }
+#[inline]
+pub fn triple_fraction(primary_mass: f64) -> f64 {
+    multiplicity_fraction(primary_mass) * lerp_by_log_mass(&HIGHER_ORDER_FRACTIONS, primary_mass)
 }
 
+/// Samples the number of stars in a system, up to three.
 #[derive(Clone, Copy)]
-pub struct RockyBodyAtmoDensityDistribution;
-
-impl RockyBodyAtmoDensityDistribution {
-    pub const MIN: f64 = 0.2e-3;
-    pub const MAX: f64 = 2.5e-3;
-    pub const PDF_MAX: f64 = 0.3277;
-}
-
-impl Distribution<f64> for RockyBodyAtmoDensityDistribution {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x_distr = Uniform::new(0., 1.);
-        let y_distr = Uniform::new(0., Self::PDF_MAX);
-
-        loop {
-            let x = rng.sample(x_distr);
-            let y = rng.sample(y_distr);
-            if y < rocky_body_atmo_density_pdf()(x) {
-                return x * (Self::MAX - Self::MIN) + Self::MIN;
-            }
+pub struct StarMultiplicityDistribution {
+    /// In solar masses.
+    pub primary_mass: f64,
+}
+
+impl Distribution<usize> for StarMultiplicityDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let x = rng.gen_range(0f64..1f64);
+        if x < triple_fraction(self.primary_mass) {
+            3
+        } else if x < multiplicity_fraction(self.primary_mass) {
+            2
+        } else {
+            1
         }
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct GiantPlanetDensityDistribution;
-
-impl GiantPlanetDensityDistribution {
-    pub const MIN: f64 = 0.3;
-    pub const MAX: f64 = 1.8;
-}
-
-impl Distribution<f64> for GiantPlanetDensityDistribution {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x = rng
-            .sample(Normal::<f64>::new(0.5, 0.25).unwrap())
-            .clamp(0., 1.);
-        x * (Self::MAX - Self::MIN) + Self::MIN
+impl Distribution<LuminosityClass> for LuminosityClassPsb {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> LuminosityClass {
+        let mut x = rng.gen_range(0f32..1f32);
+        for (lum, psb) in self.entries() {
+            if x < psb {
+                return lum;
+            }
+            x -= psb;
+        }
+        LuminosityClass::Dwarf
     }
 }
 
-#[derive(Clone, Copy)]
-pub struct MoonMassDistribution;
-
-impl MoonMassDistribution {
-    pub const MIN: f64 = 1e10;
-    pub const MAX: f64 = 1e20;
-    pub const PDF_MAX: f64 = 1.;
-}
-
-impl Distribution<f64> for MoonMassDistribution {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x_distr = Uniform::new(0., 1.);
-        let y_distr = Uniform::new(0., Self::PDF_MAX);
-
-        loop {
-            let x = rng.sample(x_distr);
-            let y = rng.sample(y_distr);
-            if y < moon_mass_pdf()(x) {
-                return x * (Self::MAX - Self::MIN) + Self::MIN;
+impl Distribution<PrimaryKind> for PrimaryKindPsb {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> PrimaryKind {
+        let mut x = rng.gen_range(0f32..1f32);
+        for (kind, psb) in self.entries() {
+            if x < psb {
+                return kind;
             }
+            x -= psb;
         }
+        PrimaryKind::Star
     }
 }
 
+/// Samples the mass of a companion star from a flat mass ratio distribution.
 #[derive(Clone, Copy)]
-pub struct MoonDensityDistribution;
+pub struct CompanionMassDistribution {
+    /// In solar masses.
+    pub primary_mass: f64,
+}
 
-impl MoonDensityDistribution {
-    pub const MIN: f64 = 1.5;
-    pub const MAX: f64 = 3.5;
-    pub const PDF_MAX: f64 = 1.;
+impl CompanionMassDistribution {
+    pub const MIN_RATIO: f64 = 0.1;
+    pub const MIN: f64 = STAR_MASS_BOUNDS.0;
 }
 
-impl Distribution<f64> for MoonDensityDistribution {
+impl Distribution<f64> for CompanionMassDistribution {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x = rng
-            .sample(Normal::<f64>::new(0.5, 0.25).unwrap())
-            .clamp(0., 1.);
-        x * (Self::MAX - Self::MIN) + Self::MIN
+        let ratio = rng.gen_range(Self::MIN_RATIO..=1.);
+        (ratio * self.primary_mass)
+            .max(Self::MIN)
+            .min(self.primary_mass)
     }
 }
 
+/// Samples separations uniformly in log space, following Öpik's law.
 #[derive(Clone, Copy)]
-pub struct MoonSmiDistIncreCoeffDistribution;
-
-impl MoonSmiDistIncreCoeffDistribution {
-    pub const MIN: f64 = 1.2;
-    pub const MAX: f64 = 2.;
-    pub const PDF_MAX: f64 = 1.;
+pub struct CompanionSepDistribution {
+    pub min: f64,
+    pub max: f64,
 }
 
-impl Distribution<f64> for MoonSmiDistIncreCoeffDistribution {
+impl Distribution<f64> for CompanionSepDistribution {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
-        let x_distr = Uniform::new(0., 1.);
-        let y_distr = Uniform::new(0., Self::PDF_MAX);
-
-        loop {
-            let x = rng.sample(x_distr);
-            let y = rng.sample(y_distr);
-            if y < moon_smi_dist_incre_coeff_pdf()(x) {
-                return x * (Self::MAX - Self::MIN) + Self::MIN;
-            }
-        }
+        rng.gen_range(self.min.ln()..self.max.ln()).exp()
     }
 }
 
@@ -326,19 +420,64 @@ impl_composition_distr!(RockyCrustCompositionDistribution, ROCKY_PLANET_CRUST);
 pub struct RockyAtmosphereCompositionDistribution;
 impl_composition_distr!(RockyAtmosphereCompositionDistribution, ROCKY_PLANET_ATMO);
 
+#[derive(Clone, Copy)]
+pub struct IcyCrustCompositionDistribution;
+impl_composition_distr!(IcyCrustCompositionDistribution, ICY_BODY_CRUST);
+
+#[derive(Clone, Copy)]
+pub struct CometCompositionDistribution;
+impl_composition_distr!(CometCompositionDistribution, COMET_ICES);
+
 #[cfg(test)]
 mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
     use crate::gen::GalaxyGeneratorConfig;
 
     use super::*;
 
+    fn distrs() -> Distributions {
+        Distributions::new(&DistributionsConfig::default())
+            .ok()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_default_distrs() {
+        let config = DistributionsConfig::default();
+        let distrs = distrs();
+        let mut rng = StdRng::seed_from_u64(0);
+        for (name, pdf) in config.iter() {
+            let pdf = PdfDistribution::new(pdf).ok().unwrap();
+            for _ in 0..100 {
+                let x = rng.sample(&pdf);
+                assert!((pdf.min()..pdf.max()).contains(&x), "{}: {}", name, x);
+            }
+        }
+        // Stars lighter than the Sun are sampled as often as the density predicts.
+        let star_mass = &distrs.star_mass;
+        let step = (1. - star_mass.min()) / 1000.;
+        let expected = (0..1000)
+            .map(|i| star_mass.density(star_mass.min() + (i as f64 + 0.5) * step) * step)
+            .sum::<f64>();
+        let num_light = (0..2000).filter(|_| rng.sample(star_mass) < 1.).count();
+        assert!(
+            (num_light as f64 / 2000. - expected).abs() < 0.03,
+            "{} {}",
+            num_light,
+            expected
+        );
+    }
+
     #[test]
     fn test_smd() {
+        let distrs = distrs();
+        let mut rng = StdRng::seed_from_u64(0);
         let mut records = vec![0; 120];
         for _ in 0..64 {
-            let mut rng = rand::thread_rng();
-            let mass = StarMassDistribution.sample(&mut rng);
-            records[mass as usize] += 1;
+            let mass = distrs.star_mass.sample(&mut rng);
+            let bucket = (mass as usize).min(records.len() - 1);
+            records[bucket] += 1;
         }
         println!("{:?}", records);
     }
@@ -359,22 +498,83 @@ mod test {
 
     #[test]
     fn test_planet_mass() {
+        let distrs = distrs();
+        let mut rng = StdRng::seed_from_u64(0);
         let mut records = vec![0; 268];
         for _ in 0..64 {
-            let mut rng = rand::thread_rng();
-            let mass = PlanetMassDistribution.sample(&mut rng);
-            records[mass as usize] += 1;
+            let mass = distrs.planet_mass.sample(&mut rng);
+            let bucket = (mass as usize).min(records.len() - 1);
+            records[bucket] += 1;
         }
         println!("{:?}", records);
     }
 
+    #[test]
+    fn test_star_pos() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let morphologies = [
+            GalaxyMorphology::Disc,
+            GalaxyMorphology::Spiral {
+                num_arms: 2,
+                pitch: 0.3,
+                arm_width: 0.4,
+            },
+            GalaxyMorphology::BarredSpiral {
+                num_arms: 2,
+                pitch: 0.3,
+                arm_width: 0.4,
+                bar_length: 0.3,
+            },
+            GalaxyMorphology::Elliptical {
+                axis_ratio: 0.6,
+                effective_radius: 0.3,
+            },
+            GalaxyMorphology::Irregular {
+                num_clumps: 5,
+                clump_radius: 0.2,
+            },
+        ];
+        let mean_dists = morphologies.map(|morphology| {
+            let distr = StarPosDistribution::new(10., morphology, &mut rng);
+            let mut quadrants = [0; 4];
+            let mut dist = 0.;
+            for _ in 0..2000 {
+                let pos = rng.sample(&distr);
+                assert!(pos.length() < 10.);
+                quadrants[(pos.x < 0.) as usize * 2 + (pos.y < 0.) as usize] += 1;
+                dist += pos.length() / 2000.;
+            }
+            if !matches!(morphology, GalaxyMorphology::Irregular { .. }) {
+                assert!(quadrants.iter().all(|&n| n > 300), "{:?}", quadrants);
+            }
+            dist
+        });
+        // Ellipticals are much more concentrated than discs.
+        assert!(mean_dists[3] < mean_dists[0] * 0.6);
+    }
+
+    #[test]
+    fn test_star_multiplicity() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let fractions = [0.1, 1., 30.].map(|primary_mass| {
+            let distr = StarMultiplicityDistribution { primary_mass };
+            let num_multiple = (0..10000).filter(|_| rng.sample(distr) > 1).count();
+            num_multiple as f64 / 10000.
+        });
+        assert!((fractions[1] - multiplicity_fraction(1.)).abs() < 0.02);
+        assert!(fractions.windows(2).all(|w| w[0] < w[1]));
+        assert!(triple_fraction(100.) < multiplicity_fraction(100.));
+    }
+
     #[test]
     fn test_moon_mass() {
+        let distrs = distrs();
+        let mut rng = StdRng::seed_from_u64(0);
         let mut records = vec![0; 23];
         for _ in 0..10 {
-            let mut rng = rand::thread_rng();
-            let mass = MoonMassDistribution.sample(&mut rng);
-            records[mass.log10() as usize] += 1;
+            let mass = distrs.moon_mass.sample(&mut rng);
+            let bucket = (mass.log10() as usize).min(records.len() - 1);
+            records[bucket] += 1;
         }
         records.iter().enumerate().for_each(|(i, &x)| {
             println!("{}: {}", i, x);
@@ -383,11 +583,13 @@ mod test {
 
     #[test]
     fn test_moon_density() {
+        let distrs = distrs();
+        let mut rng = StdRng::seed_from_u64(0);
         let mut records = vec![0; 100];
         for _ in 0..10 {
-            let mut rng = rand::thread_rng();
-            let mass = MoonDensityDistribution.sample(&mut rng);
-            records[(mass * 10.) as usize] += 1;
+            let mass = distrs.moon_density.sample(&mut rng);
+            let bucket = ((mass * 10.) as usize).min(records.len() - 1);
+            records[bucket] += 1;
         }
         records.iter().enumerate().for_each(|(i, &x)| {
             println!("{}: {}", i, x);
@@ -396,11 +598,13 @@ mod test {
 
     #[test]
     fn test_gas_giant_density() {
+        let distrs = distrs();
+        let mut rng = StdRng::seed_from_u64(0);
         let mut records = vec![0; 12];
         for _ in 0..10 {
-            let mut rng = rand::thread_rng();
-            let mass = GiantPlanetDensityDistribution.sample(&mut rng);
-            records[(mass * 10.) as usize] += 1;
+            let mass = distrs.giant_planet_density.sample(&mut rng);
+            let bucket = ((mass * 10.) as usize).min(records.len() - 1);
+            records[bucket] += 1;
         }
         records.iter().enumerate().for_each(|(i, &x)| {
             println!("{}: {}", i, x);
@@ -409,11 +613,13 @@ mod test {
 
     #[test]
     fn test_moon_smi_dist_incre_coeff() {
+        let distrs = distrs();
+        let mut rng = StdRng::seed_from_u64(0);
         let mut records = vec![0; 20];
         for _ in 0..10 {
-            let mut rng = rand::thread_rng();
-            let mass = MoonSmiDistIncreCoeffDistribution.sample(&mut rng);
-            records[(mass * 10.) as usize] += 1;
+            let mass = distrs.moon_smi_dist_incre_coeff.sample(&mut rng);
+            let bucket = ((mass * 10.) as usize).min(records.len() - 1);
+            records[bucket] += 1;
         }
         records.iter().enumerate().for_each(|(i, &x)| {
             println!("{}: {}", i, x);
@@ -422,6 +628,7 @@ mod test {
 
     #[test]
     fn test_comp_distr() {
+        let distrs = distrs();
         let mut rng = rand::thread_rng();
         let comp = StarCompositionDistribution.sample(&mut rng);
         println!("star: {:?}", comp.normalized());
@@ -438,7 +645,7 @@ mod test {
         let comp = RockyAtmosphereCompositionDistribution.sample(&mut rng);
         println!("rocky atmo: {:?}", comp.normalized());
 
-        let density = RockyBodyAtmoDensityDistribution.sample(&mut rng);
+        let density = distrs.rocky_atmo_density.sample(&mut rng);
         println!("rocky atmo density: {}", density);
     }
 }
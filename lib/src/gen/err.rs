@@ -1,7 +1,16 @@
 use std::fmt::{Display, Formatter, Result};
 
+use serde::Serialize;
+
+use super::config::ConfigError;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum PlanetGenerationError {
     MaxSystemRadiusExceeded,
+    UnstableOrbit,
+    HillSpacingNotMet,
+    HillUnstable,
+    Chaotic,
 }
 
 impl Display for PlanetGenerationError {
@@ -10,10 +19,23 @@ impl Display for PlanetGenerationError {
             PlanetGenerationError::MaxSystemRadiusExceeded => {
                 write!(f, "Max system radius exceeded")
             }
+            PlanetGenerationError::UnstableOrbit => {
+                write!(f, "Orbit perturbed by companion star")
+            }
+            PlanetGenerationError::HillSpacingNotMet => {
+                write!(f, "Too heavy to be spaced from the previous planet")
+            }
+            PlanetGenerationError::HillUnstable => {
+                write!(f, "Orbit may cross the previous planet's")
+            }
+            PlanetGenerationError::Chaotic => {
+                write!(f, "Orbit chaotic with the previous planet")
+            }
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum MoonGenerationError {
     MinAccNotMet,
     MaxDistToPlanetExceeded,
@@ -31,3 +53,55 @@ impl Display for MoonGenerationError {
         }
     }
 }
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum BeltGenerationError {
+    NotEnoughRoom,
+    UnstableOrbit,
+}
+
+impl Display for BeltGenerationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            BeltGenerationError::NotEnoughRoom => {
+                write!(f, "Not enough room between planets")
+            }
+            BeltGenerationError::UnstableOrbit => {
+                write!(f, "Orbit perturbed by companion star")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum CometGenerationError {
+    UnstableOrbit,
+}
+
+impl Display for CometGenerationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            CometGenerationError::UnstableOrbit => {
+                write!(f, "Aphelia perturbed by companion star")
+            }
+        }
+    }
+}
+
+pub enum SystemRegenerationError {
+    UnknownSystem(usize),
+    InvalidOverrides(ConfigError),
+}
+
+impl Display for SystemRegenerationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            SystemRegenerationError::UnknownSystem(index) => {
+                write!(f, "No system with index {}", index)
+            }
+            SystemRegenerationError::InvalidOverrides(err) => {
+                write!(f, "Invalid overrides: {}", err)
+            }
+        }
+    }
+}
@@ -1,91 +1,427 @@
 use bevy::{
     asset::Assets,
+    ecs::{event::Event, system::Resource},
     log::{error, info},
-    math::DVec2,
-    render::mesh::Mesh,
+    render::{color::Color, mesh::Mesh},
     sprite::{ColorMaterial, MaterialMesh2dBundle, Mesh2dHandle},
-    utils::HashSet,
+    transform::components::Transform,
 };
+use std::{f64::consts::TAU, time::Instant};
+
+use bevy::math::DVec2;
 use rand::{rngs::StdRng, Rng, SeedableRng};
 use rand_distr::Uniform;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "debug")]
+use bevy::reflect::Reflect;
+
+#[cfg(feature = "sim3d")]
+use bevy::math::DQuat;
 
 use crate::{
     assets::{
-        settings::{ConstellationNames, StarProperties},
+        settings::{ConstellationNames, PackedStarInfo, StarProperties},
         MaterialAssets, MeshAssets, SubstanceAssets,
     },
-    consts, math,
-    sci::physics,
+    consts,
+    math::{self, HexRgbaColor},
+    sci::{
+        chemistry::MatterState,
+        evolution, habitability, physics,
+        stability::{self, OrbitElements},
+    },
     sim::{
-        bundles::{CelestialBodyBundle, StarBundle},
-        components::{CelestialBodyId, PlanetType},
-        resources::{CelestialBody, Galaxy},
+        self,
+        bundles::{
+            AccretionDiskBundle, BrownDwarfBundle, CelestialBodyBundle, CompactObjectBundle,
+            PlanetBundle, RingBundle, StarBundle,
+        },
+        components::{
+            AccretionDisk, BeltType, BrownDwarf, CelestialBodyColor, CelestialBodyCrust,
+            CelestialBodyEffectiveTemp, CelestialBodyId, CelestialBodyName, CompactObject,
+            Habitability, LuminosityClass, PlanetRing, PlanetType, Star, StarLuminosity,
+            StellarEvolution, StellarPhase,
+        },
+        resources::{CelestialBody, Galaxy, GravityModel},
+        SimVec,
     },
 };
 
 use self::{
+    config::{GalaxyPresets, UniformRange},
     distr::{
-        GiantPlanetDensityDistribution, MoonDensityDistribution, MoonMassDistribution,
-        MoonSmiDistIncreCoeffDistribution, PlanetMassDistribution, PlanetSmiDistDistribution,
-        RockyBodyCrustDensityDistribution, StarMassDistribution, StarPosDistribution,
+        CompanionMassDistribution, CompanionSepDistribution, Distributions, DistributionsConfig,
+        StarCompositionDistribution, StarMultiplicityDistribution, StarPosDistribution,
     },
-    err::{MoonGenerationError, PlanetGenerationError},
+    err::{
+        BeltGenerationError, CometGenerationError, MoonGenerationError, PlanetGenerationError,
+        SystemRegenerationError,
+    },
+    names::StarNames,
+    report::{BodyKind, CulledBody, GenerationReport, PhaseTimings, SystemReport},
+    rogue::RogueGenerationConfig,
+    stream::StreamingConfig,
 };
 
+pub mod config;
 pub mod distr;
 pub mod err;
+pub mod names;
+pub mod pdf;
 pub mod props;
+pub mod report;
+pub mod rogue;
+pub mod search;
+pub mod stream;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PlanetGenerationConfig {
-    pub num_coeff: Uniform<f32>,
+    pub num_coeff: UniformRange<f32>,
     /// Minimum linear speed of a planet at farthest point from the star.
     pub min_revl_spd: f64,
-    pub sma_smi_ratio: Uniform<f64>,
+    pub sma_smi_ratio: UniformRange<f64>,
+    /// Standard deviation of orbital inclinations in radians.
+    ///
+    /// Only used with the `sim3d` feature.
+    pub inclination_sigma: f64,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MoonGenerationConfig {
-    pub num_coeff: Uniform<f32>,
-    pub sma_smi_ratio: Uniform<f64>,
+    pub num_coeff: UniformRange<f32>,
+    pub sma_smi_ratio: UniformRange<f64>,
+    /// Standard deviation of orbital inclinations in radians.
+    ///
+    /// Only used with the `sim3d` feature.
+    pub inclination_sigma: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StarSystemGenerationConfig {
+    /// Eccentricities of the orbits between stars.
+    pub ecc: UniformRange<f64>,
+    /// Minimum periapsis distance between two stars in multiples of their radii sum.
+    pub min_sep_coeff: f64,
+    /// Chance that the planets of a binary orbit both stars when both kinds of orbits fit.
+    pub p_type_psb: f32,
+    /// Standard deviation of the inclinations of stellar orbits in radians.
+    ///
+    /// Only used with the `sim3d` feature.
+    pub inclination_sigma: f64,
+    /// Ages of the stars as fractions of their main-sequence lifetimes.
+    pub age: UniformRange<f64>,
+    /// Chances that a primary star is of each evolved luminosity class instead of a dwarf.
+    pub lum_class_psb: LuminosityClassPsb,
+    /// Chances that a primary is a brown dwarf or a stellar remnant other than a white
+    /// dwarf instead of a star.
+    pub kind_psb: PrimaryKindPsb,
+}
+
+/// Chances of each luminosity class other than dwarfs, which take up the rest.
+///
+/// Evolved stars are short-lived, so most are rare: about one star in twenty around the
+/// Sun is a white dwarf, and supergiants are one in a hundred thousand.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct LuminosityClassPsb {
+    pub supergiant: f32,
+    pub bright_giant: f32,
+    pub giant: f32,
+    pub subgiant: f32,
+    pub white_dwarf: f32,
+}
+
+impl LuminosityClassPsb {
+    /// Names of the fields, in the order of [`Self::entries`].
+    pub(crate) const FIELDS: [&'static str; 5] = [
+        "supergiant",
+        "bright_giant",
+        "giant",
+        "subgiant",
+        "white_dwarf",
+    ];
+
+    #[inline]
+    pub fn entries(&self) -> [(LuminosityClass, f32); 5] {
+        [
+            (LuminosityClass::Supergiant, self.supergiant),
+            (LuminosityClass::BrightGiant, self.bright_giant),
+            (LuminosityClass::Giant, self.giant),
+            (LuminosityClass::Subgiant, self.subgiant),
+            (LuminosityClass::WhiteDwarf, self.white_dwarf),
+        ]
+    }
+}
+
+/// What the primary of a system is, before its luminosity class is sampled.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PrimaryKind {
+    Star,
+    BrownDwarf,
+    NeutronStar,
+    BlackHole,
+}
+
+/// Chances of each kind of primary other than stars, which take up the rest.
+///
+/// Brown dwarfs are about as common as a fifth of the stars, but neutron stars and
+/// black holes have mostly been kicked out of their systems or gone dark.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct PrimaryKindPsb {
+    pub brown_dwarf: f32,
+    pub neutron_star: f32,
+    pub black_hole: f32,
+}
+
+impl PrimaryKindPsb {
+    /// Names of the fields, in the order of [`Self::entries`].
+    pub(crate) const FIELDS: [&'static str; 3] = ["brown_dwarf", "neutron_star", "black_hole"];
+
+    #[inline]
+    pub fn entries(&self) -> [(PrimaryKind, f32); 3] {
+        [
+            (PrimaryKind::BrownDwarf, self.brown_dwarf),
+            (PrimaryKind::NeutronStar, self.neutron_star),
+            (PrimaryKind::BlackHole, self.black_hole),
+        ]
+    }
+}
+
+/// A supermassive black hole at the centre of the galaxy, which the systems orbit.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct CentralBlackHoleConfig {
+    /// In solar masses.
+    pub mass: f64,
+}
+
+impl CentralBlackHoleConfig {
+    /// Mass in simulation units.
+    #[inline]
+    pub fn scaled_mass(&self) -> f64 {
+        self.mass * consts::SUN_MASS * consts::STAR_MASS_SCALE
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RingGenerationConfig {
+    /// Chance that a gas or ice giant has rings.
+    pub psb: f32,
+    /// Inner radius of the rings in planet radii.
+    pub inner_coeff: UniformRange<f64>,
+    /// Outer radius of the rings relative to the Roche limit of their material.
+    pub outer_coeff: UniformRange<f64>,
+    pub optical_depth: UniformRange<f64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BeltGenerationConfig {
+    /// Chance that a system gets an asteroid belt in the widest gap between its planets.
+    pub asteroid_psb: f32,
+    /// Chance that a system gets a Kuiper belt beyond its outermost planet.
+    pub kuiper_psb: f32,
+    pub num_bodies: usize,
+    /// Total mass of a belt. It only sizes the bodies, which are simulated as massless
+    /// test particles.
+    pub mass: UniformRange<f64>,
+    /// Radial width of a belt relative to its inner radius.
+    pub width: UniformRange<f64>,
+    /// Inner radius of a Kuiper belt in multiples of the outermost planet's distance.
+    pub kuiper_dist_coeff: f64,
+    /// Eccentricities of the orbits of belt bodies.
+    pub ecc: UniformRange<f64>,
+    /// Standard deviation of orbital inclinations in radians.
+    ///
+    /// Only used with the `sim3d` feature.
+    pub inclination_sigma: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CometGenerationConfig {
+    /// Chance that a system with planets gets comets.
+    pub psb: f32,
+    pub num_comets: usize,
+    /// Mass of a comet. It only sizes the comet, which is simulated as a massless test
+    /// particle.
+    pub mass: UniformRange<f64>,
+    /// Aphelia of the orbits in multiples of the outermost planet's distance, out in the
+    /// reservoirs beyond the planets.
+    pub aphelion_coeff: UniformRange<f64>,
+    /// Eccentricities of the orbits.
+    pub ecc: UniformRange<f64>,
+    /// Standard deviation of orbital inclinations in radians.
+    ///
+    /// Only used with the `sim3d` feature.
+    pub inclination_sigma: f64,
+    /// Fraction of its mass an active comet loses per unit of simulation time at its
+    /// sublimation point.
+    pub mass_loss_rate: f64,
 }
 
+/// No comets, as in configs written before they were generated.
+impl Default for CometGenerationConfig {
+    fn default() -> Self {
+        Self {
+            psb: 0.,
+            num_comets: 0,
+            mass: UniformRange::new(1e13, 1e15),
+            aphelion_coeff: UniformRange::new(1.5, 3.),
+            ecc: UniformRange::new(0.6, 0.95),
+            inclination_sigma: 0.5,
+            mass_loss_rate: 1e-3,
+        }
+    }
+}
+
+/// How planets are kept on long-lived orbits.
+///
+/// Every planet is placed far enough from the previous one in mutual Hill radii, and
+/// dropped if the pair isn't Hill stable or, optionally, turns out chaotic.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StabilityConfig {
+    /// Minimum separation of neighbouring planets in mutual Hill radii.
+    pub min_hill_spacing: f64,
+    pub megno: Option<MegnoConfig>,
+    /// Steps of full simulation after generation that remove the bodies that collide.
+    ///
+    /// The screening above makes this unnecessary, and it's slow, so it's off by default.
+    pub pre_sim_steps: usize,
+}
+
+impl Default for StabilityConfig {
+    fn default() -> Self {
+        Self {
+            min_hill_spacing: 8.,
+            megno: None,
+            pre_sim_steps: 0,
+        }
+    }
+}
+
+/// Short integration of the host, a new planet and the one inside it, which drops the
+/// planet if their MEGNO grows past `max`.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct MegnoConfig {
+    /// Orbits of the new planet to integrate over.
+    pub num_orbits: f64,
+    /// Steps per orbit of the inner planet.
+    pub steps_per_orbit: usize,
+    /// Steps the integration is cut off at, so that pairs far apart, whose outer planet
+    /// takes many inner orbits to go round, stay cheap to screen.
+    #[serde(default = "MegnoConfig::default_max_steps")]
+    pub max_steps: usize,
+    /// Quasi-periodic orbits settle at 2.
+    pub max: f64,
+}
+
+impl MegnoConfig {
+    fn default_max_steps() -> usize {
+        10_000
+    }
+}
+
+/// Shape of the stellar density of a galaxy.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub enum GalaxyMorphology {
+    /// Featureless disc thinning out towards the edge.
+    Disc,
+    /// Disc with logarithmic spiral arms.
+    Spiral {
+        num_arms: usize,
+        /// Angle between the arms and the tangent of a circle, in radians.
+        pitch: f64,
+        /// Standard deviation of the angular distance of stars from an arm, in radians.
+        arm_width: f64,
+    },
+    /// Spiral whose arms start at the ends of a central bar.
+    BarredSpiral {
+        num_arms: usize,
+        pitch: f64,
+        arm_width: f64,
+        /// Half length of the bar relative to the galaxy radius.
+        bar_length: f64,
+    },
+    /// Ellipsoid following de Vaucouleurs' law.
+    Elliptical {
+        /// Minor over major axis.
+        axis_ratio: f64,
+        /// Radius enclosing half of the stars, relative to the galaxy radius.
+        effective_radius: f64,
+    },
+    /// Disc of randomly scattered star-forming clumps.
+    Irregular {
+        num_clumps: usize,
+        /// Typical clump radius relative to the galaxy radius.
+        clump_radius: f64,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GalaxyGeneratorConfig {
     pub seed: u64,
     pub galaxy_name: String,
     pub galaxy_radius: f64,
+    pub morphology: GalaxyMorphology,
     pub num_stars: usize,
     pub rev_revol_psb: f32,
     pub stylish: f32,
+    pub star_cfg: StarSystemGenerationConfig,
     pub pln_cfg: PlanetGenerationConfig,
     pub moon_cfg: MoonGenerationConfig,
+    pub ring_cfg: RingGenerationConfig,
+    pub belt_cfg: BeltGenerationConfig,
+    #[serde(default)]
+    pub comet_cfg: CometGenerationConfig,
+    #[serde(default)]
+    pub rogue_cfg: RogueGenerationConfig,
+    pub gravity: GravityModel,
+    /// Generate planets, moons and belts only near a [`StreamingAnchor`](stream::StreamingAnchor)
+    /// instead of all up front.
+    #[serde(default)]
+    pub streaming: Option<StreamingConfig>,
+    #[serde(default)]
+    pub distrs: DistributionsConfig,
+    #[serde(default)]
+    pub stability: StabilityConfig,
+    #[serde(default)]
+    pub central_black_hole: Option<CentralBlackHoleConfig>,
 }
 
 impl GalaxyGeneratorConfig {
+    /// The `debug` preset, compiled in so it is there even without the presets file.
     pub fn new_debug() -> Self {
-        Self {
-            seed: 2,
-            galaxy_name: "Milky Way".to_string(),
-            galaxy_radius: 1000.,
-            num_stars: 1,
-            rev_revol_psb: 0.2,
-            stylish: 0.3,
-            pln_cfg: PlanetGenerationConfig {
-                num_coeff: Uniform::new(0.6, 1.1),
-                min_revl_spd: 5.,
-                sma_smi_ratio: Uniform::new(1., 1.1),
-            },
-            moon_cfg: MoonGenerationConfig {
-                num_coeff: Uniform::new(0.9, 1.2),
-                sma_smi_ratio: Uniform::new(1., 1.05),
-            },
-        }
+        let presets: GalaxyPresets = serde_json::from_str(include_str!(
+            "../../../cosmos/assets/config/galaxy_presets.json"
+        ))
+        .unwrap_or_else(|err| panic!("Invalid bundled galaxy presets: {}", err));
+        presets.get("debug").unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
+/// What the planets of a system orbit, and how far from it they stay stable.
+#[derive(Clone)]
+pub struct PlanetHost {
+    /// A single star, or the barycentre of a binary for circumbinary planets.
+    pub body: CelestialBody,
+    /// Stars `body` stands for, from the primary on.
+    pub num_stars: usize,
+    /// The star that heats the planets.
+    pub star_bundle: StarBundle,
+    /// What the planets are named after, like "Alpha Acamar A" or "Alpha Acamar AB" for
+    /// circumbinary planets.
+    pub name: String,
+    pub inner_edge: f64,
+    pub outer_edge: f64,
+}
+
 pub struct GalaxyGenerator<'a> {
     cfg: GalaxyGeneratorConfig,
     rng: StdRng,
-    constellation_names: &'a ConstellationNames,
-    existed_constellation_names: HashSet<String>,
+    star_pos_distr: StarPosDistribution,
+    distrs: Distributions,
+    star_names: StarNames,
     star_props: &'a StarProperties,
     substance_assets: &'a SubstanceAssets,
     mesh_assets: &'a mut MeshAssets,
@@ -94,13 +430,88 @@ pub struct GalaxyGenerator<'a> {
     materials: &'a mut Assets<ColorMaterial>,
     galaxy: Galaxy,
     bundles: Vec<Option<(CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>)>>,
-    smi_dist: Vec<f64>,
-    sma_dist: Vec<f64>,
-    system_edge: Vec<f64>,
-    systems: Vec<Vec<CelestialBodyId>>,
+    systems: Vec<SystemLayout>,
+    rogue_planets: Vec<CelestialBodyId>,
+}
+
+/// Where the systems of a generated galaxy are and which bodies they own, kept so that
+/// single systems can be regenerated later.
+#[derive(Resource, Clone)]
+pub struct GalaxyLayout {
+    pub config: GalaxyGeneratorConfig,
+    pub systems: Vec<SystemLayout>,
+    /// Free-floating planets scattered between the systems, including those culled or
+    /// removed since.
+    pub rogue_planets: Vec<CelestialBodyId>,
+    /// Interstellar objects sent through the systems so far.
+    pub interstellar: Vec<CelestialBodyId>,
+}
+
+#[derive(Clone)]
+pub struct SystemLayout {
+    pub pos: SimVec,
+    /// Bodies generated for the system, including those culled or removed since. The
+    /// stars come first.
+    pub bodies: Vec<CelestialBodyId>,
+    pub num_stars: usize,
+    /// Whether the bodies around the stars are generated.
+    pub loaded: bool,
+    /// What the system was last regenerated with.
+    pub overrides: SystemOverrides,
+}
+
+/// Parameters that replace the galaxy's when a single system is regenerated.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemOverrides {
+    /// Seed of the system's RNG, instead of the one derived from the galaxy seed.
+    pub seed: Option<u64>,
+    pub rev_revol_psb: Option<f32>,
+    pub star_cfg: Option<StarSystemGenerationConfig>,
+    pub pln_cfg: Option<PlanetGenerationConfig>,
+    pub moon_cfg: Option<MoonGenerationConfig>,
+    pub ring_cfg: Option<RingGenerationConfig>,
+    pub belt_cfg: Option<BeltGenerationConfig>,
+    pub comet_cfg: Option<CometGenerationConfig>,
+}
+
+impl SystemOverrides {
+    /// `cfg` with the overridden parameters replaced.
+    pub fn apply(&self, cfg: &GalaxyGeneratorConfig) -> GalaxyGeneratorConfig {
+        let cfg = cfg.clone();
+        GalaxyGeneratorConfig {
+            rev_revol_psb: self.rev_revol_psb.unwrap_or(cfg.rev_revol_psb),
+            star_cfg: self.star_cfg.clone().unwrap_or(cfg.star_cfg),
+            pln_cfg: self.pln_cfg.clone().unwrap_or(cfg.pln_cfg),
+            moon_cfg: self.moon_cfg.clone().unwrap_or(cfg.moon_cfg),
+            ring_cfg: self.ring_cfg.clone().unwrap_or(cfg.ring_cfg),
+            belt_cfg: self.belt_cfg.clone().unwrap_or(cfg.belt_cfg),
+            comet_cfg: self.comet_cfg.clone().unwrap_or(cfg.comet_cfg),
+            ..cfg
+        }
+    }
+}
+
+/// Asks for system `index` of the galaxy to be regenerated.
+#[derive(Event, Clone)]
+pub struct RegenerateSystem {
+    pub index: usize,
+    pub overrides: SystemOverrides,
+}
+
+pub struct RegeneratedSystem {
+    /// Ids of the bodies the system had before.
+    pub removed: Vec<CelestialBodyId>,
+    pub bundles: Vec<(CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>)>,
+    pub report: SystemReport,
 }
 
 impl<'a> GalaxyGenerator<'a> {
+    /// The config must have passed [`GalaxyGeneratorConfig::validate`].
+    ///
+    /// # Panics
+    ///
+    /// If one of the distributions of the config is invalid.
     pub fn new(
         config: GalaxyGeneratorConfig,
         constellation_names: &'a ConstellationNames,
@@ -111,23 +522,29 @@ impl<'a> GalaxyGenerator<'a> {
         meshes: &'a mut Assets<Mesh>,
         materials: &'a mut Assets<ColorMaterial>,
     ) -> Self {
+        let mut galaxy = Galaxy::default();
+        galaxy.set_gravity(config.gravity);
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let star_pos_distr =
+            StarPosDistribution::new(config.galaxy_radius, config.morphology, &mut rng);
+        let distrs = Distributions::new(&config.distrs)
+            .unwrap_or_else(|(field, err)| panic!("Invalid distribution `{}`: {}", field, err));
         Self {
-            rng: SeedableRng::seed_from_u64(config.seed),
+            rng,
+            star_pos_distr,
+            distrs,
+            star_names: StarNames::new(constellation_names.iter(), config.seed),
             cfg: config,
-            constellation_names,
-            existed_constellation_names: HashSet::new(),
             star_props,
             substance_assets,
             mesh_assets,
             material_assets,
             meshes,
             materials,
-            galaxy: Galaxy::default(),
+            galaxy,
             bundles: Vec::new(),
-            smi_dist: Vec::new(),
-            sma_dist: Vec::new(),
-            system_edge: Vec::new(),
             systems: Vec::new(),
+            rogue_planets: Vec::new(),
         }
     }
 
@@ -135,146 +552,1066 @@ impl<'a> GalaxyGenerator<'a> {
         &mut self,
         galaxy: &mut Galaxy,
         bundles: &mut Vec<(CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>)>,
-    ) {
+    ) -> GalaxyLayout {
         std::mem::swap(&mut self.galaxy, galaxy);
         *bundles = self.bundles.drain(..).filter_map(|x| x).collect();
+        GalaxyLayout {
+            config: self.cfg.clone(),
+            systems: std::mem::take(&mut self.systems),
+            rogue_planets: std::mem::take(&mut self.rogue_planets),
+            interstellar: Vec::new(),
+        }
     }
 
-    pub fn generate(&mut self) {
-        let start = std::time::SystemTime::now();
+    pub fn generate(&mut self) -> GenerationReport {
+        let start = Instant::now();
         info!("Galaxy generation started");
 
-        for system_id in 0..self.cfg.num_stars {
+        let ctx = SystemContext {
+            cfg: &self.cfg,
+            star_names: &self.star_names,
+            star_props: self.star_props,
+            substance_assets: self.substance_assets,
+            distrs: &self.distrs,
+        };
+        let systems = gen_systems(ctx, &self.star_pos_distr, &mut self.rng);
+        let systems_end = Instant::now();
+
+        let mut galaxy = std::mem::take(&mut self.galaxy);
+        if let Some(central_black_hole) = self.cfg.central_black_hole {
+            let bundle = self.commit_central_black_hole(&mut galaxy, central_black_hole);
+            self.bundles.push(Some(bundle));
+        }
+        let mut reports = Vec::with_capacity(systems.len());
+        for mut system in systems {
+            reports.push(std::mem::take(&mut system.report));
+            let (layout, bundles) = self.commit_system(&mut galaxy, system, &[]);
+            self.systems.push(layout);
+            self.bundles.extend(bundles.into_iter().map(Some));
+        }
+        let rogue_planets = self.commit_rogue_planets(&mut galaxy);
+        self.bundles.extend(rogue_planets.into_iter().map(Some));
+        self.galaxy = galaxy;
+        let commit_end = Instant::now();
+
+        info!("Simulating and culling started");
+        let culled = self.sim_and_cull();
+        info!("Simulating and culling finished");
+        for system in culled.iter().filter_map(|body| body.system) {
+            reports[system].culled += 1;
+        }
+
+        let end = Instant::now();
+        let timings = PhaseTimings {
+            systems_ms: PhaseTimings::ms(systems_end - start),
+            commit_ms: PhaseTimings::ms(commit_end - systems_end),
+            sim_and_cull_ms: PhaseTimings::ms(end - commit_end),
+            total_ms: PhaseTimings::ms(end - start),
+        };
+        info!(
+            "Galaxy generation finished in {}ms",
+            (end - start).as_millis()
+        );
+
+        GenerationReport::new(
+            self.cfg.seed,
+            reports,
+            self.rogue_planets.len(),
+            culled,
+            timings,
+        )
+    }
+
+    /// Replaces the bodies of system `index` in `galaxy` with new ones, generated from the
+    /// layout's config with `overrides` applied.
+    ///
+    /// The new stars take over the position and velocity of the old ones, wherever the
+    /// simulation has taken them. Every other body is left untouched, so the new
+    /// ones skip the pre-simulation that culls overlapping bodies. They get fresh ids,
+    /// and the meshes and materials of the replaced ones are dropped.
+    pub fn regenerate_system(
+        &mut self,
+        galaxy: &mut Galaxy,
+        layout: &mut GalaxyLayout,
+        index: usize,
+        overrides: &SystemOverrides,
+    ) -> Result<RegeneratedSystem, SystemRegenerationError> {
+        let Some(pos) = layout.systems.get(index).map(|system| system.pos) else {
+            return Err(SystemRegenerationError::UnknownSystem(index));
+        };
+        overrides
+            .apply(&layout.config)
+            .validate()
+            .map_err(SystemRegenerationError::InvalidOverrides)?;
+        let mut system = self.gen_system(&layout.config, index, overrides, pos);
+        let report = std::mem::take(&mut system.report);
+        // The stars have moved since the galaxy was generated, so the new ones take over
+        // where those still around are.
+        let old = &layout.systems[index];
+        let stars = old.bodies[..old.num_stars]
+            .iter()
+            .filter_map(|&id| galaxy.get_body(id))
+            .collect::<Vec<_>>();
+        if !stars.is_empty() {
+            let num_stars = system
+                .bundles
+                .iter()
+                .take_while(|bundle| bundle.star().is_some())
+                .count();
+            let (star_pos, star_vel) = stream::barycentre(&stars);
+            let (generated_pos, generated_vel) = stream::barycentre(&system.bodies[..num_stars]);
+            system.shift(star_pos - generated_pos, star_vel - generated_vel);
+        }
+
+        for &id in &layout.systems[index].bodies {
+            galaxy.remove_body(id);
+            self.mesh_assets.remove(id);
+            self.material_assets.remove(id);
+        }
+        let (mut system_layout, bundles) = self.commit_system(galaxy, system, &[]);
+        system_layout.overrides = overrides.clone();
+        let removed = std::mem::replace(&mut layout.systems[index], system_layout).bodies;
+        info!("Regenerated system {} with {} bodies", index, bundles.len());
+
+        Ok(RegeneratedSystem {
+            removed,
+            bundles,
+            report,
+        })
+    }
+
+    /// Generates all bodies of system `index` of a galaxy made with `config`.
+    fn gen_system(
+        &self,
+        config: &GalaxyGeneratorConfig,
+        index: usize,
+        overrides: &SystemOverrides,
+        pos: SimVec,
+    ) -> GeneratedSystem {
+        let cfg = overrides.apply(config);
+        let ctx = SystemContext {
+            cfg: &cfg,
+            star_names: &self.star_names,
+            star_props: self.star_props,
+            substance_assets: self.substance_assets,
+            distrs: &self.distrs,
+        };
+        let seed = overrides
+            .seed
+            .unwrap_or_else(|| system_seed(config.seed, index));
+        SystemGenerator::new(ctx, index, seed).generate(pos)
+    }
+
+    /// Adds the bodies of a generated system to `galaxy`, giving them their final ids
+    /// and their meshes.
+    ///
+    /// `committed` are the ids of the bodies generated before the system's first one,
+    /// which are already in the galaxy.
+    fn commit_system(
+        &mut self,
+        galaxy: &mut Galaxy,
+        system: GeneratedSystem,
+        committed: &[CelestialBodyId],
+    ) -> (
+        SystemLayout,
+        Vec<(CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>)>,
+    ) {
+        let num_stars = system
+            .bundles
+            .iter()
+            .filter(|bundle| bundle.star().is_some())
+            .count();
+        let mut ids = committed.to_vec();
+        let mut bundles = Vec::with_capacity(system.bodies.len());
+        for (body, mut bundle) in system.bodies.into_iter().zip(system.bundles) {
+            let id = galaxy.add_body(body);
+            bundle.set_id(id);
+            if let Some(heating) = bundle.heating_mut() {
+                heating.star = ids[heating.star.0];
+            }
+            galaxy.set_color(id, bundle.color());
+
+            let mesh = self.gen_meshes(id, &body, &mut bundle);
+            bundles.push((bundle, mesh));
+            ids.push(id);
+        }
+
+        (
+            SystemLayout {
+                pos: system.pos,
+                bodies: ids.split_off(committed.len()),
+                num_stars,
+                loaded: system.complete,
+                overrides: SystemOverrides::default(),
+            },
+            bundles,
+        )
+    }
+
+    /// Generates the meshes of body `id`, and those of its rings or accretion disk.
+    fn gen_meshes(
+        &mut self,
+        id: CelestialBodyId,
+        body: &CelestialBody,
+        bundle: &mut CelestialBodyBundle,
+    ) -> MaterialMesh2dBundle<ColorMaterial> {
+        let color = bundle.color();
+        let radius = match bundle {
+            CelestialBodyBundle::CompactObject { .. } => drawn_radius(body.radius()),
+            _ => body.radius(),
+        };
+        let mesh = Mesh2dHandle(self.mesh_assets.generate(self.meshes, id, radius));
+        let material = self.material_assets.generate(self.materials, id, color);
+
+        let disk = match bundle {
+            CelestialBodyBundle::Planet {
+                ring: Some(ring), ..
+            } => Some((
+                &mut ring.mesh,
+                ring.ring.inner_radius,
+                ring.ring.outer_radius,
+                ring.ring.color,
+            )),
+            CelestialBodyBundle::CompactObject {
+                disk: Some(disk), ..
+            } => Some((
+                &mut disk.mesh,
+                disk.disk.inner_radius,
+                disk.disk.outer_radius,
+                disk.disk.color,
+            )),
+            _ => None,
+        };
+        if let Some((disk_mesh, inner_radius, outer_radius, disk_color)) = disk {
+            disk_mesh.mesh = Mesh2dHandle(self.mesh_assets.generate_ring(
+                self.meshes,
+                id,
+                inner_radius,
+                outer_radius,
+            ));
+            disk_mesh.material = self
+                .material_assets
+                .generate_ring(self.materials, id, disk_color);
+        }
+
+        MaterialMesh2dBundle {
+            mesh,
+            material,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a body generated outside the systems to `galaxy`, giving it its final id and
+    /// its meshes.
+    fn commit_body(
+        &mut self,
+        galaxy: &mut Galaxy,
+        body: CelestialBody,
+        mut bundle: CelestialBodyBundle,
+    ) -> (CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>) {
+        let id = galaxy.add_body(body);
+        bundle.set_id(id);
+        galaxy.set_color(id, bundle.color());
+        let mesh = self.gen_meshes(id, &body, &mut bundle);
+        (bundle, mesh)
+    }
+
+    /// Adds the central black hole to `galaxy`, ahead of every system.
+    fn commit_central_black_hole(
+        &mut self,
+        galaxy: &mut Galaxy,
+        central_black_hole: CentralBlackHoleConfig,
+    ) -> (CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>) {
+        let evolution = evolution::remnant_evolution(
+            central_black_hole.mass / evolution::BLACK_HOLE_MASS_FRACTION,
+            0.,
+        );
+        let info = remnant_info(self.star_props, &evolution);
+        let (mass, radius) = scaled_star(&info);
+        let body = CelestialBody::new(SimVec::ZERO, radius, mass, SimVec::ZERO);
+        let id = galaxy.add_body(body);
+
+        let star = StarBundle {
+            id,
+            color: CelestialBodyColor(info.color.into()),
+            name: CelestialBodyName(format!("{} Central Black Hole", self.cfg.galaxy_name)),
+            class: info.class,
+            composition: CelestialBodyCrust {
+                content: self.rng.sample(StarCompositionDistribution),
+                density: math::mass_radius_to_density(info.mass, info.radius),
+            },
+            effective_temp: CelestialBodyEffectiveTemp(info.effective_temp),
+            luminosity: StarLuminosity(info.luminosity),
+            habitable_zone: habitability::habitable_zone(info.luminosity, info.effective_temp),
+            evolution,
+            tag: Star,
+        };
+        let mut bundle = star_body_bundle(star, radius);
+        galaxy.set_color(id, bundle.color());
+        let mesh = self.gen_meshes(id, &body, &mut bundle);
+        (bundle, mesh)
+    }
+
+    /// Steps the galaxy and removes the bodies that overlap, returning what was removed.
+    fn sim_and_cull(&mut self) -> Vec<CulledBody> {
+        let mut owners = vec![None; self.bundles.len()];
+        for (index, system) in self.systems.iter().enumerate() {
+            for id in &system.bodies {
+                owners[id.0] = Some(index);
+            }
+        }
+
+        let mut culled = Vec::new();
+        for step in 1..=self.cfg.stability.pre_sim_steps {
+            self.galaxy.step();
+            self.galaxy.test_overlapping().into_iter().for_each(|id| {
+                self.galaxy.remove_body(id);
+                if let Some((bundle, _)) = self.bundles[id.0].take() {
+                    culled.push(CulledBody {
+                        id: id.0,
+                        name: bundle.name().to_string(),
+                        kind: BodyKind::of(&bundle),
+                        system: owners[id.0],
+                        step,
+                    });
+                }
+                info!("Removed body {} at pre-sim step {}", id.0, step);
+            });
+        }
+        culled
+    }
+}
+
+/// Seed of the RNG that generates system `index` of a galaxy seeded with `seed`.
+///
+/// Both are mixed with SplitMix64, so neighbouring systems get unrelated streams.
+pub fn system_seed(seed: u64, index: usize) -> u64 {
+    let mut z = seed ^ (index as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// What every star system of a galaxy is generated from.
+#[derive(Clone, Copy)]
+struct SystemContext<'a> {
+    cfg: &'a GalaxyGeneratorConfig,
+    star_names: &'a StarNames,
+    star_props: &'a StarProperties,
+    substance_assets: &'a SubstanceAssets,
+    distrs: &'a Distributions,
+}
+
+/// Bodies of a star system, whose ids are their indices within the system until it is
+/// committed to the galaxy.
+pub struct GeneratedSystem {
+    pub pos: SimVec,
+    pub bodies: Vec<CelestialBody>,
+    /// Stars first, the primary leading.
+    pub bundles: Vec<CelestialBodyBundle>,
+    /// Whether the bodies around the stars are generated too.
+    pub complete: bool,
+    /// Stars, from the primary on, that the planets, moons, belts and comets orbit the
+    /// barycentre of.
+    pub host_stars: usize,
+    pub report: SystemReport,
+}
+
+impl GeneratedSystem {
+    /// The star every planet of the system is heated by.
+    pub fn primary(&self) -> &StarBundle {
+        self.bundles[0]
+            .star()
+            .expect("Systems start with their primary")
+    }
+
+    pub fn planets(&self) -> impl Iterator<Item = &PlanetBundle> {
+        self.bundles.iter().filter_map(|bundle| match bundle {
+            CelestialBodyBundle::Planet { planet, .. } => Some(planet),
+            _ => None,
+        })
+    }
+
+    /// Scores of the rocky planets and moons of the system.
+    pub fn habitability(&self) -> impl Iterator<Item = &Habitability> {
+        self.bundles.iter().filter_map(|bundle| match bundle {
+            CelestialBodyBundle::Planet { habitability, .. } => habitability.as_ref(),
+            CelestialBodyBundle::Moon { habitability, .. } => Some(habitability),
+            _ => None,
+        })
+    }
+}
+
+/// Generates every star system of a galaxy, in system order. With streaming, only their
+/// stars are generated.
+///
+/// Systems are placed one after another with the galaxy's `rng`, as each has to keep
+/// clear of the ones before it. Everything else comes from the system's own RNG seeded
+/// with [`system_seed`], so the systems are filled in in parallel and the result does
+/// not depend on the number of threads.
+fn gen_systems(
+    ctx: SystemContext,
+    star_pos_distr: &StarPosDistribution,
+    rng: &mut StdRng,
+) -> Vec<GeneratedSystem> {
+    let mut placed: Vec<(SimVec, f64)> = Vec::with_capacity(ctx.cfg.num_stars + 1);
+    // Systems keep as clear of the central black hole as of each other.
+    if let Some(central_black_hole) = ctx.cfg.central_black_hole {
+        placed.push((SimVec::ZERO, central_black_hole.scaled_mass()));
+    }
+    let generators = (0..ctx.cfg.num_stars)
+        .map(|system_id| {
+            let seed = system_seed(ctx.cfg.seed, system_id);
+            let generator = SystemGenerator::new(ctx, system_id, seed);
+            let mass = scaled_star(&generator.primary).0;
+            let pos = sample_star_pos(star_pos_distr, rng, &placed, mass);
+            placed.push((pos, mass));
+            (generator, pos)
+        })
+        .collect::<Vec<_>>();
+
+    generators
+        .into_par_iter()
+        .map(|(generator, pos)| match ctx.cfg.streaming {
+            Some(_) => generator.generate_stars(pos),
+            None => generator.generate(pos),
+        })
+        .collect()
+}
+
+/// Samples a position for a new system of primary `mass` that is far enough from the
+/// `placed` ones.
+fn sample_star_pos(
+    star_pos_distr: &StarPosDistribution,
+    rng: &mut StdRng,
+    placed: &[(SimVec, f64)],
+    mass: f64,
+) -> SimVec {
+    loop {
+        let pos = sim::from_plane(rng.sample(star_pos_distr));
+        let num_confl = placed
+            .iter()
+            .filter(|&&(rhs_pos, rhs_mass)| {
+                let f = physics::force_between(
+                    &CelestialBody::new(pos, 0., mass, SimVec::ZERO),
+                    &CelestialBody::new(rhs_pos, 0., rhs_mass, SimVec::ZERO),
+                );
+
+                f / mass > consts::STAR_ACC_THRESHOLD || f / rhs_mass > consts::STAR_ACC_THRESHOLD
+            })
+            .count();
+        if num_confl == 0 {
+            break pos;
+        }
+    }
+}
+
+/// Velocity of a system at `pos` on a circular orbit around the central black hole,
+/// if the galaxy has one.
+fn system_vel(cfg: &GalaxyGeneratorConfig, pos: SimVec) -> SimVec {
+    let Some(central_black_hole) = cfg.central_black_hole else {
+        return SimVec::ZERO;
+    };
+    let pos = sim::to_plane(pos);
+    let dist = pos.length();
+    let spd = physics::vis_viva_get_smi_vel(central_black_hole.scaled_mass(), dist, dist);
+    sim::from_plane(pos.perp() / dist * spd)
+}
+
+/// Mass and radius of a star in simulation units.
+fn scaled_star(info: &PackedStarInfo) -> (f64, f64) {
+    (
+        info.mass * consts::SUN_MASS * consts::STAR_MASS_SCALE,
+        info.radius * consts::SUN_RADIUS * consts::STAR_RADIUS_SCALE,
+    )
+}
+
+/// Radius a body of `radius` in simulation units is drawn with if it is a compact
+/// object.
+#[inline]
+fn drawn_radius(radius: f64) -> f64 {
+    radius.max(consts::COMPACT_OBJECT_MIN_RADIUS * consts::SUN_RADIUS * consts::STAR_RADIUS_SCALE)
+}
+
+/// Properties of the remnant with `evolution` as if it were tabulated. It keeps the
+/// class of its progenitor, and neutron stars glow with the colour of the hottest stars.
+fn remnant_info(star_props: &StarProperties, evolution: &StellarEvolution) -> PackedStarInfo {
+    let state = evolution.state;
+    let dwarf = |value: f64, key: fn(&PackedStarInfo) -> f64| {
+        star_props.interpolate(LuminosityClass::Dwarf, value, key)
+    };
+    let color = match state.phase {
+        StellarPhase::BlackHole => HexRgbaColor {
+            r: 0.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        },
+        _ => dwarf(state.effective_temp, |info| info.effective_temp).color,
+    };
+    PackedStarInfo {
+        class: dwarf(evolution.initial_mass, |info| info.mass).class,
+        mass: state.mass,
+        radius: state.radius,
+        luminosity: state.luminosity,
+        effective_temp: state.effective_temp,
+        color,
+    }
+}
+
+/// Wraps `star`, whose body has `radius` in simulation units, in the bundle of its kind
+/// of body.
+fn star_body_bundle(star: StarBundle, radius: f64) -> CelestialBodyBundle {
+    if star.class.ty.is_substellar() {
+        return CelestialBodyBundle::BrownDwarf(BrownDwarfBundle {
+            star,
+            tag: BrownDwarf,
+        });
+    }
+    let Some(kind) = CompactObject::of(star.evolution.state.phase) else {
+        return CelestialBodyBundle::Star(star);
+    };
+
+    let disk = (kind == CompactObject::BlackHole).then(|| {
+        let radius = drawn_radius(radius);
+        Box::new(AccretionDiskBundle {
+            disk: AccretionDisk {
+                inner_radius: radius * consts::ACCRETION_DISK_RADII.start,
+                outer_radius: radius * consts::ACCRETION_DISK_RADII.end,
+                color: Color::hex("ffb060").unwrap(),
+            },
+            mesh: MaterialMesh2dBundle {
+                // Behind the black hole.
+                transform: Transform::from_xyz(0., 0., -1e-3),
+                ..Default::default()
+            },
+        })
+    });
+    CelestialBodyBundle::CompactObject {
+        object: CompactObjectBundle { star, kind },
+        disk,
+    }
+}
+
+/// Generates the bodies of one star system from its own RNG.
+struct SystemGenerator<'a> {
+    cfg: &'a GalaxyGeneratorConfig,
+    rng: StdRng,
+    star_names: &'a StarNames,
+    star_props: &'a StarProperties,
+    substance_assets: &'a SubstanceAssets,
+    distrs: &'a Distributions,
+    system_id: usize,
+    /// Properties of the primary star, sampled first so that the system can be placed
+    /// before it is generated.
+    primary: PackedStarInfo,
+    /// Evolution of a primary generated as a neutron star or black hole, which aren't
+    /// tabulated.
+    primary_remnant: Option<StellarEvolution>,
+    bodies: Vec<CelestialBody>,
+    bundles: Vec<CelestialBodyBundle>,
+    smi_dist: Vec<f64>,
+    sma_dist: Vec<f64>,
+    /// Planets around the host so far as they started, innermost first.
+    planets: Vec<(CelestialBody, OrbitElements)>,
+    report: SystemReport,
+}
+
+impl<'a> SystemGenerator<'a> {
+    fn new(ctx: SystemContext<'a>, system_id: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let star_cfg = &ctx.cfg.star_cfg;
+        let mut primary_remnant = None;
+        let primary = match rng.sample(star_cfg.kind_psb) {
+            // Evolved primaries come from their own tables, whose masses are those of
+            // stars old enough to have evolved.
+            PrimaryKind::Star => match rng.sample(star_cfg.lum_class_psb) {
+                LuminosityClass::Dwarf => {
+                    let mass = rng.sample(&ctx.distrs.star_mass);
+                    ctx.star_props
+                        .interpolate(LuminosityClass::Dwarf, mass, |info| info.mass)
+                }
+                lum => ctx.star_props.along(lum, rng.gen_range(0f64..=1f64)),
+            },
+            PrimaryKind::BrownDwarf => ctx.star_props.brown_dwarf_along(rng.gen_range(0f64..=1f64)),
+            kind => {
+                let phase = match kind {
+                    PrimaryKind::NeutronStar => StellarPhase::NeutronStar,
+                    _ => StellarPhase::BlackHole,
+                };
+                // Log-uniform, which the mass function is close enough to over the range.
+                let masses = evolution::progenitor_masses(phase);
+                let initial_mass =
+                    masses.start * (masses.end / masses.start).powf(rng.gen_range(0f64..1f64));
+                let cooling_age =
+                    rng.sample(star_cfg.age) * evolution::main_sequence_lifetime(initial_mass);
+                let remnant = evolution::remnant_evolution(initial_mass, cooling_age);
+                primary_remnant = Some(remnant);
+                remnant_info(ctx.star_props, &remnant)
+            }
+        };
+        Self {
+            cfg: ctx.cfg,
+            rng,
+            star_names: ctx.star_names,
+            star_props: ctx.star_props,
+            substance_assets: ctx.substance_assets,
+            distrs: ctx.distrs,
+            system_id,
+            primary,
+            primary_remnant,
+            bodies: Vec::new(),
+            bundles: Vec::new(),
+            smi_dist: Vec::new(),
+            sma_dist: Vec::new(),
+            planets: Vec::new(),
+            report: SystemReport::new(system_id, seed),
+        }
+    }
+
+    /// Generates the stars, planets, moons and belts of the system around `pos`.
+    fn generate(mut self, pos: SimVec) -> GeneratedSystem {
+        let start = Instant::now();
+        let (system_id, seed) = (self.system_id, self.cfg.seed);
+        info!(
+            "Generating stars {}/{}[{}]",
+            system_id, self.cfg.num_stars, seed
+        );
+
+        let host = self.gen_star_system(pos);
+        // Supernovae blow away the planets of their stars.
+        if !matches!(
+            host.star_bundle.evolution.state.phase,
+            StellarPhase::NeutronStar | StellarPhase::BlackHole
+        ) {
+            self.gen_planetary_system(&host);
+        }
+        self.finish(pos, &host, true, start)
+    }
+
+    /// Generates only the stars of the system, which are the same as those of
+    /// [`Self::generate`].
+    fn generate_stars(mut self, pos: SimVec) -> GeneratedSystem {
+        let start = Instant::now();
+        info!(
+            "Generating stars {}/{}[{}]",
+            self.system_id, self.cfg.num_stars, self.cfg.seed
+        );
+
+        let host = self.gen_star_system(pos);
+        self.finish(pos, &host, false, start)
+    }
+
+    fn finish(
+        mut self,
+        pos: SimVec,
+        host: &PlanetHost,
+        complete: bool,
+        start: Instant,
+    ) -> GeneratedSystem {
+        self.report.complete = complete;
+        self.report.count_bodies(&self.bundles);
+        self.report.elapsed_ms = PhaseTimings::ms(start.elapsed());
+        GeneratedSystem {
+            pos,
+            bodies: self.bodies,
+            bundles: self.bundles,
+            complete,
+            host_stars: host.num_stars,
+            report: self.report,
+        }
+    }
+
+    /// Generates the planets, moons, belts and comets around `host`.
+    fn gen_planetary_system(&mut self, host: &PlanetHost) {
+        let (system_id, seed) = (self.system_id, self.cfg.seed);
+        let num_planets = distr::max_num_planets(
+            host.star_bundle.class.to_index(),
+            self.cfg.pln_cfg.num_coeff,
+            &mut self.rng,
+        );
+        let system_edge =
+            physics::linear_spd_to_dist(self.cfg.pln_cfg.min_revl_spd, host.body.mass());
+        let mut planet_dists = Vec::with_capacity(num_planets);
+
+        for planet_systemic_id in 0..num_planets {
             info!(
-                "Generating stars {}/{}[{}]",
-                system_id, self.cfg.num_stars, self.cfg.seed
+                "Generating planets {}/{}[{}, {}]",
+                planet_systemic_id, num_planets, system_id, seed
             );
+            let (planet, planet_id) = match self.gen_planet(host, system_edge) {
+                Ok(body) => body,
+                Err(err) => {
+                    error!("Planet generation failed: {}", err);
+                    self.report.planet_failed(err);
+                    continue;
+                }
+            };
+            let planet_name = names::planet(&host.name, planet_dists.len());
+            self.bundles[planet_id.0].set_name(planet_name.clone());
+            planet_dists.push(self.smi_dist[planet_id.0]);
 
-            let (star, star_bundle) = self.gen_star();
-            let num_planets = distr::max_num_planets(
-                star_bundle.class.to_index(),
-                self.cfg.pln_cfg.num_coeff,
-                &mut self.rng,
-            );
-            let system_edge =
-                physics::linear_spd_to_dist(self.cfg.pln_cfg.min_revl_spd, star.mass());
-            self.system_edge.push(system_edge);
+            let num_moons =
+                distr::max_num_moons(planet.mass(), self.cfg.moon_cfg.num_coeff, &mut self.rng);
+            let mut num_named_moons = 0;
 
-            for planet_systemic_id in 0..num_planets {
-                // for planet_systemic_id in 0..2 {
+            for moon_systemic_id in 0..num_moons {
                 info!(
-                    "Generating planets {}/{}[{}, {}]",
-                    planet_systemic_id, num_planets, system_id, self.cfg.seed
+                    "Generating moons {}/{}[{}, {}, {}]",
+                    moon_systemic_id, num_moons, system_id, planet_systemic_id, seed
                 );
-                let (planet, planet_id) =
-                    match self.gen_planet(&star, &star_bundle, system_id, system_edge) {
-                        Ok(body) => body,
-                        Err(err) => {
-                            error!("Planet generation failed: {}", err);
-                            continue;
-                        }
-                    };
-
-                let num_moons =
-                    distr::max_num_moons(planet.mass(), self.cfg.moon_cfg.num_coeff, &mut self.rng);
-
-                for moon_systemic_id in 0..num_moons {
-                    info!(
-                        "Generating moons {}/{}[{}, {}, {}]",
-                        moon_systemic_id, num_moons, system_id, planet_systemic_id, self.cfg.seed
-                    );
-                    match self.gen_moon(system_id, &star, &star_bundle, planet_id, &planet) {
-                        Ok(_) => {}
-                        Err(err) => error!("Moon generation failed: {}", err),
+                match self.gen_moon(&host.body, &host.star_bundle, planet_id, &planet) {
+                    Ok(moon_id) => {
+                        let name = names::moon(&planet_name, num_named_moons);
+                        self.bundles[moon_id.0].set_name(name);
+                        num_named_moons += 1;
+                    }
+                    Err(err) => {
+                        error!("Moon generation failed: {}", err);
+                        self.report.moon_failed(err);
                     }
                 }
             }
         }
 
-        info!("Simulating and culling started");
-        self.sim_and_cull();
-        info!("Simulating and culling finished");
-
-        let elapsed = start.elapsed().unwrap();
+        self.gen_belts(host, &planet_dists);
+        if let Err(err) = self.gen_comets(host, &planet_dists) {
+            error!("Comet generation failed: {}", err);
+            self.report.comet_failed(err);
+        }
+    }
 
-        info!("Galaxy generation finished in {}ms", elapsed.as_millis());
+    /// Adds a body to the system and returns its id within the system.
+    fn add_body(&mut self, body: CelestialBody) -> CelestialBodyId {
+        self.bodies.push(body);
+        CelestialBodyId(self.bodies.len() - 1)
     }
 
-    fn gen_star(&mut self) -> (CelestialBody, StarBundle) {
-        let spd = StarPosDistribution::new(self.cfg.galaxy_radius);
-        let smd = StarMassDistribution;
+    /// Generates the stars of a system and decides where its planets go.
+    ///
+    /// Systems get up to two companions depending on the primary's mass. Triples are
+    /// hierarchical, with the third star orbiting the inner binary. Brown dwarfs,
+    /// neutron stars and black holes are generated alone.
+    fn gen_star_system(&mut self, pos: SimVec) -> PlanetHost {
+        let star_cfg = &self.cfg.star_cfg;
+        let (ecc_distr, min_sep_coeff, p_type_psb) =
+            (star_cfg.ecc, star_cfg.min_sep_coeff, star_cfg.p_type_psb);
+        let min_revl_spd = self.cfg.pln_cfg.min_revl_spd;
 
-        let mass = self.rng.sample(smd);
-        let pos = {
-            loop {
-                let pos = self.rng.sample(spd);
-                let num_confl = self
-                    .systems
-                    .iter()
-                    .filter(|id| {
-                        let rhs = self.galaxy.get_body(id[0]).unwrap();
-                        let f = physics::force_between(
-                            &CelestialBody::new(pos, 0., mass, DVec2::ZERO),
-                            rhs,
-                        );
-
-                        f / mass > consts::STAR_ACC_THRESHOLD
-                            || f / rhs.mass() > consts::STAR_ACC_THRESHOLD
-                    })
-                    .count();
-                if num_confl == 0 {
-                    break pos;
+        let primary_mass = self.primary.mass;
+        let alone = self.primary_remnant.is_some() || self.primary.class.ty.is_substellar();
+        let num_stars = if alone {
+            1
+        } else {
+            self.rng
+                .sample(StarMultiplicityDistribution { primary_mass })
+        };
+        let mut infos = vec![(self.primary.clone(), self.primary_remnant)];
+        for _ in 1..num_stars {
+            let mass = self.rng.sample(CompanionMassDistribution { primary_mass });
+            infos.push((
+                self.star_props
+                    .interpolate(LuminosityClass::Dwarf, mass, |info| info.mass),
+                None,
+            ));
+        }
+        let (mass, radius): (Vec<_>, Vec<_>) =
+            infos.iter().map(|(info, _)| scaled_star(info)).unzip();
+
+        // Leave room around the inner binary for the third star of a triple.
+        let outer_ecc = (num_stars > 2).then(|| self.rng.sample(ecc_distr));
+        let outer_max = physics::linear_spd_to_dist(min_revl_spd, mass.iter().sum());
+        let inner = if num_stars > 1 {
+            let ecc = self.rng.sample(ecc_distr);
+            let min = min_sep_coeff * (radius[0] + radius[1]) / (1. - ecc);
+            let max = physics::linear_spd_to_dist(min_revl_spd, mass[0] + mass[1]);
+            let max = match outer_ecc {
+                Some(outer_ecc) => {
+                    let ratio = physics::triple_critical_sma_ratio(
+                        mass[2] / (mass[0] + mass[1]),
+                        outer_ecc,
+                    );
+                    // Falls back to a plain binary if no third star fits around it.
+                    if min < outer_max / ratio {
+                        max.min(outer_max / ratio)
+                    } else {
+                        max
+                    }
                 }
+                None => max,
+            };
+            (min < max).then(|| (self.rng.sample(CompanionSepDistribution { min, max }), ecc))
+        } else {
+            None
+        };
+        let outer = match (inner, outer_ecc) {
+            (Some((inner_sma, _)), Some(ecc)) => {
+                let min = (inner_sma
+                    * physics::triple_critical_sma_ratio(mass[2] / (mass[0] + mass[1]), ecc))
+                .max(min_sep_coeff * (radius[0] + radius[1] + radius[2]) / (1. - ecc));
+                let max = outer_max;
+                (min < max).then(|| (self.rng.sample(CompanionSepDistribution { min, max }), ecc))
             }
+            _ => None,
         };
 
-        let (bound_floor, bound_ceil) = self.star_props.find_bound(mass, |info| info.mass);
-        let lerp_factor = (mass - bound_floor.mass) / (bound_ceil.mass - bound_floor.mass);
-        let radius = bound_floor.radius + (bound_ceil.radius - bound_floor.radius) * lerp_factor;
+        let vel = system_vel(self.cfg, pos);
+        let (mut inner_pos, mut inner_vel) = (pos, vel);
+        let mut placements = Vec::with_capacity(3);
+        if let Some((sma, ecc)) = outer {
+            let [inner_binary, third] =
+                self.place_pair(pos, vel, mass[0] + mass[1], mass[2], sma, ecc);
+            (inner_pos, inner_vel) = inner_binary;
+            placements.push(third);
+        }
+        if let Some((sma, ecc)) = inner {
+            let [primary, secondary] =
+                self.place_pair(inner_pos, inner_vel, mass[0], mass[1], sma, ecc);
+            placements.splice(0..0, [primary, secondary]);
+        } else {
+            placements.push((inner_pos, inner_vel));
+        }
 
-        let star = CelestialBody::new(
-            pos,
-            radius * consts::SUN_RADIUS * consts::STAR_RADIUS_SCALE,
-            mass * consts::SUN_MASS * consts::STAR_MASS_SCALE,
-            DVec2::ZERO,
-        );
+        let mut stars = placements
+            .iter()
+            .zip(&infos)
+            .map(|(&(pos, vel), (info, remnant))| self.gen_star(pos, vel, info, *remnant))
+            .collect::<Vec<_>>();
+        let system_name = stars[0].1.name.0.clone();
+        if stars.len() > 1 {
+            for (component, (_, bundle)) in stars.iter_mut().enumerate() {
+                bundle.name.0 = names::star_component(&system_name, component);
+                self.bundles[bundle.id.0].set_name(bundle.name.0.clone());
+            }
+        }
+        let (primary, primary_bundle) = stars[0].clone();
+
+        let Some((inner_sma, inner_ecc)) = inner else {
+            return PlanetHost {
+                body: primary,
+                num_stars: 1,
+                name: primary_bundle.name.0.clone(),
+                star_bundle: primary_bundle,
+                inner_edge: 0.,
+                outer_edge: f64::INFINITY,
+            };
+        };
 
-        let id = self.galaxy.add_body(star);
-        self.systems.push(vec![id]);
+        let binary_mass = mass[0] + mass[1];
+        let mass_ratio = mass[1] / binary_mass;
+        let p_type_inner_edge = physics::p_type_critical_sma(inner_sma, mass_ratio, inner_ecc);
+        let p_type_outer_edge = outer
+            .map_or(f64::INFINITY, |(sma, ecc)| {
+                physics::s_type_critical_sma(sma, mass[2] / (binary_mass + mass[2]), ecc)
+            })
+            .min(physics::linear_spd_to_dist(min_revl_spd, binary_mass));
 
-        let bundle = self.gen_star_props(id, lerp_factor, bound_floor, bound_ceil);
+        if p_type_inner_edge < p_type_outer_edge && self.rng.gen_range(0f32..1f32) < p_type_psb {
+            let secondary = &stars[1].0;
+            PlanetHost {
+                body: CelestialBody::new(
+                    (primary.pos() * primary.mass() + secondary.pos() * secondary.mass())
+                        / binary_mass,
+                    primary.radius(),
+                    binary_mass,
+                    inner_vel,
+                ),
+                num_stars: 2,
+                star_bundle: primary_bundle,
+                name: format!("{} AB", system_name),
+                inner_edge: p_type_inner_edge,
+                outer_edge: p_type_outer_edge,
+            }
+        } else {
+            PlanetHost {
+                body: primary,
+                num_stars: 1,
+                name: primary_bundle.name.0.clone(),
+                star_bundle: primary_bundle,
+                inner_edge: 0.,
+                outer_edge: physics::s_type_critical_sma(inner_sma, mass_ratio, inner_ecc),
+            }
+        }
+    }
 
-        let mesh = Mesh2dHandle(self.mesh_assets.generate(self.meshes, id, star.radius()));
-        let material = self
-            .material_assets
-            .generate(self.materials, id, bundle.color.0);
-        self.galaxy.set_color(id, bundle.color.0);
+    /// Places two bodies at the periapsis of their mutual orbit around `center`, which
+    /// moves at `vel`.
+    fn place_pair(
+        &mut self,
+        center: SimVec,
+        vel: SimVec,
+        lhs_mass: f64,
+        rhs_mass: f64,
+        sma: f64,
+        ecc: f64,
+    ) -> [(SimVec, SimVec); 2] {
+        let mass = lhs_mass + rhs_mass;
+        let dist = sma * (1. - ecc);
+        let spd = physics::vis_viva_get_smi_vel(mass, dist, sma);
+        let (rel_pos, rel_vel) =
+            self.orient_orbit(dist, spd, 0., self.cfg.star_cfg.inclination_sigma);
+        [
+            (
+                center - rel_pos * (rhs_mass / mass),
+                vel - rel_vel * (rhs_mass / mass),
+            ),
+            (
+                center + rel_pos * (lhs_mass / mass),
+                vel + rel_vel * (lhs_mass / mass),
+            ),
+        ]
+    }
 
-        self.bundles.push(Some((
-            CelestialBodyBundle::Star(bundle.clone()),
-            MaterialMesh2dBundle {
-                mesh,
-                material,
-                ..Default::default()
-            },
-        )));
+    /// Adds a star with the properties of `info` to the system, or the brown dwarf or
+    /// compact object they describe.
+    fn gen_star(
+        &mut self,
+        pos: SimVec,
+        vel: SimVec,
+        info: &PackedStarInfo,
+        remnant: Option<StellarEvolution>,
+    ) -> (CelestialBody, StarBundle) {
+        let (mass, radius) = scaled_star(info);
+
+        let star = CelestialBody::new(pos, radius, mass, vel);
+
+        let id = self.add_body(star);
+
+        let bundle = self.gen_star_props(id, info, remnant);
+
+        self.bundles.push(star_body_bundle(bundle.clone(), radius));
         self.smi_dist.push(0.);
         self.sma_dist.push(0.);
 
-        let star = self.galaxy.get_body(id).unwrap();
-        (star.clone(), bundle.clone())
+        (star, bundle)
     }
 
     pub fn gen_planet(
         &mut self,
-        star: &CelestialBody,
-        star_bundle: &StarBundle,
-        system_id: usize,
+        host: &PlanetHost,
         system_edge: f64,
     ) -> Result<(CelestialBody, CelestialBodyId), PlanetGenerationError> {
-        let system = self.systems.get_mut(system_id).unwrap();
+        let star = &host.body;
+        let (mass, radius, ty, density) = self.sample_planet();
+
+        let (min_smi_dist, max_smi_dist) = {
+            if self
+                .bundles
+                .last()
+                .is_some_and(|bundle| bundle.star().is_some())
+            {
+                let min = (star.radius() + radius) * consts::PLANET_TO_STAR_DIST_COEFF;
+                let max = star.radius()
+                    * consts::PLANET_TO_STAR_DIST_COEFF
+                    * consts::BASE_PLANET_INTERV_COEFF
+                    + radius;
+                // Circumbinary planets start beyond the unstable zone around the binary.
+                let offset = (host.inner_edge - min).max(0.);
+                (min + offset, max + offset)
+            } else {
+                let rhs = self.bodies.last().unwrap();
+                let d1 = physics::mass_acc_to_dist(mass, consts::PLANET_ACC_THRESHOLD);
+                let d2 = physics::mass_acc_to_dist(rhs.mass(), consts::PLANET_ACC_THRESHOLD);
+                let pos = self.smi_dist.last().unwrap();
+                let min = consts::BASE_PLANET_INTERV_COEFF * rhs.radius() + *pos;
+                let max = min + d1.max(d2);
+                // Keep the previous planet's semi-major axis `min_hill_spacing` mutual
+                // Hill radii away, which heavy pairs can't be at any distance.
+                let (_, prev) = self.planets.last().unwrap();
+                let hill = (prev.mass + mass) / (3. * star.mass());
+                let half_spacing = self.cfg.stability.min_hill_spacing * hill.cbrt() / 2.;
+                if half_spacing >= 1. {
+                    return Err(PlanetGenerationError::HillSpacingNotMet);
+                }
+                let offset = (prev.sma * (1. + half_spacing) / (1. - half_spacing) - min).max(0.);
+                (min + offset, max + offset)
+            }
+        };
+
+        if min_smi_dist > system_edge {
+            return Err(PlanetGenerationError::MaxSystemRadiusExceeded);
+        }
+
+        let smi_dist = self.rng.sample(
+            self.distrs
+                .planet_smi_dist
+                .within(min_smi_dist, max_smi_dist),
+        );
+        let sma_dist = smi_dist * self.rng.sample(self.cfg.pln_cfg.sma_smi_ratio);
+
+        if sma_dist > host.outer_edge {
+            return Err(PlanetGenerationError::UnstableOrbit);
+        }
+
+        let orbit = OrbitElements::from_periapsis(mass, smi_dist, sma_dist);
+        if let Some((_, prev)) = self.planets.last() {
+            if !stability::is_hill_stable(star.mass(), prev, &orbit) {
+                return Err(PlanetGenerationError::HillUnstable);
+            }
+        }
+
+        let mut init_vel = physics::vis_viva_get_smi_vel(star.mass() + mass, smi_dist, sma_dist);
+        if self.rng.gen_range(0f32..1f32) < self.cfg.rev_revol_psb {
+            init_vel *= -1.;
+        }
+
+        let (rel_pos, rel_vel) =
+            self.orient_orbit(smi_dist, init_vel, 0., self.cfg.pln_cfg.inclination_sigma);
+        let body = CelestialBody::new(star.pos() + rel_pos, radius, mass, star.vel() + rel_vel);
+
+        if let (Some(megno), Some((prev_body, prev))) =
+            (self.cfg.stability.megno, self.planets.last())
+        {
+            let dt =
+                stability::orbital_period(star.mass(), prev.sma) / megno.steps_per_orbit as f64;
+            let duration = stability::orbital_period(star.mass(), sma_dist) * megno.num_orbits;
+            let num_steps = ((duration / dt).ceil() as usize).min(megno.max_steps);
+            if stability::megno(&[*star, *prev_body, body], dt, num_steps) > megno.max {
+                return Err(PlanetGenerationError::Chaotic);
+            }
+        }
 
-        let mass = self.rng.sample(PlanetMassDistribution)
+        self.smi_dist.push(smi_dist);
+        self.sma_dist.push(sma_dist);
+        self.planets.push((body, orbit));
+
+        let id = self.add_body(body);
+
+        let (bundle, crust, atmo) =
+            self.gen_planet_props(id, &body, density, ty, star, &host.star_bundle);
+
+        let ring = match ty {
+            PlanetType::GasGiant | PlanetType::IceGiant => {
+                self.gen_ring(&bundle, radius, density).map(Box::new)
+            }
+            PlanetType::Rocky => None,
+        };
+        let habitability = (ty == PlanetType::Rocky).then(|| {
+            habitability::habitability(
+                &host.star_bundle.habitable_zone,
+                body.pos().distance(star.pos()),
+                bundle.effective_temp.0,
+                crust.as_ref(),
+                atmo.as_ref(),
+                &bundle.substance_props,
+            )
+        });
+
+        self.bundles.push(CelestialBodyBundle::Planet {
+            planet: bundle,
+            crust,
+            atmo,
+            ring,
+            habitability,
+        });
+
+        Ok((body, id))
+    }
+
+    /// Samples the mass, radius, type and density of a planet, the first two in simulation
+    /// units.
+    fn sample_planet(&mut self) -> (f64, f64, PlanetType, f64) {
+        let mass = self.rng.sample(&self.distrs.planet_mass)
             * consts::EARTH_MASS
             * consts::PLANET_MASS_SCALE;
 
@@ -282,7 +1619,7 @@ impl<'a> GalaxyGenerator<'a> {
             if mass / consts::EARTH_MASS / consts::PLANET_MASS_SCALE
                 > consts::GIANT_PLANET_MASS_THRESHOLD
             {
-                let density = self.rng.sample(GiantPlanetDensityDistribution);
+                let density = self.rng.sample(&self.distrs.giant_planet_density);
                 if density > consts::ICE_GIANT_PLANET_DENSITY_THRESHOLD {
                     (PlanetType::IceGiant, density)
                 } else {
@@ -291,7 +1628,7 @@ impl<'a> GalaxyGenerator<'a> {
             } else {
                 (
                     PlanetType::Rocky,
-                    self.rng.sample(RockyBodyCrustDensityDistribution),
+                    self.rng.sample(&self.distrs.rocky_crust_density),
                 )
             }
         };
@@ -300,95 +1637,65 @@ impl<'a> GalaxyGenerator<'a> {
             * consts::EARTH_RADIUS
             * consts::PLANET_RADIUS_SCALE;
 
-        let (min_smi_dist, max_smi_dist) = {
-            if system.len() == 1 {
-                (
-                    (star.radius() + radius) * consts::PLANET_TO_STAR_DIST_COEFF,
-                    star.radius()
-                        * consts::PLANET_TO_STAR_DIST_COEFF
-                        * consts::BASE_PLANET_INTERV_COEFF
-                        + radius,
-                )
-            } else {
-                let last_planet_id = system.last().unwrap();
-                let rhs = self.galaxy.get_body(*last_planet_id).unwrap();
-                let d1 = physics::mass_acc_to_dist(mass, consts::PLANET_ACC_THRESHOLD);
-                let d2 = physics::mass_acc_to_dist(rhs.mass(), consts::PLANET_ACC_THRESHOLD);
-                let pos = self.smi_dist.last().unwrap();
-                (
-                    consts::BASE_PLANET_INTERV_COEFF * rhs.radius() + *pos,
-                    consts::BASE_PLANET_INTERV_COEFF * rhs.radius() + *pos + d1.max(d2),
-                )
-            }
-        };
+        (mass, radius, ty, density)
+    }
 
-        if min_smi_dist > system_edge {
-            return Err(PlanetGenerationError::MaxSystemRadiusExceeded);
+    /// Gives a giant planet rings between `inner_coeff` planet radii and its Roche limit.
+    ///
+    /// The mesh of the rings is left empty until the system is committed.
+    fn gen_ring(&mut self, planet: &PlanetBundle, radius: f64, density: f64) -> Option<RingBundle> {
+        let ring_cfg = &self.cfg.ring_cfg;
+        let (inner_coeff, outer_coeff, optical_depth) = (
+            ring_cfg.inner_coeff,
+            ring_cfg.outer_coeff,
+            ring_cfg.optical_depth,
+        );
+        if self.rng.gen_range(0f32..1f32) >= ring_cfg.psb {
+            return None;
         }
 
-        let smi_dist = self.rng.sample(PlanetSmiDistDistribution {
-            min: min_smi_dist,
-            max: max_smi_dist,
-        });
-        let sma_dist = smi_dist * self.rng.sample(self.cfg.pln_cfg.sma_smi_ratio);
-
-        self.smi_dist.push(smi_dist);
-        self.sma_dist.push(sma_dist);
-
-        let mut init_vel = physics::vis_viva_get_smi_vel(star.mass() + mass, smi_dist, sma_dist);
-        if self.rng.gen_range(0f32..1f32) < self.cfg.rev_revol_psb {
-            init_vel *= -1.;
+        let (content, ring_density) = self.gen_ring_props(planet)?;
+        let inner_radius = radius * self.rng.sample(inner_coeff);
+        let outer_radius =
+            physics::roche_limit(radius, density, ring_density) * self.rng.sample(outer_coeff);
+        if inner_radius >= outer_radius {
+            return None;
         }
 
-        let body = CelestialBody::new(
-            star.pos() + consts::DEFAULT_BODY_EXTEND_AXIS * smi_dist,
-            // DVec2 { x: 1e5, y: 0. },
-            radius,
-            mass,
-            init_vel * consts::DEFAULT_BODY_VEL_DIR,
-        );
-
-        let id = self.galaxy.add_body(body);
-        system.push(id);
+        let optical_depth = self.rng.sample(optical_depth);
+        let mut color: Color = content
+            .estimate_color(self.substance_assets, MatterState::Solid)
+            .into();
+        color.set_a(1. - (-optical_depth as f32).exp());
 
-        let (bundle, crust, atmo) =
-            self.gen_planet_props(id, &body, density, ty, star, star_bundle);
-
-        let mesh = Mesh2dHandle(self.mesh_assets.generate(self.meshes, id, radius));
-        let material = self
-            .material_assets
-            .generate(self.materials, id, bundle.color.0);
-
-        self.galaxy.set_color(id, bundle.color.0);
-        self.bundles.push(Some((
-            CelestialBodyBundle::Planet {
-                planet: bundle,
-                crust,
-                atmo,
+        Some(RingBundle {
+            ring: PlanetRing {
+                inner_radius,
+                outer_radius,
+                optical_depth,
+                color,
+                content,
             },
-            MaterialMesh2dBundle {
-                mesh,
-                material,
+            mesh: MaterialMesh2dBundle {
+                // Behind the planet.
+                transform: Transform::from_xyz(0., 0., -1e-3),
                 ..Default::default()
             },
-        )));
-
-        Ok((self.galaxy.get_body(id).unwrap().clone(), id))
+        })
     }
 
     fn gen_moon(
         &mut self,
-        system_id: usize,
         star: &CelestialBody,
         star_bundle: &StarBundle,
         planet_id: CelestialBodyId,
         planet: &CelestialBody,
-    ) -> Result<(), MoonGenerationError> {
-        let mass = self.rng.sample(MoonMassDistribution);
-        let density = self.rng.sample(MoonDensityDistribution);
+    ) -> Result<CelestialBodyId, MoonGenerationError> {
+        let mass = self.rng.sample(&self.distrs.moon_mass);
+        let density = self.rng.sample(&self.distrs.moon_density);
         let radius = math::mass_to_radius(mass, density) * consts::MOON_RADIUS_SCALE;
         let planet_smi_dist = self.smi_dist[planet_id.0];
-        let smi_dist_incre_coeff = self.rng.sample(MoonSmiDistIncreCoeffDistribution);
+        let smi_dist_incre_coeff = self.rng.sample(&self.distrs.moon_smi_dist_incre_coeff);
         let smi_dist_rel = {
             if self.smi_dist.len() - 1 == planet_id.0 {
                 planet.radius()
@@ -424,49 +1731,772 @@ impl<'a> GalaxyGenerator<'a> {
             init_vel *= -1.;
         }
 
-        let body = CelestialBody::new(
-            star.pos() + (planet_smi_dist + smi_dist_rel) * consts::DEFAULT_BODY_EXTEND_AXIS,
-            radius,
-            mass,
-            planet.vel() + init_vel * consts::DEFAULT_BODY_VEL_DIR,
+        let (rel_pos, rel_vel) = self.orient_orbit(
+            smi_dist_rel,
+            init_vel,
+            0.,
+            self.cfg.moon_cfg.inclination_sigma,
         );
+        let body = CelestialBody::new(planet.pos() + rel_pos, radius, mass, planet.vel() + rel_vel);
 
-        let id = self.galaxy.add_body(body);
-        self.systems[system_id].push(id);
+        let id = self.add_body(body);
 
         let (bundle, crust, atmo) = self.gen_moon_props(id, &body, star, star_bundle);
+        let habitability = habitability::habitability(
+            &star_bundle.habitable_zone,
+            body.pos().distance(star.pos()),
+            bundle.effective_temp.0,
+            Some(&crust),
+            atmo.as_ref(),
+            &bundle.substance_props,
+        );
 
-        let mesh = Mesh2dHandle(self.mesh_assets.generate(self.meshes, id, radius));
-        let material = self
-            .material_assets
-            .generate(self.materials, id, bundle.color.0);
+        self.bundles.push(CelestialBodyBundle::Moon {
+            moon: bundle,
+            crust,
+            atmo,
+            habitability,
+        });
+
+        Ok(id)
+    }
+
+    /// Adds an asteroid belt in the widest gap between the planets and a Kuiper belt
+    /// beyond the outermost one, each with its own chance.
+    ///
+    /// `planet_dists` are the distances of the planets from the host, innermost first.
+    /// A belt that fails is reported without keeping the other from being generated.
+    fn gen_belts(&mut self, host: &PlanetHost, planet_dists: &[f64]) {
+        let belt_cfg = &self.cfg.belt_cfg;
+        let (asteroid_psb, kuiper_psb, width_distr, kuiper_dist_coeff) = (
+            belt_cfg.asteroid_psb,
+            belt_cfg.kuiper_psb,
+            belt_cfg.width,
+            belt_cfg.kuiper_dist_coeff,
+        );
+
+        let mut results = Vec::with_capacity(2);
+        let widest_gap = planet_dists
+            .windows(2)
+            .max_by(|lhs, rhs| (lhs[1] / lhs[0]).total_cmp(&(rhs[1] / rhs[0])));
+        if let Some(gap) = widest_gap {
+            if self.rng.gen_range(0f32..1f32) < asteroid_psb {
+                let ratio = (1. + self.rng.sample(width_distr)).sqrt();
+                let center = (gap[0] * gap[1]).sqrt();
+                let (inner, outer) = (center / ratio, center * ratio);
+                results.push(if inner <= gap[0] || outer >= gap[1] {
+                    Err(BeltGenerationError::NotEnoughRoom)
+                } else {
+                    self.gen_belt(host, BeltType::Asteroid, inner, outer)
+                });
+            }
+        }
+
+        if let Some(outermost) = planet_dists.last() {
+            if self.rng.gen_range(0f32..1f32) < kuiper_psb {
+                let inner = outermost * kuiper_dist_coeff;
+                let outer = inner * (1. + self.rng.sample(width_distr));
+                results.push(self.gen_belt(host, BeltType::Kuiper, inner, outer));
+            }
+        }
 
-        self.galaxy.set_color(id, bundle.color.0);
+        for err in results.into_iter().filter_map(Result::err) {
+            error!("Belt generation failed: {}", err);
+            self.report.belt_failed(err);
+        }
+    }
 
-        self.bundles.push(Some((
-            CelestialBodyBundle::Moon {
-                moon: bundle,
+    /// Scatters the bodies of a belt between `inner` and `outer` around the host.
+    ///
+    /// The bodies are massless test particles, so they follow the planets and stars
+    /// without pulling on them or on each other.
+    fn gen_belt(
+        &mut self,
+        host: &PlanetHost,
+        ty: BeltType,
+        inner: f64,
+        outer: f64,
+    ) -> Result<(), BeltGenerationError> {
+        if outer > host.outer_edge {
+            return Err(BeltGenerationError::UnstableOrbit);
+        }
+
+        let belt_cfg = &self.cfg.belt_cfg;
+        let (num_bodies, ecc_distr, inclination_sigma) = (
+            belt_cfg.num_bodies,
+            belt_cfg.ecc,
+            belt_cfg.inclination_sigma,
+        );
+        let mass = self.rng.sample(belt_cfg.mass) / num_bodies as f64;
+        let dist_distr = Uniform::new(inner, outer);
+        let star = &host.body;
+
+        for index in 0..num_bodies {
+            let density = match ty {
+                BeltType::Asteroid => self.rng.sample(&self.distrs.rocky_crust_density),
+                BeltType::Kuiper => self.rng.sample(&self.distrs.icy_crust_density),
+            };
+            let radius = math::mass_to_radius(mass, density) * consts::MOON_RADIUS_SCALE;
+
+            // Bodies start at periapsis, anywhere around the host.
+            let smi_dist = self.rng.sample(dist_distr);
+            let sma_dist = smi_dist / (1. - self.rng.sample(ecc_distr));
+            let spd = physics::vis_viva_get_smi_vel(star.mass(), smi_dist, sma_dist);
+            let phase = self.rng.gen_range(0. ..TAU);
+            let (rel_pos, rel_vel) = self.orient_orbit(smi_dist, spd, phase, inclination_sigma);
+            let body = CelestialBody::new(star.pos() + rel_pos, radius, 0., star.vel() + rel_vel);
+
+            let id = self.add_body(body);
+            self.smi_dist.push(smi_dist);
+            self.sma_dist.push(sma_dist);
+
+            let (bundle, crust) =
+                self.gen_belt_body_props(id, &body, mass, density, ty, star, &host.star_bundle);
+
+            self.bundles.push(CelestialBodyBundle::BeltBody {
+                body: bundle,
                 crust,
-                atmo,
-            },
-            MaterialMesh2dBundle {
-                mesh,
-                material,
-                ..Default::default()
-            },
-        )));
+            });
+            self.bundles[id.0].set_name(names::belt_body(&host.name, ty, index));
+        }
 
         Ok(())
     }
 
-    fn sim_and_cull(&mut self) {
-        for step in 0..consts::PRE_SIM_STEPS {
-            self.galaxy.step();
-            self.galaxy.test_overlapping().into_iter().for_each(|id| {
-                self.galaxy.remove_body(id);
-                self.bundles[id.0] = None;
-                info!("Removed body {} at pre-sim step {}", id.0, step);
+    /// Sends comets from the reservoirs beyond the outermost planet onto eccentric orbits
+    /// that dive towards the host.
+    ///
+    /// They start at aphelion, anywhere around the host, and are massless test particles
+    /// like belt bodies.
+    fn gen_comets(
+        &mut self,
+        host: &PlanetHost,
+        planet_dists: &[f64],
+    ) -> Result<(), CometGenerationError> {
+        let comet_cfg = &self.cfg.comet_cfg;
+        let Some(outermost) = planet_dists.last() else {
+            return Ok(());
+        };
+        if self.rng.gen_range(0f32..1f32) >= comet_cfg.psb {
+            return Ok(());
+        }
+        if outermost * comet_cfg.aphelion_coeff.max > host.outer_edge {
+            return Err(CometGenerationError::UnstableOrbit);
+        }
+
+        let (num_comets, mass_distr, aphelion_distr, ecc_distr, inclination_sigma) = (
+            comet_cfg.num_comets,
+            comet_cfg.mass,
+            comet_cfg.aphelion_coeff,
+            comet_cfg.ecc,
+            comet_cfg.inclination_sigma,
+        );
+        let star = &host.body;
+
+        for index in 0..num_comets {
+            let mass = self.rng.sample(mass_distr);
+            let density = self.rng.sample(&self.distrs.icy_crust_density);
+            let radius = math::mass_to_radius(mass, density) * consts::MOON_RADIUS_SCALE;
+
+            let aphelion = outermost * self.rng.sample(aphelion_distr);
+            let ecc = self.rng.sample(ecc_distr);
+            let sma_dist = aphelion / (1. + ecc);
+            let spd = physics::vis_viva_get_smi_vel(star.mass(), aphelion, sma_dist);
+            let phase = self.rng.gen_range(0. ..TAU);
+            let (rel_pos, rel_vel) = self.orient_orbit(aphelion, spd, phase, inclination_sigma);
+            let body = CelestialBody::new(star.pos() + rel_pos, radius, 0., star.vel() + rel_vel);
+
+            let id = self.add_body(body);
+            self.smi_dist.push(sma_dist * (1. - ecc));
+            self.sma_dist.push(sma_dist);
+
+            let (bundle, crust) =
+                self.gen_comet_props(id, &body, mass, density, star, &host.star_bundle);
+            self.bundles.push(CelestialBodyBundle::Comet {
+                comet: bundle,
+                crust,
             });
+            self.bundles[id.0].set_name(names::comet(&host.name, index));
+        }
+
+        Ok(())
+    }
+
+    /// Places a body at `dist` from its parent, moving at `spd`, and returns the relative
+    /// position and velocity.
+    ///
+    /// Orbits start on `DEFAULT_BODY_EXTEND_AXIS` turned by `phase` radians within the
+    /// reference plane, and in 3D they are tilted by a sampled inclination around a
+    /// sampled longitude of the ascending node.
+    #[cfg_attr(not(feature = "sim3d"), allow(unused_variables))]
+    fn orient_orbit(
+        &mut self,
+        dist: f64,
+        spd: f64,
+        phase: f64,
+        inclination_sigma: f64,
+    ) -> (SimVec, SimVec) {
+        let turn = DVec2::from_angle(phase);
+        let pos = sim::from_plane(turn.rotate(consts::DEFAULT_BODY_EXTEND_AXIS * dist));
+        let vel = sim::from_plane(turn.rotate(consts::DEFAULT_BODY_VEL_DIR * spd));
+
+        #[cfg(feature = "sim3d")]
+        {
+            let (inclination, node) = self
+                .rng
+                .sample(distr::OrbitOrientationDistribution::new(inclination_sigma));
+            let rot = DQuat::from_rotation_z(node) * DQuat::from_rotation_x(inclination);
+            (rot * pos, rot * vel)
+        }
+
+        #[cfg(not(feature = "sim3d"))]
+        (pos, vel)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{
+        ecs::world::{FromWorld, World},
+        utils::HashSet,
+    };
+
+    use crate::{
+        assets::{CelestialBodyAssets, GlobalConfig},
+        sci::chemistry::Substance,
+        sim::components::Comet,
+        utils,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_system_seed() {
+        let seeds = (0..1000).map(|i| system_seed(2, i)).collect::<HashSet<_>>();
+        assert_eq!(seeds.len(), 1000);
+        assert_ne!(system_seed(2, 0), system_seed(3, 0));
+        assert_eq!(system_seed(2, 7), system_seed(2, 7));
+    }
+
+    pub(super) fn load_assets() -> (ConstellationNames, StarProperties, SubstanceAssets) {
+        (
+            ConstellationNames::load(format!("../{}", consts::STAR_NAMES)).unwrap(),
+            StarProperties::load(format!("../{}", consts::STAR_PROPERTIES)).unwrap(),
+            SubstanceAssets::load(format!("../{}", consts::SUBSTANCE_ASSETS)).unwrap(),
+        )
+    }
+
+    pub(super) fn test_config() -> GalaxyGeneratorConfig {
+        let mut cfg = GalaxyGeneratorConfig::new_debug();
+        cfg.seed = 4;
+        cfg.num_stars = 4;
+        cfg.galaxy_radius = 1e11;
+        cfg
+    }
+
+    pub(super) fn bits(body: &CelestialBody) -> Vec<u64> {
+        let (pos, vel) = (body.pos().to_array(), body.vel().to_array());
+        pos.into_iter()
+            .chain(vel)
+            .chain([body.mass(), body.radius()])
+            .map(f64::to_bits)
+            .collect()
+    }
+
+    /// Generates the systems of a galaxy with `cfg` on `num_threads` threads.
+    pub(super) fn gen_test_systems(
+        cfg: &GalaxyGeneratorConfig,
+        num_threads: usize,
+    ) -> Vec<GeneratedSystem> {
+        let (constellation_names, star_props, substance_assets) = load_assets();
+        let distrs = Distributions::new(&cfg.distrs).ok().unwrap();
+        let star_names = StarNames::new(constellation_names.iter(), cfg.seed);
+        let ctx = SystemContext {
+            cfg,
+            star_names: &star_names,
+            star_props: &star_props,
+            substance_assets: &substance_assets,
+            distrs: &distrs,
+        };
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            let mut rng = StdRng::seed_from_u64(cfg.seed);
+            let star_pos_distr =
+                StarPosDistribution::new(cfg.galaxy_radius, cfg.morphology, &mut rng);
+            gen_systems(ctx, &star_pos_distr, &mut rng)
+        })
+    }
+
+    #[test]
+    fn test_gen_systems_thread_independence() {
+        let cfg = test_config();
+        let serial = gen_test_systems(&cfg, 1);
+        let parallel = gen_test_systems(&cfg, 4);
+        assert_eq!(serial.len(), parallel.len());
+        for (lhs, rhs) in serial.iter().zip(&parallel) {
+            assert_eq!(lhs.bodies.len(), rhs.bodies.len());
+            for (lhs, rhs) in lhs.bodies.iter().zip(&rhs.bodies) {
+                assert_eq!(bits(lhs), bits(rhs));
+            }
+            for (lhs, rhs) in lhs.bundles.iter().zip(&rhs.bundles) {
+                assert!(lhs.id() == rhs.id() && lhs.color() == rhs.color());
+                assert_eq!(lhs.name(), rhs.name());
+            }
+        }
+    }
+
+    #[cfg(feature = "sim3d")]
+    #[test]
+    fn test_inclined_orbits() {
+        let systems = gen_test_systems(&test_config(), 4);
+        let mut planets = systems.iter().flat_map(|system| {
+            let primary = system.bodies[0];
+            system
+                .planets()
+                .map(move |planet| system.bodies[planet.id.0].pos() - primary.pos())
+        });
+        assert!(planets.any(|rel_pos| rel_pos.z.abs() > 1e-6 * rel_pos.length()));
+    }
+
+    #[test]
+    fn test_body_names() {
+        let mut cfg = test_config();
+        cfg.num_stars = 8;
+        let systems = gen_test_systems(&cfg, 4);
+
+        let star_names = StarNames::new(load_assets().0.iter(), cfg.seed);
+        let mut names = HashSet::new();
+        for (index, system) in systems.iter().enumerate() {
+            let system_name = star_names.star(index);
+            for bundle in &system.bundles {
+                assert!(bundle.name().starts_with(&system_name), "{}", bundle.name());
+                if let CelestialBodyBundle::Moon { .. } = bundle {
+                    let (planet, _) = bundle.name().rsplit_once(' ').unwrap();
+                    assert!(names.contains(planet), "{}", bundle.name());
+                }
+                assert!(names.insert(bundle.name().to_string()), "{}", bundle.name());
+            }
+        }
+        assert!(systems.iter().any(|system| system
+            .bundles
+            .iter()
+            .any(|bundle| bundle.name().ends_with(" b"))));
+    }
+
+    #[test]
+    fn test_habitability() {
+        let systems = gen_test_systems(&test_config(), 4);
+        let scores = systems
+            .iter()
+            .flat_map(|system| &system.bundles)
+            .filter_map(|bundle| match bundle {
+                CelestialBodyBundle::Planet {
+                    planet,
+                    habitability,
+                    ..
+                } => {
+                    assert_eq!(habitability.is_some(), planet.ty == PlanetType::Rocky);
+                    *habitability
+                }
+                CelestialBodyBundle::Moon { habitability, .. } => Some(*habitability),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(!scores.is_empty());
+        for score in scores {
+            let parts = [
+                score.distance,
+                score.temperature,
+                score.atmosphere,
+                score.water,
+                score.overall,
+            ];
+            assert!(parts.iter().all(|x| (0. ..=1.).contains(x)));
         }
     }
+
+    #[test]
+    fn test_stability_screening() {
+        let mut cfg = test_config();
+        cfg.num_stars = 16;
+        cfg.stability.megno = Some(MegnoConfig {
+            num_orbits: 5.,
+            steps_per_orbit: 50,
+            max_steps: 10_000,
+            max: 4.,
+        });
+        let mut pairs = 0;
+        for system in gen_test_systems(&cfg, 4) {
+            if system.report.stars > 1 {
+                continue;
+            }
+            let star = system.bodies[0];
+            let planets = system
+                .bodies
+                .iter()
+                .zip(&system.bundles)
+                .filter(|(_, bundle)| matches!(bundle, CelestialBodyBundle::Planet { .. }))
+                .map(|(body, _)| {
+                    let (pos, vel) = (body.pos() - star.pos(), body.vel() - star.vel());
+                    let sma = 1.
+                        / (2. / pos.length()
+                            - vel.length_squared() / (consts::G * (star.mass() + body.mass())));
+                    OrbitElements::from_periapsis(body.mass(), pos.length(), sma)
+                })
+                .collect::<Vec<_>>();
+            for pair in planets.windows(2) {
+                let spacing = stability::hill_spacing(star.mass(), &pair[0], &pair[1]);
+                assert!(
+                    spacing > cfg.stability.min_hill_spacing * 0.99,
+                    "{}",
+                    spacing
+                );
+                assert!(stability::is_hill_stable(star.mass(), &pair[0], &pair[1]));
+                pairs += 1;
+            }
+        }
+        assert!(pairs > 0);
+    }
+
+    #[test]
+    fn test_luminosity_classes() {
+        let mut cfg = test_config();
+        cfg.num_stars = 16;
+        cfg.star_cfg.lum_class_psb = LuminosityClassPsb {
+            supergiant: 0.2,
+            bright_giant: 0.1,
+            giant: 0.2,
+            subgiant: 0.1,
+            white_dwarf: 0.2,
+        };
+        cfg.star_cfg.kind_psb = PrimaryKindPsb {
+            brown_dwarf: 0.,
+            neutron_star: 0.,
+            black_hole: 0.,
+        };
+        let systems = gen_test_systems(&cfg, 4);
+        let classes = systems
+            .iter()
+            .map(|system| system.primary().class.lum)
+            .collect::<HashSet<_>>();
+        assert!(classes.len() > 2);
+
+        for system in &systems {
+            let primary = system.primary();
+            let generated = primary.evolution.generated;
+            assert!(generated.phase == primary.class.lum.phase());
+            let radius = system.bodies[0].radius() / consts::SUN_RADIUS / consts::STAR_RADIUS_SCALE;
+            assert!((radius / generated.radius - 1.).abs() < 1e-9);
+            match primary.class.lum {
+                LuminosityClass::Supergiant => assert!(radius > 20.),
+                LuminosityClass::WhiteDwarf => assert!(radius < 0.02),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_primary_kinds() {
+        let mut cfg = test_config();
+        cfg.num_stars = 16;
+        cfg.star_cfg.kind_psb = PrimaryKindPsb {
+            brown_dwarf: 0.3,
+            neutron_star: 0.2,
+            black_hole: 0.2,
+        };
+        let systems = gen_test_systems(&cfg, 4);
+        let mut kinds = Vec::new();
+        for system in &systems {
+            let primary = system.primary();
+            let phase = primary.evolution.state.phase;
+            match &system.bundles[0] {
+                CelestialBodyBundle::BrownDwarf(brown_dwarf) => {
+                    assert!(brown_dwarf.star.class.ty.is_substellar());
+                    assert!(brown_dwarf.star.evolution.lifetime.is_infinite());
+                    assert_eq!(system.report.stars, 1);
+                }
+                CelestialBodyBundle::CompactObject { object, disk } => {
+                    assert!(CompactObject::of(phase) == Some(object.kind));
+                    assert_eq!(disk.is_some(), object.kind == CompactObject::BlackHole);
+                    if object.kind != CompactObject::WhiteDwarf {
+                        assert_eq!(system.report.stars, 1);
+                        assert_eq!(system.planets().count(), 0);
+                    }
+                }
+                _ => assert!(!phase.is_remnant() && !primary.class.ty.is_substellar()),
+            }
+            let kind = BodyKind::of(&system.bundles[0]);
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        assert_eq!(kinds.len(), 3);
+    }
+
+    #[test]
+    fn test_comets() {
+        let mut cfg = test_config();
+        cfg.comet_cfg.psb = 1.;
+        let (_, _, substance_assets) = load_assets();
+        let systems = gen_test_systems(&cfg, 2);
+        let mut num_comets = 0;
+        for system in &systems {
+            let primary = system.bodies[0];
+            let outermost = system
+                .planets()
+                .map(|planet| system.bodies[planet.id.0].pos().distance(primary.pos()))
+                .fold(0., f64::max);
+            let comets = system.bundles.iter().filter_map(|bundle| match bundle {
+                CelestialBodyBundle::Comet { comet, crust } => Some((comet, crust)),
+                _ => None,
+            });
+            for (comet, crust) in comets {
+                num_comets += 1;
+                let ices = [
+                    Substance::Water,
+                    Substance::AmorphousIce,
+                    Substance::Methane,
+                    Substance::Ammonia,
+                ];
+                assert!(crust.content.iter().all(|(sub, _)| ices.contains(sub)));
+                // Ices sublimate in a vacuum well below where they melt.
+                for (sub, _) in crust.content.iter() {
+                    let props = substance_assets.get(*sub);
+                    assert!(props.sublimation_point < props.melting_point);
+                }
+                let sublimation_point = crust
+                    .content
+                    .iter()
+                    .map(|(sub, _)| substance_assets.get(*sub).sublimation_point)
+                    .fold(f64::INFINITY, f64::min);
+                assert_eq!(comet.tag.sublimation_point, sublimation_point);
+
+                // Comets start at aphelion, out beyond the planets of single stars.
+                let body = system.bodies[comet.id.0];
+                if system.report.stars == 1 {
+                    assert!(body.pos().distance(primary.pos()) > outermost);
+                }
+                assert_eq!(body.mass(), 0.);
+            }
+            assert_eq!(
+                system.report.comets,
+                system
+                    .bundles
+                    .iter()
+                    .filter(|bundle| BodyKind::of(bundle) == BodyKind::Comet)
+                    .count()
+            );
+        }
+        assert!(num_comets > 0);
+
+        let comet = Comet {
+            mass: 1e14,
+            sublimation_point: 90.,
+            mass_loss_rate: 1e-3,
+        };
+        assert!(!comet.is_active(90.));
+        assert_eq!(comet.mass_loss(80., 1.), 0.);
+        let loss = comet.mass_loss(100., 1.);
+        assert!(loss > 0. && loss < comet.mass);
+        assert!(comet.mass_loss(200., 1.) > loss);
+    }
+
+    #[test]
+    fn test_central_black_hole() {
+        let mut cfg = test_config();
+        let central_black_hole = CentralBlackHoleConfig { mass: 1000. };
+        cfg.central_black_hole = Some(central_black_hole);
+        assert!(cfg.validate().is_ok());
+
+        // Each system as a whole circles the hole.
+        for system in gen_test_systems(&cfg, 4) {
+            let (mass, momentum) = system
+                .bodies
+                .iter()
+                .zip(&system.bundles)
+                .filter(|(_, bundle)| bundle.star().is_some())
+                .fold((0., SimVec::ZERO), |(mass, momentum), (body, _)| {
+                    (mass + body.mass(), momentum + body.vel() * body.mass())
+                });
+            let vel = momentum / mass;
+            let dist = system.pos.length();
+            let spd = physics::vis_viva_get_smi_vel(central_black_hole.scaled_mass(), dist, dist);
+            assert!((vel.length() / spd - 1.).abs() < 1e-6);
+            assert!(vel.dot(system.pos).abs() < 1e-6 * spd * dist);
+        }
+
+        with_galaxy(cfg.clone(), |_, galaxy, layout| {
+            let hole = galaxy.get_body(CelestialBodyId(0)).unwrap();
+            assert!(hole.pos() == SimVec::ZERO && hole.mass() == central_black_hole.scaled_mass());
+            assert!(layout
+                .systems
+                .iter()
+                .all(|system| system.bodies[0] != CelestialBodyId(0)));
+        });
+
+        cfg.central_black_hole = Some(CentralBlackHoleConfig { mass: 1e9 });
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn test_system_reports() {
+        let cfg = test_config();
+        let systems = gen_test_systems(&cfg, 4);
+        for (index, system) in systems.iter().enumerate() {
+            let report = &system.report;
+            assert_eq!(report.index, index);
+            assert_eq!(report.seed, system_seed(cfg.seed, index));
+            assert_eq!(
+                report.stars
+                    + report.planets.accepted
+                    + report.moons.accepted
+                    + report.belt_bodies
+                    + report.comets,
+                system.bundles.len()
+            );
+            assert_eq!(
+                report.planets.rejected(),
+                report.planet_failures.values().sum::<usize>()
+            );
+            assert_eq!(
+                report.moons.rejected(),
+                report.moon_failures.values().sum::<usize>()
+            );
+        }
+
+        let reports = systems.into_iter().map(|system| system.report).collect();
+        let report = GenerationReport::new(cfg.seed, reports, 0, Vec::new(), Default::default());
+        let json = serde_json::from_str::<serde_json::Value>(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["summary"]["systems"], cfg.num_stars);
+        assert_eq!(
+            json["summary"]["planets"]["accepted"],
+            report.summary.planets.accepted
+        );
+    }
+
+    /// Generates a galaxy with `cfg` and hands it to `f` along with its generator.
+    pub(super) fn with_galaxy(
+        cfg: GalaxyGeneratorConfig,
+        f: impl FnOnce(&mut GalaxyGenerator, Galaxy, GalaxyLayout),
+    ) {
+        let (constellation_names, star_props, substance_assets) = load_assets();
+        let mut world = World::new();
+        world.insert_resource(
+            utils::deser::<GlobalConfig>(format!("../{}", consts::GLOBAL_CONFIG)).unwrap(),
+        );
+        let mut mesh_assets = MeshAssets::from_world(&mut world);
+        let mut material_assets = MaterialAssets::default();
+        let mut meshes = Assets::<Mesh>::default();
+        let mut materials = Assets::<ColorMaterial>::default();
+
+        let mut generator = GalaxyGenerator::new(
+            cfg,
+            &constellation_names,
+            &star_props,
+            &substance_assets,
+            &mut mesh_assets,
+            &mut material_assets,
+            &mut meshes,
+            &mut materials,
+        );
+        generator.generate();
+        let (mut galaxy, mut bundles) = (Galaxy::default(), Vec::new());
+        let layout = generator.transfer_result(&mut galaxy, &mut bundles);
+        f(&mut generator, galaxy, layout);
+    }
+
+    #[test]
+    fn test_regenerate_system() {
+        with_galaxy(test_config(), |generator, mut galaxy, mut layout| {
+            let others = layout
+                .systems
+                .iter()
+                .enumerate()
+                .filter(|&(index, _)| index != 1)
+                .flat_map(|(_, system)| system.bodies.clone())
+                .filter_map(|id| galaxy.get_body(id).map(|body| (id, bits(&body))))
+                .collect::<Vec<_>>();
+            let overrides = SystemOverrides {
+                seed: Some(42),
+                ..Default::default()
+            };
+            let system = generator
+                .regenerate_system(&mut galaxy, &mut layout, 1, &overrides)
+                .ok()
+                .unwrap();
+
+            assert!(system
+                .removed
+                .iter()
+                .all(|&id| galaxy.get_body(id).is_none()
+                    && generator.mesh_assets.get(id).is_none()
+                    && generator.material_assets.get(id).is_none()));
+            assert!(system
+                .bundles
+                .iter()
+                .all(|(bundle, _)| generator.mesh_assets.get(bundle.id()).is_some()));
+            assert!(system
+                .bundles
+                .iter()
+                .all(|(bundle, _)| galaxy.get_body(bundle.id()).is_some()));
+            assert_eq!(layout.systems[1].bodies.len(), system.bundles.len());
+            let stars = &layout.systems[1].bodies[..layout.systems[1].num_stars];
+            assert!(system
+                .bundles
+                .iter()
+                .filter_map(|(bundle, _)| bundle.heating())
+                .all(|heating| stars.contains(&heating.star)));
+            for (id, body) in others {
+                assert_eq!(bits(&galaxy.get_body(id).unwrap()), body);
+            }
+
+            assert!(matches!(
+                generator.regenerate_system(&mut galaxy, &mut layout, 4, &overrides),
+                Err(SystemRegenerationError::UnknownSystem(4))
+            ));
+            let mut ring_cfg = layout.config.ring_cfg.clone();
+            ring_cfg.psb = 2.;
+            let overrides = SystemOverrides {
+                ring_cfg: Some(ring_cfg),
+                ..Default::default()
+            };
+            assert!(matches!(
+                generator.regenerate_system(&mut galaxy, &mut layout, 1, &overrides),
+                Err(SystemRegenerationError::InvalidOverrides(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn test_regenerate_moved_system() {
+        with_galaxy(test_config(), |generator, mut galaxy, mut layout| {
+            let stars = |galaxy: &Galaxy, layout: &GalaxyLayout| {
+                let system = &layout.systems[1];
+                let stars = system.bodies[..system.num_stars]
+                    .iter()
+                    .map(|&id| galaxy.get_body(id).unwrap())
+                    .collect::<Vec<_>>();
+                stream::barycentre(&stars)
+            };
+            let (start_pos, _) = stars(&galaxy, &layout);
+            for _ in 0..1000 {
+                galaxy.step();
+            }
+            let (pos, vel) = stars(&galaxy, &layout);
+            let moved = pos.distance(start_pos);
+            assert!(moved > 1.);
+
+            generator
+                .regenerate_system(&mut galaxy, &mut layout, 1, &SystemOverrides::default())
+                .ok()
+                .unwrap();
+            let (new_pos, new_vel) = stars(&galaxy, &layout);
+            assert!(new_pos.distance(pos) < 1e-3 * moved);
+            assert!(new_vel.distance(vel) < 1e-9 * vel.length());
+        });
+    }
 }
@@ -0,0 +1,188 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{consts::greek_alphabets, sim::components::BeltType};
+
+/// Unique names of the star systems of a galaxy.
+///
+/// Every pairing of a Greek letter and a constellation name is used once, in an order
+/// shuffled by the galaxy seed. Systems beyond that get catalogue numbers.
+pub struct StarNames {
+    constellations: Vec<String>,
+    order: Vec<u32>,
+}
+
+impl StarNames {
+    const CATALOGUE: &'static str = "HIP";
+    /// Keeps the shuffle apart from the galaxy's RNG, which has the same seed.
+    const SEED_SALT: u64 = 0x6e61_6d65_7321;
+
+    pub fn new<'a>(constellations: impl IntoIterator<Item = &'a str>, seed: u64) -> Self {
+        let mut unique = Vec::new();
+        for name in constellations {
+            if !unique.iter().any(|n| n == name) {
+                unique.push(name.to_string());
+            }
+        }
+        let num_letters = greek_alphabets::ALPHABETS_ENGLISH.len();
+        let mut order = (0..(unique.len() * num_letters) as u32).collect::<Vec<_>>();
+        order.shuffle(&mut StdRng::seed_from_u64(seed ^ Self::SEED_SALT));
+        Self {
+            constellations: unique,
+            order,
+        }
+    }
+
+    /// Name of the primary star of system `index`, which its companions, planets and
+    /// moons are named after.
+    pub fn star(&self, index: usize) -> String {
+        match self.order.get(index) {
+            Some(&pairing) => {
+                let num_letters = greek_alphabets::ALPHABETS_ENGLISH.len();
+                let letter = pairing as usize % num_letters;
+                let constellation = pairing as usize / num_letters;
+                format!(
+                    "{} {}",
+                    greek_alphabets::ALPHABETS_ENGLISH[letter],
+                    self.constellations[constellation]
+                )
+            }
+            None => format!("{} {}", Self::CATALOGUE, index + 1),
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.order.len()
+    }
+}
+
+/// Star `component` of a multiple system, like "Alpha Acamar B".
+pub fn star_component(system: &str, component: usize) -> String {
+    format!("{} {}", system, (b'A' + component as u8) as char)
+}
+
+/// Planets get lowercase letters from "b" on, like "Alpha Acamar b", in order of
+/// distance. Those past "z" are numbered instead.
+pub fn planet(host: &str, index: usize) -> String {
+    if index < 25 {
+        format!("{} {}", host, (b'b' + index as u8) as char)
+    } else {
+        format!("{} p{}", host, index + 1)
+    }
+}
+
+/// Moons get roman numerals from "I" on, like "Alpha Acamar b II".
+pub fn moon(planet: &str, index: usize) -> String {
+    format!("{} {}", planet, roman(index + 1))
+}
+
+/// Belt bodies are numbered within their belt.
+pub fn belt_body(host: &str, ty: BeltType, index: usize) -> String {
+    let belt = match ty {
+        BeltType::Asteroid => "Asteroid",
+        BeltType::Kuiper => "KBO",
+    };
+    format!("{} {} {}", host, belt, index + 1)
+}
+
+/// Comets are numbered within their system, like "Alpha Acamar Comet 1".
+pub fn comet(host: &str, index: usize) -> String {
+    format!("{} Comet {}", host, index + 1)
+}
+
+/// Rogue planets are numbered across the galaxy, like "Rogue 1".
+pub fn rogue_planet(index: usize) -> String {
+    format!("Rogue {}", index + 1)
+}
+
+/// Interstellar objects are numbered in order of arrival, like "1I".
+pub fn interstellar(index: usize) -> String {
+    format!("{}I", index + 1)
+}
+
+/// Roman numeral of `n`, or its decimal digits where there is none.
+pub fn roman(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    if n == 0 || n >= 4000 {
+        return n.to_string();
+    }
+
+    let mut numeral = String::new();
+    for (value, digits) in NUMERALS {
+        while n >= value {
+            numeral.push_str(digits);
+            n -= value;
+        }
+    }
+    numeral
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_roman() {
+        let numerals = [1, 4, 9, 14, 40, 90, 400, 1994, 3999, 4000].map(roman);
+        assert_eq!(
+            numerals,
+            [
+                "I",
+                "IV",
+                "IX",
+                "XIV",
+                "XL",
+                "XC",
+                "CD",
+                "MCMXCIV",
+                "MMMCMXCIX",
+                "4000"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_star_names() {
+        let names = StarNames::new(["Acamar", "Achernar", "Acamar"], 2);
+        assert_eq!(names.capacity(), 48);
+
+        let stars = (0..60).map(|i| names.star(i)).collect::<Vec<_>>();
+        assert_eq!(stars.iter().collect::<HashSet<_>>().len(), 60);
+        assert!(stars[..48]
+            .iter()
+            .all(|name| name.ends_with(" Acamar") || name.ends_with(" Achernar")));
+        assert_eq!(stars[48], "HIP 49");
+
+        let reseeded = StarNames::new(["Acamar", "Achernar"], 3);
+        assert!((0..48).any(|i| reseeded.star(i) != stars[i]));
+        assert_eq!(StarNames::new(["Acamar", "Achernar"], 2).star(7), stars[7]);
+    }
+
+    #[test]
+    fn test_body_names() {
+        assert_eq!(planet("Alpha Acamar", 0), "Alpha Acamar b");
+        assert_eq!(planet("Alpha Acamar", 24), "Alpha Acamar z");
+        assert_eq!(planet("Alpha Acamar", 25), "Alpha Acamar p26");
+        assert_eq!(moon("Alpha Acamar b", 1), "Alpha Acamar b II");
+        assert_eq!(star_component("Alpha Acamar", 1), "Alpha Acamar B");
+        assert_eq!(comet("Alpha Acamar", 0), "Alpha Acamar Comet 1");
+        assert_eq!(rogue_planet(0), "Rogue 1");
+        assert_eq!(interstellar(1), "2I");
+    }
+}
@@ -0,0 +1,586 @@
+use std::{
+    fmt::{Display, Formatter},
+    iter::Peekable,
+    str::CharIndices,
+};
+
+use rand::Rng;
+use rand_distr::{Distribution, Uniform};
+use serde::{Deserialize, Serialize};
+
+use super::config::UniformRange;
+
+/// Probability density over `x` in [0, 1], which spans the range of the distribution.
+///
+/// It doesn't have to be normalized.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PdfShape {
+    /// Expression of `x` with `+ - * / ^`, parentheses, the constants `pi` and `e`, and
+    /// the functions `exp ln log10 sqrt abs tanh sin cos min max`.
+    Expr(String),
+    /// Linear interpolation between `[x, density]` points sorted by `x`, and zero
+    /// outside them.
+    Table(Vec<[f64; 2]>),
+}
+
+/// How `x` of a [`PdfShape`] maps onto the range.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PdfScale {
+    #[default]
+    Linear,
+    Log,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PdfConfig {
+    pub pdf: PdfShape,
+    pub range: UniformRange<f64>,
+    #[serde(default)]
+    pub scale: PdfScale,
+}
+
+impl PdfConfig {
+    pub fn expr(expr: &str, min: f64, max: f64) -> Self {
+        Self {
+            pdf: PdfShape::Expr(expr.to_string()),
+            range: UniformRange::new(min, max),
+            scale: PdfScale::Linear,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum PdfError {
+    /// Position and message of a syntax error.
+    Syntax(usize, String),
+    UnsortedTable,
+    EmptyRange,
+    /// Log scales need a positive range.
+    NonPositiveLogRange,
+    Negative(f64),
+    NotFinite(f64),
+    /// Zero or infinite integral.
+    NotNormalizable,
+}
+
+impl Display for PdfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdfError::Syntax(pos, msg) => write!(f, "{} at {}", msg, pos),
+            PdfError::UnsortedTable => write!(f, "table is not sorted by x"),
+            PdfError::EmptyRange => write!(f, "empty range"),
+            PdfError::NonPositiveLogRange => write!(f, "log scale needs a positive range"),
+            PdfError::Negative(x) => write!(f, "negative density at x = {}", x),
+            PdfError::NotFinite(x) => write!(f, "density is not finite at x = {}", x),
+            PdfError::NotNormalizable => write!(f, "density does not integrate to a finite value"),
+        }
+    }
+}
+
+/// Rejection sampler of a [`PdfConfig`].
+///
+/// The envelope is piecewise constant over `NUM_BINS` bins, each set above the largest
+/// density probed in it, so peaked densities don't waste samples.
+#[derive(Clone)]
+pub struct PdfDistribution {
+    shape: Shape,
+    min: f64,
+    max: f64,
+    scale: PdfScale,
+    envelope: Vec<f64>,
+    /// Cumulative area of the envelope, ending at 1.
+    envelope_cdf: Vec<f64>,
+    /// Integral of the density over [0, 1].
+    norm: f64,
+}
+
+impl PdfDistribution {
+    const NUM_BINS: usize = 64;
+    const PROBES_PER_BIN: usize = 32;
+    /// Headroom over the probed maxima for peaks between probes.
+    const ENVELOPE_MARGIN: f64 = 1.25;
+
+    pub fn new(config: &PdfConfig) -> Result<Self, PdfError> {
+        let (min, max) = (config.range.min, config.range.max);
+        if min >= max || min.is_nan() || max.is_nan() {
+            return Err(PdfError::EmptyRange);
+        }
+        if config.scale == PdfScale::Log && min <= 0. {
+            return Err(PdfError::NonPositiveLogRange);
+        }
+
+        let shape = match &config.pdf {
+            PdfShape::Expr(expr) => Shape::Expr(Parser::new(expr).parse()?),
+            PdfShape::Table(points) => {
+                if points.windows(2).any(|w| w[0][0] > w[1][0]) {
+                    return Err(PdfError::UnsortedTable);
+                }
+                Shape::Table(points.clone())
+            }
+        };
+
+        // Probes include both ends of every bin.
+        let num_probes = Self::NUM_BINS * Self::PROBES_PER_BIN;
+        let mut probes = Vec::with_capacity(num_probes + 1);
+        for i in 0..=num_probes {
+            let x = i as f64 / num_probes as f64;
+            let y = shape.eval(x);
+            if !y.is_finite() {
+                return Err(PdfError::NotFinite(x));
+            }
+            if y < 0. {
+                return Err(PdfError::Negative(x));
+            }
+            probes.push(y);
+        }
+
+        let norm = probes
+            .windows(2)
+            .map(|w| (w[0] + w[1]) / 2. / num_probes as f64)
+            .sum::<f64>();
+        if norm <= 0. || !norm.is_finite() {
+            return Err(PdfError::NotNormalizable);
+        }
+
+        let envelope = probes
+            .windows(Self::PROBES_PER_BIN + 1)
+            .step_by(Self::PROBES_PER_BIN)
+            .map(|bin| bin.iter().fold(0f64, |max, &y| max.max(y)) * Self::ENVELOPE_MARGIN)
+            .collect::<Vec<_>>();
+        let total = envelope.iter().sum::<f64>();
+        let envelope_cdf = envelope
+            .iter()
+            .scan(0., |area, &y| {
+                *area += y / total;
+                Some(*area)
+            })
+            .collect();
+
+        Ok(Self {
+            shape,
+            min,
+            max,
+            scale: config.scale,
+            envelope,
+            envelope_cdf,
+            norm,
+        })
+    }
+
+    #[inline]
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    #[inline]
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Normalized density at `value`.
+    pub fn density(&self, value: f64) -> f64 {
+        if !(self.min..=self.max).contains(&value) {
+            return 0.;
+        }
+        let (x, dx_dvalue) = match self.scale {
+            PdfScale::Linear => (
+                (value - self.min) / (self.max - self.min),
+                1. / (self.max - self.min),
+            ),
+            PdfScale::Log => {
+                let ln_span = (self.max / self.min).ln();
+                ((value / self.min).ln() / ln_span, 1. / (value * ln_span))
+            }
+        };
+        self.shape.eval(x) / self.norm * dx_dvalue
+    }
+
+    /// Samples between `min` and `max` instead of the configured range.
+    pub fn within(&self, min: f64, max: f64) -> PdfWithin<'_> {
+        PdfWithin {
+            pdf: self,
+            min,
+            max,
+        }
+    }
+
+    /// Samples `x` in [0, 1].
+    fn sample_unit<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let unit = Uniform::new(0., 1.);
+        loop {
+            let area = rng.sample(unit);
+            let bin = self
+                .envelope_cdf
+                .partition_point(|&cdf| cdf < area)
+                .min(Self::NUM_BINS - 1);
+            let x = (bin as f64 + rng.sample(unit)) / Self::NUM_BINS as f64;
+            if rng.sample(unit) * self.envelope[bin] < self.shape.eval(x) {
+                return x;
+            }
+        }
+    }
+
+    fn map(&self, x: f64, min: f64, max: f64) -> f64 {
+        match self.scale {
+            PdfScale::Linear => x * (max - min) + min,
+            PdfScale::Log => min * (max / min).powf(x),
+        }
+    }
+}
+
+impl Distribution<f64> for PdfDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.map(self.sample_unit(rng), self.min, self.max)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct PdfWithin<'a> {
+    pdf: &'a PdfDistribution,
+    min: f64,
+    max: f64,
+}
+
+impl Distribution<f64> for PdfWithin<'_> {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.pdf.map(self.pdf.sample_unit(rng), self.min, self.max)
+    }
+}
+
+#[derive(Clone)]
+enum Shape {
+    Expr(Expr),
+    Table(Vec<[f64; 2]>),
+}
+
+impl Shape {
+    fn eval(&self, x: f64) -> f64 {
+        match self {
+            Shape::Expr(expr) => expr.eval(x),
+            Shape::Table(points) => {
+                let i = points.partition_point(|p| p[0] < x);
+                match (i.checked_sub(1).map(|i| points[i]), points.get(i)) {
+                    (_, Some(&[x1, y1])) if x1 == x => y1,
+                    (Some([x0, y0]), Some([x1, y1])) => y0 + (y1 - y0) * (x - x0) / (x1 - x0),
+                    _ => 0.,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Expr {
+    Num(f64),
+    X,
+    Neg(Box<Expr>),
+    Bin(char, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, x: f64) -> f64 {
+        match self {
+            Expr::Num(n) => *n,
+            Expr::X => x,
+            Expr::Neg(e) => -e.eval(x),
+            Expr::Bin(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(x), rhs.eval(x));
+                match op {
+                    '+' => lhs + rhs,
+                    '-' => lhs - rhs,
+                    '*' => lhs * rhs,
+                    '/' => lhs / rhs,
+                    _ => lhs.powf(rhs),
+                }
+            }
+            Expr::Call(func, args) => {
+                let a = args[0].eval(x);
+                match func {
+                    Func::Exp => a.exp(),
+                    Func::Ln => a.ln(),
+                    Func::Log10 => a.log10(),
+                    Func::Sqrt => a.sqrt(),
+                    Func::Abs => a.abs(),
+                    Func::Tanh => a.tanh(),
+                    Func::Sin => a.sin(),
+                    Func::Cos => a.cos(),
+                    Func::Min => a.min(args[1].eval(x)),
+                    Func::Max => a.max(args[1].eval(x)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Func {
+    Exp,
+    Ln,
+    Log10,
+    Sqrt,
+    Abs,
+    Tanh,
+    Sin,
+    Cos,
+    Min,
+    Max,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<(Self, usize)> {
+        Some(match name {
+            "exp" => (Func::Exp, 1),
+            "ln" => (Func::Ln, 1),
+            "log10" => (Func::Log10, 1),
+            "sqrt" => (Func::Sqrt, 1),
+            "abs" => (Func::Abs, 1),
+            "tanh" => (Func::Tanh, 1),
+            "sin" => (Func::Sin, 1),
+            "cos" => (Func::Cos, 1),
+            "min" => (Func::Min, 2),
+            "max" => (Func::Max, 2),
+            _ => return None,
+        })
+    }
+}
+
+/// Recursive descent parser where `^` binds tighter than unary minus and is right
+/// associative.
+struct Parser<'a> {
+    src: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            chars: src.char_indices().peekable(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Expr, PdfError> {
+        let expr = self.expr()?;
+        match self.peek() {
+            Some((pos, c)) => Err(PdfError::Syntax(pos, format!("unexpected '{}'", c))),
+            None => Ok(expr),
+        }
+    }
+
+    fn peek(&mut self) -> Option<(usize, char)> {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    fn eat(&mut self, expected: char) -> Result<(), PdfError> {
+        match self.peek() {
+            Some((_, c)) if c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            Some((pos, c)) => Err(PdfError::Syntax(
+                pos,
+                format!("expected '{}', found '{}'", expected, c),
+            )),
+            None => Err(self.eof(format!("expected '{}'", expected))),
+        }
+    }
+
+    fn eof(&self, msg: String) -> PdfError {
+        PdfError::Syntax(self.src.len(), msg)
+    }
+
+    fn expr(&mut self) -> Result<Expr, PdfError> {
+        let mut lhs = self.term()?;
+        while let Some((_, op @ ('+' | '-'))) = self.peek() {
+            self.chars.next();
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, PdfError> {
+        let mut lhs = self.unary()?;
+        while let Some((_, op @ ('*' | '/'))) = self.peek() {
+            self.chars.next();
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, PdfError> {
+        if let Some((_, '-')) = self.peek() {
+            self.chars.next();
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, PdfError> {
+        let base = self.atom()?;
+        if let Some((_, '^')) = self.peek() {
+            self.chars.next();
+            return Ok(Expr::Bin('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, PdfError> {
+        let Some((start, c)) = self.peek() else {
+            return Err(self.eof("unexpected end".to_string()));
+        };
+
+        if c == '(' {
+            self.chars.next();
+            let expr = self.expr()?;
+            self.eat(')')?;
+            return Ok(expr);
+        }
+
+        if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some((i, c)) = self.chars.peek().copied() {
+                let exponent_sign = (c == '-' || c == '+') && self.src[..i].ends_with(['e', 'E']);
+                if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                self.chars.next();
+            }
+            return self.src[start..end]
+                .parse()
+                .map(Expr::Num)
+                .map_err(|_| PdfError::Syntax(start, "invalid number".to_string()));
+        }
+
+        if c.is_ascii_alphabetic() {
+            let mut end = start;
+            while let Some((i, c)) = self.chars.peek().copied() {
+                if !c.is_ascii_alphanumeric() {
+                    break;
+                }
+                end = i + 1;
+                self.chars.next();
+            }
+            let name = &self.src[start..end];
+            return match name {
+                "x" => Ok(Expr::X),
+                "pi" => Ok(Expr::Num(std::f64::consts::PI)),
+                "e" => Ok(Expr::Num(std::f64::consts::E)),
+                _ => {
+                    let Some((func, arity)) = Func::from_name(name) else {
+                        return Err(PdfError::Syntax(start, format!("unknown name '{}'", name)));
+                    };
+                    self.eat('(')?;
+                    let mut args = vec![self.expr()?];
+                    for _ in 1..arity {
+                        self.eat(',')?;
+                        args.push(self.expr()?);
+                    }
+                    self.eat(')')?;
+                    Ok(Expr::Call(func, args))
+                }
+            };
+        }
+
+        Err(PdfError::Syntax(start, format!("unexpected '{}'", c)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn eval(expr: &str, x: f64) -> f64 {
+        Parser::new(expr).parse().ok().unwrap().eval(x)
+    }
+
+    #[test]
+    fn test_expr() {
+        assert_eq!(eval("1 + 2 * 3", 0.), 7.);
+        assert_eq!(eval("-2^2", 0.), -4.);
+        assert_eq!(eval("2^3^2", 0.), 512.);
+        assert_eq!(eval("(1 - x) / 2", 0.5), 0.25);
+        assert_eq!(eval("max(x, 1e-1) + min(2.5E1, 3)", 0.), 3.1);
+        assert_eq!(eval("exp(0) * abs(-x)", 2.), 2.);
+        assert!(matches!(
+            Parser::new("1 + ").parse(),
+            Err(PdfError::Syntax(4, _))
+        ));
+        assert!(matches!(
+            Parser::new("foo(x)").parse(),
+            Err(PdfError::Syntax(0, _))
+        ));
+        assert!(matches!(
+            Parser::new("max(x)").parse(),
+            Err(PdfError::Syntax(5, _))
+        ));
+    }
+
+    #[test]
+    fn test_normalization() {
+        let linear = PdfDistribution::new(&PdfConfig::expr("x", 2., 4.))
+            .ok()
+            .unwrap();
+        let log = PdfDistribution::new(&PdfConfig {
+            scale: PdfScale::Log,
+            ..PdfConfig::expr("1 - x", 1., 100.)
+        })
+        .ok()
+        .unwrap();
+        for pdf in [linear, log] {
+            let n = 10000;
+            let step = (pdf.max() - pdf.min()) / n as f64;
+            let integral = (0..n)
+                .map(|i| pdf.density(pdf.min() + (i as f64 + 0.5) * step) * step)
+                .sum::<f64>();
+            assert!((integral - 1.).abs() < 1e-3, "{}", integral);
+        }
+
+        let errors = [
+            ("x - 0.5", PdfError::Negative(0.)),
+            ("1 / x", PdfError::NotFinite(0.)),
+            ("0 * x", PdfError::NotNormalizable),
+        ];
+        for (expr, expected) in errors {
+            match PdfDistribution::new(&PdfConfig::expr(expr, 0., 1.)) {
+                Err(err) => assert!(err == expected, "{}", err),
+                Ok(_) => panic!("{} should be rejected", expr),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sampling() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // Sharp peak at the lower end, which a single global bound would sample slowly.
+        let peaked = PdfDistribution::new(&PdfConfig::expr("exp(-50 * x)", 0., 1.))
+            .ok()
+            .unwrap();
+        let n = 20000;
+        let mean = (0..n).map(|_| rng.sample(&peaked)).sum::<f64>() / n as f64;
+        assert!((mean - 0.02).abs() < 0.002, "{}", mean);
+
+        let table = PdfDistribution::new(&PdfConfig {
+            pdf: PdfShape::Table(vec![[0., 0.], [0.5, 1.], [1., 0.]]),
+            ..PdfConfig::expr("", 10., 20.)
+        })
+        .ok()
+        .unwrap();
+        let samples = (0..n).map(|_| rng.sample(&table)).collect::<Vec<_>>();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        assert!(samples.iter().all(|x| (10. ..=20.).contains(x)));
+        assert!((mean - 15.).abs() < 0.1, "{}", mean);
+
+        let within = (0..n)
+            .map(|_| rng.sample(table.within(0., 1.)))
+            .sum::<f64>()
+            / n as f64;
+        assert!((within - 0.5).abs() < 0.01, "{}", within);
+    }
+}
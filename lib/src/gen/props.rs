@@ -1,20 +1,22 @@
 use rand::Rng;
-use rand_distr::Uniform;
 
 use crate::{
     assets::settings::PackedStarInfo,
-    consts::{self, greek_alphabets},
+    consts,
     math::{self},
     sci::{
-        chemistry::{MatterState, SubstanceContent},
-        physics,
+        chemistry::{MatterState, Substance, SubstanceContent},
+        evolution, habitability, physics,
     },
     sim::{
-        bundles::{MoonBundle, PlanetBundle, StarBundle},
+        bundles::{
+            BeltBodyBundle, CometBundle, MoonBundle, PlanetBundle, RoguePlanetBundle, StarBundle,
+        },
         components::{
-            CelestialBodyAtmosphere, CelestialBodyColor, CelestialBodyCrust,
+            BeltBody, BeltType, CelestialBodyAtmosphere, CelestialBodyColor, CelestialBodyCrust,
             CelestialBodyEffectiveTemp, CelestialBodyId, CelestialBodyName,
-            CelestialBodySubstanceProps, Moon, Planet, PlanetType, Star, StarLuminosity,
+            CelestialBodySubstanceProps, Comet, Moon, Planet, PlanetType, RoguePlanet, Star,
+            StarLuminosity, StellarEvolution, StellarHeating, StellarPhase, StellarState,
         },
         resources::CelestialBody,
     },
@@ -22,50 +24,84 @@ use crate::{
 
 use super::{
     distr::{
-        self, GasGiantCompositionDistribution, IceGiantCompositionDistribution,
-        RockyAtmosphereCompositionDistribution, RockyBodyAtmoDensityDistribution,
-        RockyBodyCrustDensityDistribution, RockyCrustCompositionDistribution,
+        self, CometCompositionDistribution, GasGiantCompositionDistribution,
+        IceGiantCompositionDistribution, IcyCrustCompositionDistribution,
+        RockyAtmosphereCompositionDistribution, RockyCrustCompositionDistribution,
         StarCompositionDistribution,
     },
-    GalaxyGenerator,
+    SystemGenerator,
 };
 
-impl<'a> GalaxyGenerator<'a> {
+impl<'a> SystemGenerator<'a> {
+    /// Properties of a star interpolated as `lerped`. Neutron stars and black holes
+    /// aren't tabulated, so they come with the `remnant` evolution they were generated
+    /// from.
     pub fn gen_star_props(
         &mut self,
         id: CelestialBodyId,
-        lerp_factor: f64,
-        bound_floor: &PackedStarInfo,
-        bound_ceil: &PackedStarInfo,
+        lerped: &PackedStarInfo,
+        remnant: Option<StellarEvolution>,
     ) -> StarBundle {
-        let name_distr = Uniform::new(0, self.constellation_names.len());
-        let constellation = loop {
-            let i = self.rng.sample(name_distr);
-            let name = self.constellation_names.get_cloned(i);
-            if !self.existed_constellation_names.contains(&name) {
-                break name;
-            }
-        };
-        let prefix = greek_alphabets::ALPHABETS_GREEK[self
-            .rng
-            .gen_range(0..greek_alphabets::ALPHABETS_GREEK.len())]
-        .to_string();
-        let lerped = bound_floor.lerp(bound_ceil, lerp_factor);
         let content = self.rng.sample(StarCompositionDistribution);
         let density = math::mass_radius_to_density(lerped.mass, lerped.radius);
+        let evolution = match remnant {
+            Some(evolution) => evolution,
+            None => self.gen_star_evolution(lerped),
+        };
 
         StarBundle {
             id,
-            name: CelestialBodyName(format!("{} {}", prefix, constellation)),
+            name: CelestialBodyName(self.star_names.star(self.system_id)),
             class: lerped.class,
             composition: CelestialBodyCrust { content, density },
             effective_temp: CelestialBodyEffectiveTemp(lerped.effective_temp),
             luminosity: StarLuminosity(lerped.luminosity),
+            habitable_zone: habitability::habitable_zone(lerped.luminosity, lerped.effective_temp),
+            evolution,
             color: CelestialBodyColor(lerped.color.into()),
             tag: Star,
         }
     }
 
+    fn gen_star_evolution(&mut self, lerped: &PackedStarInfo) -> StellarEvolution {
+        // The table describes the star at whatever age it is generated at. Evolved stars
+        // are generated as they enter their phase.
+        let phase = lerped.class.lum.phase();
+        let state = StellarState {
+            phase,
+            mass: lerped.mass,
+            radius: lerped.radius,
+            luminosity: lerped.luminosity,
+            effective_temp: lerped.effective_temp,
+        };
+
+        // Brown dwarfs never fuse hydrogen, so they stay as they are generated.
+        let (initial_mass, lifetime, age) = if lerped.class.ty.is_substellar() {
+            let age = self.rng.sample(self.cfg.star_cfg.age) * consts::SUN_MAIN_SEQUENCE_LIFETIME;
+            (lerped.mass, f64::INFINITY, age)
+        } else {
+            let initial_mass = match phase {
+                StellarPhase::WhiteDwarf => evolution::progenitor_mass(lerped.mass),
+                _ => lerped.mass,
+            };
+            let lifetime = evolution::main_sequence_lifetime(initial_mass);
+            let age = match phase {
+                StellarPhase::MainSequence => self.rng.sample(self.cfg.star_cfg.age) * lifetime,
+                _ => evolution::phase_age(phase, lifetime, lerped.effective_temp),
+            };
+            (initial_mass, lifetime, age)
+        };
+
+        StellarEvolution {
+            initial_mass,
+            lifetime,
+            age,
+            generated_age: age,
+            generated: state,
+            state,
+        }
+    }
+
     pub fn gen_planet_props(
         &mut self,
         id: CelestialBodyId,
@@ -78,6 +114,78 @@ impl<'a> GalaxyGenerator<'a> {
         PlanetBundle,
         Option<CelestialBodyCrust>,
         Option<CelestialBodyAtmosphere>,
+    ) {
+        let eff_temp = physics::planetary_eq_temp_from_temp(
+            star_bundle.effective_temp.0,
+            star.radius(),
+            0.,
+            planet.pos().distance(star.pos()),
+        ) * consts::PLANET_EFFCETIVE_TEMP_SCALE;
+        let (color, eff_temp, substance_props, crust, atmosphere) =
+            self.gen_planet_matter(density, ty, eff_temp);
+
+        (
+            PlanetBundle {
+                id,
+                name: CelestialBodyName("".to_string()),
+                ty,
+                color,
+                effective_temp: CelestialBodyEffectiveTemp(eff_temp),
+                substance_props,
+                heating: heating(star_bundle, eff_temp),
+                tag: Planet,
+            },
+            crust,
+            atmosphere,
+        )
+    }
+
+    /// Properties of a planet of `mass` in simulation units that no star heats.
+    pub fn gen_rogue_planet_props(
+        &mut self,
+        id: CelestialBodyId,
+        mass: f64,
+        density: f64,
+        ty: PlanetType,
+    ) -> (
+        RoguePlanetBundle,
+        Option<CelestialBodyCrust>,
+        Option<CelestialBodyAtmosphere>,
+    ) {
+        let eff_temp =
+            physics::internal_heat_temp(mass / consts::EARTH_MASS / consts::PLANET_MASS_SCALE);
+        let (color, eff_temp, substance_props, crust, atmosphere) =
+            self.gen_planet_matter(density, ty, eff_temp);
+
+        (
+            RoguePlanetBundle {
+                id,
+                name: CelestialBodyName("".to_string()),
+                ty,
+                color,
+                effective_temp: CelestialBodyEffectiveTemp(eff_temp),
+                substance_props,
+                tag: Planet,
+                rogue: RoguePlanet,
+            },
+            crust,
+            atmosphere,
+        )
+    }
+
+    /// Atmosphere, crust and colour of a planet at `eff_temp`, along with the effective
+    /// temperature they adjust it to.
+    fn gen_planet_matter(
+        &mut self,
+        density: f64,
+        ty: PlanetType,
+        mut eff_temp: f64,
+    ) -> (
+        CelestialBodyColor,
+        f64,
+        CelestialBodySubstanceProps,
+        Option<CelestialBodyCrust>,
+        Option<CelestialBodyAtmosphere>,
     ) {
         let mut atmosphere = match ty {
             PlanetType::GasGiant => Some(CelestialBodyAtmosphere {
@@ -89,7 +197,7 @@ impl<'a> GalaxyGenerator<'a> {
                 density,
             }),
             PlanetType::Rocky => {
-                let density = self.rng.sample(RockyBodyAtmoDensityDistribution);
+                let density = self.rng.sample(&self.distrs.rocky_atmo_density);
                 if density < consts::ATMOSPHERE_DENSITY_MIN {
                     None
                 } else {
@@ -101,13 +209,6 @@ impl<'a> GalaxyGenerator<'a> {
             }
         };
 
-        let mut eff_temp = physics::planetary_eq_temp_from_temp(
-            star_bundle.effective_temp.0,
-            star.radius(),
-            0.,
-            (planet.pos() - star.pos()).dot(consts::DEFAULT_BODY_EXTEND_AXIS),
-        ) * consts::PLANET_EFFCETIVE_TEMP_SCALE;
-
         let mut crust = match ty {
             PlanetType::GasGiant | PlanetType::IceGiant => None,
             PlanetType::Rocky => Some(CelestialBodyCrust {
@@ -148,15 +249,9 @@ impl<'a> GalaxyGenerator<'a> {
         };
 
         (
-            PlanetBundle {
-                id,
-                name: CelestialBodyName("".to_string()),
-                ty,
-                color: CelestialBodyColor(color.into()),
-                effective_temp: CelestialBodyEffectiveTemp(eff_temp),
-                substance_props,
-                tag: Planet,
-            },
+            CelestialBodyColor(color.into()),
+            eff_temp,
+            substance_props,
             crust,
             atmosphere,
         )
@@ -174,7 +269,7 @@ impl<'a> GalaxyGenerator<'a> {
         Option<CelestialBodyAtmosphere>,
     ) {
         let mut atmosphere = {
-            let density = self.rng.sample(RockyBodyAtmoDensityDistribution);
+            let density = self.rng.sample(&self.distrs.rocky_atmo_density);
             if density < consts::ATMOSPHERE_DENSITY_MIN {
                 None
             } else {
@@ -189,12 +284,12 @@ impl<'a> GalaxyGenerator<'a> {
             star_bundle.effective_temp.0,
             star.radius(),
             0.,
-            (moon.pos() - star.pos()).dot(consts::DEFAULT_BODY_EXTEND_AXIS),
+            moon.pos().distance(star.pos()),
         ) * consts::PLANET_EFFCETIVE_TEMP_SCALE;
 
         let mut crust = Some(CelestialBodyCrust {
             content: self.rng.sample(RockyCrustCompositionDistribution),
-            density: self.rng.sample(RockyBodyCrustDensityDistribution),
+            density: self.rng.sample(&self.distrs.rocky_crust_density),
         });
 
         let substance_props = self.adjust(&mut atmosphere, &mut crust, &mut eff_temp);
@@ -218,6 +313,7 @@ impl<'a> GalaxyGenerator<'a> {
                 color: CelestialBodyColor(color.into()),
                 effective_temp: CelestialBodyEffectiveTemp(eff_temp),
                 substance_props,
+                heating: heating(star_bundle, eff_temp),
                 tag: Moon,
             },
             crust,
@@ -225,6 +321,139 @@ impl<'a> GalaxyGenerator<'a> {
         )
     }
 
+    /// Picks the composition and density of a giant planet's ring particles.
+    ///
+    /// Rings are icy when water freezes at the planet's effective temperature and rocky
+    /// otherwise. Substances that wouldn't be solid there are left out, and `None` is
+    /// returned if nothing is left.
+    pub fn gen_ring_props(&mut self, planet: &PlanetBundle) -> Option<(SubstanceContent, f64)> {
+        let eff_temp = planet.effective_temp.0;
+        let is_solid = |sub: Substance| {
+            planet
+                .substance_props
+                .get(sub)
+                .is_some_and(|prop| prop.melting_point > eff_temp)
+        };
+
+        let (content, density) = if is_solid(Substance::Water) {
+            (
+                self.rng.sample(IcyCrustCompositionDistribution),
+                self.rng.sample(&self.distrs.icy_crust_density),
+            )
+        } else {
+            (
+                self.rng.sample(RockyCrustCompositionDistribution),
+                self.rng.sample(&self.distrs.rocky_crust_density),
+            )
+        };
+        let content = SubstanceContent::new(
+            content
+                .iter()
+                .filter(|(sub, _)| is_solid(**sub))
+                .map(|(sub, content)| (*sub, *content))
+                .collect(),
+        );
+
+        (content.num_compounds() > 0).then(|| (content.normalized(), density))
+    }
+
+    /// Belt bodies have no atmosphere, so volatiles that would boil off are lost.
+    #[allow(clippy::too_many_arguments)]
+    pub fn gen_belt_body_props(
+        &mut self,
+        id: CelestialBodyId,
+        body: &CelestialBody,
+        mass: f64,
+        density: f64,
+        ty: BeltType,
+        star: &CelestialBody,
+        star_bundle: &StarBundle,
+    ) -> (BeltBodyBundle, CelestialBodyCrust) {
+        let mut eff_temp = physics::planetary_eq_temp_from_temp(
+            star_bundle.effective_temp.0,
+            star.radius(),
+            0.,
+            body.pos().distance(star.pos()),
+        ) * consts::PLANET_EFFCETIVE_TEMP_SCALE;
+
+        let content = match ty {
+            BeltType::Asteroid => self.rng.sample(RockyCrustCompositionDistribution),
+            BeltType::Kuiper => self.rng.sample(IcyCrustCompositionDistribution),
+        };
+        let mut crust = Some(CelestialBodyCrust { content, density });
+
+        let substance_props = self.adjust(&mut None, &mut crust, &mut eff_temp);
+        let crust = crust.unwrap();
+        let color = crust
+            .content
+            .estimate_color(self.substance_assets, MatterState::Solid);
+
+        (
+            BeltBodyBundle {
+                id,
+                name: CelestialBodyName("".to_string()),
+                color: CelestialBodyColor(color.into()),
+                effective_temp: CelestialBodyEffectiveTemp(eff_temp),
+                substance_props,
+                heating: heating(star_bundle, eff_temp),
+                ty,
+                tag: BeltBody { mass },
+            },
+            crust,
+        )
+    }
+
+    /// Comets are made of ices, and become active once their surface rises above the
+    /// lowest sublimation point among them.
+    pub fn gen_comet_props(
+        &mut self,
+        id: CelestialBodyId,
+        body: &CelestialBody,
+        mass: f64,
+        density: f64,
+        star: &CelestialBody,
+        star_bundle: &StarBundle,
+    ) -> (CometBundle, CelestialBodyCrust) {
+        let mut eff_temp = physics::planetary_eq_temp_from_temp(
+            star_bundle.effective_temp.0,
+            star.radius(),
+            0.,
+            body.pos().distance(star.pos()),
+        ) * consts::PLANET_EFFCETIVE_TEMP_SCALE;
+
+        let mut crust = Some(CelestialBodyCrust {
+            content: self.rng.sample(CometCompositionDistribution),
+            density,
+        });
+        let substance_props = self.adjust(&mut None, &mut crust, &mut eff_temp);
+        let crust = crust.unwrap();
+        let color = crust
+            .content
+            .estimate_color(self.substance_assets, MatterState::Solid);
+        let sublimation_point = crust
+            .content
+            .iter()
+            .map(|(sub, _)| self.substance_assets.get(*sub).sublimation_point)
+            .fold(f64::INFINITY, f64::min);
+
+        (
+            CometBundle {
+                id,
+                name: CelestialBodyName("".to_string()),
+                color: CelestialBodyColor(color.into()),
+                effective_temp: CelestialBodyEffectiveTemp(eff_temp),
+                substance_props,
+                heating: heating(star_bundle, eff_temp),
+                tag: Comet {
+                    mass,
+                    sublimation_point,
+                    mass_loss_rate: self.cfg.comet_cfg.mass_loss_rate,
+                },
+            },
+            crust,
+        )
+    }
+
     fn adjust(
         &self,
         atmo: &mut Option<CelestialBodyAtmosphere>,
@@ -289,3 +518,12 @@ impl<'a> GalaxyGenerator<'a> {
         CelestialBodySubstanceProps::new(substance_props.to_vec())
     }
 }
+
+#[inline]
+fn heating(star_bundle: &StarBundle, eff_temp: f64) -> StellarHeating {
+    StellarHeating {
+        star: star_bundle.id,
+        luminosity: star_bundle.luminosity.0,
+        effective_temp: eff_temp,
+    }
+}
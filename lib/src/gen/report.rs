@@ -0,0 +1,334 @@
+use std::{collections::BTreeMap, io::Error, path::Path, time::Duration};
+
+use bevy::ecs::system::Resource;
+use serde::Serialize;
+
+use crate::{sim::bundles::CelestialBodyBundle, utils};
+
+use super::err::{
+    BeltGenerationError, CometGenerationError, MoonGenerationError, PlanetGenerationError,
+};
+
+/// What happened while a galaxy was generated, for regression tracking.
+#[derive(Resource, Clone, Default, Serialize)]
+pub struct GenerationReport {
+    pub seed: u64,
+    pub systems: Vec<SystemReport>,
+    /// Free-floating planets scattered between the systems.
+    pub rogue_planets: usize,
+    /// Bodies removed by the pre-simulation, in the order they were removed.
+    pub culled: Vec<CulledBody>,
+    pub timings: PhaseTimings,
+    pub summary: ReportSummary,
+}
+
+impl GenerationReport {
+    pub fn new(
+        seed: u64,
+        systems: Vec<SystemReport>,
+        rogue_planets: usize,
+        culled: Vec<CulledBody>,
+        timings: PhaseTimings,
+    ) -> Self {
+        let summary = ReportSummary::new(&systems, &culled);
+        Self {
+            seed,
+            systems,
+            rogue_planets,
+            culled,
+            timings,
+            summary,
+        }
+    }
+
+    #[inline]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    #[inline]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        utils::ser(path, self)
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BodyCounts {
+    pub attempted: usize,
+    pub accepted: usize,
+}
+
+impl BodyCounts {
+    #[inline]
+    pub fn rejected(&self) -> usize {
+        self.attempted - self.accepted
+    }
+
+    fn add(&mut self, other: BodyCounts) {
+        self.attempted += other.attempted;
+        self.accepted += other.accepted;
+    }
+}
+
+/// Bodies generated for one star system, and why the rest were rejected.
+#[derive(Clone, Default, Serialize)]
+pub struct SystemReport {
+    pub index: usize,
+    pub seed: u64,
+    /// Whether the bodies around the stars were generated too.
+    pub complete: bool,
+    /// Stars, brown dwarfs and compact objects.
+    pub stars: usize,
+    pub planets: BodyCounts,
+    pub moons: BodyCounts,
+    pub belt_bodies: usize,
+    pub comets: usize,
+    pub planet_failures: BTreeMap<PlanetGenerationError, usize>,
+    pub moon_failures: BTreeMap<MoonGenerationError, usize>,
+    pub belt_failures: BTreeMap<BeltGenerationError, usize>,
+    pub comet_failures: BTreeMap<CometGenerationError, usize>,
+    /// Bodies of the system removed by the pre-simulation.
+    pub culled: usize,
+    pub elapsed_ms: f64,
+}
+
+impl SystemReport {
+    pub fn new(index: usize, seed: u64) -> Self {
+        Self {
+            index,
+            seed,
+            ..Default::default()
+        }
+    }
+
+    pub fn planet_failed(&mut self, err: PlanetGenerationError) {
+        self.planets.attempted += 1;
+        *self.planet_failures.entry(err).or_default() += 1;
+    }
+
+    pub fn moon_failed(&mut self, err: MoonGenerationError) {
+        self.moons.attempted += 1;
+        *self.moon_failures.entry(err).or_default() += 1;
+    }
+
+    pub fn belt_failed(&mut self, err: BeltGenerationError) {
+        *self.belt_failures.entry(err).or_default() += 1;
+    }
+
+    pub fn comet_failed(&mut self, err: CometGenerationError) {
+        *self.comet_failures.entry(err).or_default() += 1;
+    }
+
+    /// Counts the bodies that made it into the system.
+    pub fn count_bodies(&mut self, bundles: &[CelestialBodyBundle]) {
+        for bundle in bundles {
+            match BodyKind::of(bundle) {
+                BodyKind::Star | BodyKind::BrownDwarf | BodyKind::CompactObject => self.stars += 1,
+                BodyKind::Planet => {
+                    self.planets.attempted += 1;
+                    self.planets.accepted += 1;
+                }
+                BodyKind::Moon => {
+                    self.moons.attempted += 1;
+                    self.moons.accepted += 1;
+                }
+                BodyKind::BeltBody => self.belt_bodies += 1,
+                BodyKind::Comet => self.comets += 1,
+                // Only generated between the systems.
+                BodyKind::RoguePlanet => {}
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BodyKind {
+    Star,
+    BrownDwarf,
+    CompactObject,
+    Planet,
+    RoguePlanet,
+    Moon,
+    BeltBody,
+    Comet,
+}
+
+impl BodyKind {
+    pub fn of(bundle: &CelestialBodyBundle) -> Self {
+        match bundle {
+            CelestialBodyBundle::Star(_) => BodyKind::Star,
+            CelestialBodyBundle::BrownDwarf(_) => BodyKind::BrownDwarf,
+            CelestialBodyBundle::CompactObject { .. } => BodyKind::CompactObject,
+            CelestialBodyBundle::Planet { .. } => BodyKind::Planet,
+            CelestialBodyBundle::RoguePlanet { .. } => BodyKind::RoguePlanet,
+            CelestialBodyBundle::Moon { .. } => BodyKind::Moon,
+            CelestialBodyBundle::BeltBody { .. } => BodyKind::BeltBody,
+            CelestialBodyBundle::Comet { .. } => BodyKind::Comet,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct CulledBody {
+    pub id: usize,
+    pub name: String,
+    pub kind: BodyKind,
+    /// `None` for the bodies outside every system, like rogue planets.
+    pub system: Option<usize>,
+    /// Pre-simulation step the body was removed at.
+    pub step: usize,
+}
+
+/// Wall-clock time of each phase of the generation, in milliseconds.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct PhaseTimings {
+    /// Placing the systems and generating their bodies.
+    pub systems_ms: f64,
+    /// Adding the bodies to the galaxy and building their meshes.
+    pub commit_ms: f64,
+    pub sim_and_cull_ms: f64,
+    pub total_ms: f64,
+}
+
+impl PhaseTimings {
+    #[inline]
+    pub fn ms(duration: Duration) -> f64 {
+        duration.as_secs_f64() * 1e3
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Stats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+impl Stats {
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Self {
+        let (mut min, mut max, mut sum, mut n) = (f64::INFINITY, f64::NEG_INFINITY, 0., 0);
+        for value in values {
+            min = min.min(value);
+            max = max.max(value);
+            sum += value;
+            n += 1;
+        }
+        if n == 0 {
+            return Self::default();
+        }
+        Self {
+            min,
+            max,
+            mean: sum / n as f64,
+        }
+    }
+}
+
+/// Totals over every system of the galaxy.
+#[derive(Clone, Default, Serialize)]
+pub struct ReportSummary {
+    pub systems: usize,
+    pub stars: usize,
+    pub planets: BodyCounts,
+    pub moons: BodyCounts,
+    pub belt_bodies: usize,
+    pub comets: usize,
+    pub culled: usize,
+    pub planet_failures: BTreeMap<PlanetGenerationError, usize>,
+    pub moon_failures: BTreeMap<MoonGenerationError, usize>,
+    pub belt_failures: BTreeMap<BeltGenerationError, usize>,
+    pub comet_failures: BTreeMap<CometGenerationError, usize>,
+    /// Accepted planets per complete system.
+    pub planets_per_system: Stats,
+    /// Accepted moons per accepted planet.
+    pub moons_per_planet: f64,
+    pub system_ms: Stats,
+}
+
+impl ReportSummary {
+    pub fn new(systems: &[SystemReport], culled: &[CulledBody]) -> Self {
+        let mut summary = Self {
+            systems: systems.len(),
+            culled: culled.len(),
+            planets_per_system: Stats::of(
+                systems
+                    .iter()
+                    .filter(|system| system.complete)
+                    .map(|system| system.planets.accepted as f64),
+            ),
+            system_ms: Stats::of(systems.iter().map(|system| system.elapsed_ms)),
+            ..Default::default()
+        };
+        for system in systems {
+            summary.stars += system.stars;
+            summary.planets.add(system.planets);
+            summary.moons.add(system.moons);
+            summary.belt_bodies += system.belt_bodies;
+            summary.comets += system.comets;
+            merge(&mut summary.planet_failures, &system.planet_failures);
+            merge(&mut summary.moon_failures, &system.moon_failures);
+            merge(&mut summary.belt_failures, &system.belt_failures);
+            merge(&mut summary.comet_failures, &system.comet_failures);
+        }
+        if summary.planets.accepted > 0 {
+            summary.moons_per_planet =
+                summary.moons.accepted as f64 / summary.planets.accepted as f64;
+        }
+        summary
+    }
+}
+
+fn merge<K: Ord + Copy>(total: &mut BTreeMap<K, usize>, counts: &BTreeMap<K, usize>) {
+    for (&key, &count) in counts {
+        *total.entry(key).or_default() += count;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut lhs = SystemReport::new(0, 1);
+        lhs.complete = true;
+        lhs.planets = BodyCounts {
+            attempted: 3,
+            accepted: 2,
+        };
+        lhs.planet_failures
+            .insert(PlanetGenerationError::UnstableOrbit, 1);
+        let mut rhs = SystemReport::new(1, 2);
+        rhs.complete = true;
+        rhs.planet_failed(PlanetGenerationError::UnstableOrbit);
+        rhs.moon_failed(MoonGenerationError::MinAccNotMet);
+        rhs.comet_failed(CometGenerationError::UnstableOrbit);
+
+        let summary = ReportSummary::new(&[lhs, rhs], &[]);
+        assert!(
+            summary.planets
+                == BodyCounts {
+                    attempted: 4,
+                    accepted: 2
+                }
+        );
+        assert_eq!(summary.planets.rejected(), 2);
+        assert_eq!(
+            summary.planet_failures[&PlanetGenerationError::UnstableOrbit],
+            2
+        );
+        assert_eq!(
+            summary.comet_failures[&CometGenerationError::UnstableOrbit],
+            1
+        );
+        assert!(
+            summary.planets_per_system
+                == Stats {
+                    min: 0.,
+                    max: 2.,
+                    mean: 1.
+                }
+        );
+        assert_eq!(summary.moons_per_planet, 0.);
+    }
+}
@@ -0,0 +1,302 @@
+use std::f64::consts::TAU;
+
+use bevy::{
+    log::info,
+    math::DVec2,
+    sprite::{ColorMaterial, MaterialMesh2dBundle},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consts,
+    sci::physics,
+    sim::{
+        self,
+        bundles::CelestialBodyBundle,
+        components::{CelestialBodyId, Interstellar, PlanetType},
+        resources::{CelestialBody, Galaxy},
+        SimVec,
+    },
+};
+
+use super::{
+    config::UniformRange, names, sample_star_pos, system_seed, system_vel, GalaxyGenerator,
+    GalaxyLayout, SystemContext, SystemGenerator,
+};
+
+/// Planets that belong to no star system.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RogueGenerationConfig {
+    /// Free-floating planets scattered between the systems per star system.
+    pub per_system: f32,
+    #[serde(default)]
+    pub interstellar: Option<InterstellarConfig>,
+}
+
+impl RogueGenerationConfig {
+    /// Free-floating planets of a galaxy with `num_stars` systems.
+    #[inline]
+    pub fn num_planets(&self, num_stars: usize) -> usize {
+        (self.per_system as f64 * num_stars as f64).round() as usize
+    }
+}
+
+/// Rogue planets sent through the systems on hyperbolic trajectories during the
+/// simulation.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct InterstellarConfig {
+    /// Simulation steps between two objects.
+    pub interval: u32,
+    /// Speed relative to the target system once far away from it.
+    pub excess_spd: UniformRange<f64>,
+    /// Distance the object is aimed to pass the target's primary at, as a fraction of
+    /// the radius the planets of a system stay within.
+    pub impact: UniformRange<f64>,
+}
+
+/// Mass, radius, type and density of a planet, as sampled by
+/// [`SystemGenerator::sample_planet`].
+type SampledPlanet = (f64, f64, PlanetType, f64);
+
+impl GalaxyLayout {
+    /// Whether the next interstellar object is due once the galaxy has been simulated for
+    /// `steps` steps.
+    pub fn interstellar_due(&self, steps: u64) -> bool {
+        self.config
+            .rogue_cfg
+            .interstellar
+            .is_some_and(|cfg| steps >= (self.interstellar.len() as u64 + 1) * cfg.interval as u64)
+    }
+}
+
+impl<'a> GalaxyGenerator<'a> {
+    /// Scatters the free-floating planets between the systems in `galaxy`, keeping them as
+    /// clear of the systems and of each other as the systems are.
+    ///
+    /// Planet `index` comes from its own RNG, seeded as if it were system
+    /// `num_stars + index`.
+    pub(super) fn commit_rogue_planets(
+        &mut self,
+        galaxy: &mut Galaxy,
+    ) -> Vec<(CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>)> {
+        let mut placed = self
+            .systems
+            .iter()
+            .filter_map(|system| galaxy.get_body(system.bodies[0]))
+            .map(|primary| (primary.pos(), primary.mass()))
+            .collect::<Vec<_>>();
+        if let Some(central_black_hole) = self.cfg.central_black_hole {
+            placed.push((SimVec::ZERO, central_black_hole.scaled_mass()));
+        }
+
+        let ctx = SystemContext {
+            cfg: &self.cfg,
+            star_names: &self.star_names,
+            star_props: self.star_props,
+            substance_assets: self.substance_assets,
+            distrs: &self.distrs,
+        };
+        let num_stars = self.cfg.num_stars;
+        let planets = (0..self.cfg.rogue_cfg.num_planets(num_stars))
+            .map(|index| {
+                let seed = system_seed(ctx.cfg.seed, num_stars + index);
+                let mut generator = SystemGenerator::new(ctx, num_stars + index, seed);
+                let planet = generator.sample_planet();
+                let pos = sample_star_pos(&self.star_pos_distr, &mut self.rng, &placed, planet.0);
+                placed.push((pos, planet.0));
+                generator.gen_rogue_planet(
+                    planet,
+                    pos,
+                    system_vel(ctx.cfg, pos),
+                    names::rogue_planet(index),
+                    None,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        planets
+            .into_iter()
+            .map(|(body, bundle)| {
+                let committed = self.commit_body(galaxy, body, bundle);
+                self.rogue_planets.push(committed.0.id());
+                committed
+            })
+            .collect()
+    }
+
+    /// Sends the next interstellar object of `layout` through a random system and returns
+    /// its bundle, or `None` if the galaxy has no interstellar objects or no systems left.
+    ///
+    /// The object starts on the edge of the system, aimed to pass the primary at the
+    /// sampled impact parameter, and fast enough to keep the sampled excess speed once
+    /// it leaves, so its orbit around the primary is hyperbolic. Object `index` comes from
+    /// its own RNG, seeded as if it were the system after the rogue planets.
+    pub fn inject_interstellar(
+        &mut self,
+        galaxy: &mut Galaxy,
+        layout: &mut GalaxyLayout,
+    ) -> Option<(CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>)> {
+        let cfg = &layout.config;
+        let interstellar = cfg.rogue_cfg.interstellar?;
+        let targets = layout
+            .systems
+            .iter()
+            .enumerate()
+            .filter_map(|(index, system)| Some((index, galaxy.get_body(system.bodies[0])?)))
+            .collect::<Vec<_>>();
+        if targets.is_empty() {
+            return None;
+        }
+
+        let index = layout.interstellar.len();
+        let system_id = cfg.num_stars + cfg.rogue_cfg.num_planets(cfg.num_stars) + index;
+        let ctx = SystemContext {
+            cfg,
+            star_names: &self.star_names,
+            star_props: self.star_props,
+            substance_assets: self.substance_assets,
+            distrs: &self.distrs,
+        };
+        let mut generator = SystemGenerator::new(ctx, system_id, system_seed(cfg.seed, system_id));
+        let (system, primary) = targets[generator.rng.gen_range(0..targets.len())];
+        let planet = generator.sample_planet();
+
+        let edge = physics::linear_spd_to_dist(cfg.pln_cfg.min_revl_spd, primary.mass());
+        let impact = generator.rng.sample(interstellar.impact) * edge;
+        let excess_spd = generator.rng.sample(interstellar.excess_spd);
+        let dir = DVec2::from_angle(generator.rng.gen_range(0f64..TAU));
+        let rel_pos = dir.perp() * impact - dir * (edge * edge - impact * impact).sqrt();
+        let spd =
+            (excess_spd * excess_spd + 2. * consts::G * (primary.mass() + planet.0) / edge).sqrt();
+        let (body, bundle) = generator.gen_rogue_planet(
+            planet,
+            primary.pos() + sim::from_plane(rel_pos),
+            primary.vel() + sim::from_plane(dir * spd),
+            names::interstellar(index),
+            Some(Interstellar { system }),
+        );
+
+        let committed = self.commit_body(galaxy, body, bundle);
+        layout.interstellar.push(committed.0.id());
+        info!("Sent {} through system {}", committed.0.name(), system);
+        Some(committed)
+    }
+}
+
+impl<'a> SystemGenerator<'a> {
+    /// Generates the properties of a rogue `planet` at `pos` moving at `vel`.
+    fn gen_rogue_planet(
+        &mut self,
+        (mass, radius, ty, density): SampledPlanet,
+        pos: SimVec,
+        vel: SimVec,
+        name: String,
+        interstellar: Option<Interstellar>,
+    ) -> (CelestialBody, CelestialBodyBundle) {
+        let body = CelestialBody::new(pos, radius, mass, vel);
+        let (mut planet, crust, atmo) =
+            self.gen_rogue_planet_props(CelestialBodyId(0), mass, density, ty);
+        planet.name.0 = name;
+        (
+            body,
+            CelestialBodyBundle::RoguePlanet {
+                planet,
+                crust,
+                atmo,
+                interstellar,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        gen::test::{test_config, with_galaxy},
+        sim::bundles::CelestialBodyBundle,
+    };
+
+    use super::*;
+
+    fn rogue_config() -> crate::gen::GalaxyGeneratorConfig {
+        let mut cfg = test_config();
+        cfg.rogue_cfg = RogueGenerationConfig {
+            per_system: 0.5,
+            interstellar: Some(InterstellarConfig {
+                interval: 100,
+                excess_spd: UniformRange::new(2., 10.),
+                impact: UniformRange::new(0.05, 0.5),
+            }),
+        };
+        cfg
+    }
+
+    #[test]
+    fn test_rogue_planets() {
+        let cfg = rogue_config();
+        with_galaxy(cfg.clone(), |_, galaxy, layout| {
+            assert_eq!(
+                layout.rogue_planets.len(),
+                cfg.rogue_cfg.num_planets(cfg.num_stars)
+            );
+            for &id in &layout.rogue_planets {
+                let planet = galaxy.get_body(id).unwrap();
+                assert!(layout
+                    .systems
+                    .iter()
+                    .all(|system| !system.bodies.contains(&id)));
+                for system in &layout.systems {
+                    let Some(primary) = galaxy.get_body(system.bodies[0]) else {
+                        continue;
+                    };
+                    let acc = physics::mass_dist_to_acc(
+                        primary.mass(),
+                        planet.pos().distance(primary.pos()),
+                    );
+                    assert!(acc <= consts::STAR_ACC_THRESHOLD);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_interstellar() {
+        with_galaxy(rogue_config(), |generator, mut galaxy, mut layout| {
+            assert!(!layout.interstellar_due(99));
+            assert!(layout.interstellar_due(100));
+
+            let (bundle, _) = generator
+                .inject_interstellar(&mut galaxy, &mut layout)
+                .unwrap();
+            assert!(layout.interstellar == vec![bundle.id()]);
+            assert!(!layout.interstellar_due(199));
+            assert_eq!(bundle.name(), "1I");
+            assert!(bundle.heating().is_none());
+
+            let CelestialBodyBundle::RoguePlanet {
+                planet,
+                interstellar: Some(interstellar),
+                ..
+            } = &bundle
+            else {
+                panic!("Interstellar objects are rogue planets");
+            };
+            let body = galaxy.get_body(bundle.id()).unwrap();
+            let primary = galaxy
+                .get_body(layout.systems[interstellar.system].bodies[0])
+                .unwrap();
+            // Atmospheres scale it by up to half either way.
+            let mass = body.mass() / consts::EARTH_MASS / consts::PLANET_MASS_SCALE;
+            let ratio = planet.effective_temp.0 / physics::internal_heat_temp(mass);
+            assert!((0.5..=1.5).contains(&ratio));
+
+            // Unbound from the primary, and headed towards it.
+            let (rel_pos, rel_vel) = (body.pos() - primary.pos(), body.vel() - primary.vel());
+            let energy = rel_vel.length_squared() / 2.
+                - consts::G * (primary.mass() + body.mass()) / rel_pos.length();
+            assert!(energy > 0.);
+            assert!(rel_pos.dot(rel_vel) < 0.);
+        });
+    }
+}
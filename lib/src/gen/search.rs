@@ -0,0 +1,410 @@
+use std::{ops::Range, path::Path, time::Instant};
+
+use rand::{rngs::StdRng, SeedableRng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::{
+        settings::{ConstellationNames, StarProperties},
+        SubstanceAssets,
+    },
+    sim::components::{PlanetType, SpectralType},
+    utils,
+};
+
+use super::{
+    config::ConfigError,
+    distr::{Distributions, StarPosDistribution},
+    gen_systems,
+    names::StarNames,
+    report::PhaseTimings,
+    GalaxyGeneratorConfig, GeneratedSystem, SystemContext,
+};
+
+/// Every system of a galaxy, fully generated but not added to a
+/// [`Galaxy`](crate::sim::resources::Galaxy) or pre-simulated.
+pub struct GalaxyBlueprint {
+    pub seed: u64,
+    pub systems: Vec<GeneratedSystem>,
+}
+
+/// A condition on single systems that enough systems of a galaxy have to meet.
+pub struct Predicate {
+    pub name: String,
+    pub min_systems: usize,
+    test: Box<dyn Fn(&GeneratedSystem) -> bool + Send + Sync>,
+}
+
+impl Predicate {
+    /// Met by galaxies with at least one system that passes `test`.
+    pub fn new(
+        name: impl Into<String>,
+        test: impl Fn(&GeneratedSystem) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            min_systems: 1,
+            test: Box::new(test),
+        }
+    }
+
+    /// Requires `min_systems` systems to pass instead of one.
+    pub fn at_least(mut self, min_systems: usize) -> Self {
+        self.min_systems = min_systems;
+        self
+    }
+
+    /// Systems with `num_planets` planets or more.
+    pub fn min_planets(num_planets: usize) -> Self {
+        Self::new(format!("{}+ planets", num_planets), move |system| {
+            system.planets().count() >= num_planets
+        })
+    }
+
+    /// Systems with a primary of spectral type `ty` and a rocky planet or moon that scores
+    /// at least `min_habitability` overall.
+    pub fn earth_like(ty: SpectralType, min_habitability: f64) -> Self {
+        Self::new(
+            format!("Earth-like world around {} star", ty),
+            move |system| {
+                system.primary().class.ty == ty
+                    && system
+                        .habitability()
+                        .any(|score| score.overall >= min_habitability)
+            },
+        )
+    }
+
+    /// Systems with a gas or ice giant.
+    pub fn giant_planet() -> Self {
+        Self::new("Giant planet", |system| {
+            system
+                .planets()
+                .any(|planet| planet.ty != PlanetType::Rocky)
+        })
+    }
+
+    /// Indices of the systems of `galaxy` that pass, if there are enough of them.
+    pub fn eval(&self, galaxy: &GalaxyBlueprint) -> Option<Vec<usize>> {
+        let systems = galaxy
+            .systems
+            .iter()
+            .enumerate()
+            .filter(|(_, system)| (self.test)(system))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        (systems.len() >= self.min_systems).then_some(systems)
+    }
+}
+
+/// A [`Predicate`] as written in a search file.
+#[derive(Clone, Deserialize)]
+pub struct PredicateConfig {
+    pub kind: PredicateKind,
+    #[serde(default = "PredicateConfig::default_min_systems")]
+    pub min_systems: usize,
+}
+
+impl PredicateConfig {
+    fn default_min_systems() -> usize {
+        1
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub enum PredicateKind {
+    MinPlanets(usize),
+    EarthLike {
+        ty: SpectralType,
+        min_habitability: f64,
+    },
+    GiantPlanet,
+}
+
+impl From<&PredicateConfig> for Predicate {
+    fn from(config: &PredicateConfig) -> Self {
+        let predicate = match config.kind {
+            PredicateKind::MinPlanets(num_planets) => Predicate::min_planets(num_planets),
+            PredicateKind::EarthLike {
+                ty,
+                min_habitability,
+            } => Predicate::earth_like(ty, min_habitability),
+            PredicateKind::GiantPlanet => Predicate::giant_planet(),
+        };
+        predicate.at_least(config.min_systems)
+    }
+}
+
+/// Where the config of a search comes from.
+#[derive(Clone, Deserialize)]
+pub enum ConfigSource {
+    /// A preset of [`consts::GALAXY_PRESETS`](crate::consts::GALAXY_PRESETS).
+    Preset(String),
+    /// A JSON file holding a single config.
+    File(String),
+}
+
+impl ConfigSource {
+    pub fn load(&self) -> Result<GalaxyGeneratorConfig, ConfigError> {
+        match self {
+            ConfigSource::Preset(name) => GalaxyGeneratorConfig::preset(name),
+            ConfigSource::File(path) => GalaxyGeneratorConfig::load(path),
+        }
+    }
+}
+
+/// A headless seed search, as loaded from a JSON file like
+/// [`consts::SEED_SEARCH`](crate::consts::SEED_SEARCH).
+#[derive(Clone, Deserialize)]
+pub struct SearchConfig {
+    pub config: ConfigSource,
+    pub seeds: Range<u64>,
+    /// Matches to stop after, or none to search every seed.
+    #[serde(default)]
+    pub limit: Option<usize>,
+    pub predicates: Vec<PredicateConfig>,
+}
+
+impl SearchConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Ok(utils::deser(path)?)
+    }
+
+    /// Loads the config and runs the search.
+    pub fn run(
+        &self,
+        constellation_names: &ConstellationNames,
+        star_props: &StarProperties,
+        substance_assets: &SubstanceAssets,
+    ) -> Result<SearchResult, ConfigError> {
+        let search = self.predicates.iter().fold(
+            SeedSearch::new(
+                self.config.load()?,
+                constellation_names,
+                star_props,
+                substance_assets,
+            )?,
+            |search, predicate| search.with(predicate.into()),
+        );
+        Ok(search.run(self.seeds.clone(), self.limit))
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct MatchedSystem {
+    pub index: usize,
+    pub name: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PredicateMatch {
+    pub predicate: String,
+    pub systems: Vec<MatchedSystem>,
+}
+
+/// A seed whose galaxy meets every predicate, and the systems that met each.
+#[derive(Clone, Serialize)]
+pub struct SeedMatch {
+    pub seed: u64,
+    pub matches: Vec<PredicateMatch>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SearchResult {
+    /// Number of seeds generated, which stops short of the range once enough matched.
+    pub searched: usize,
+    /// Matching seeds in ascending order.
+    pub matches: Vec<SeedMatch>,
+    pub elapsed_ms: f64,
+}
+
+impl SearchResult {
+    #[inline]
+    pub fn seeds(&self) -> impl Iterator<Item = u64> + '_ {
+        self.matches.iter().map(|m| m.seed)
+    }
+
+    #[inline]
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Looks for seeds whose galaxies meet a set of predicates, without a Bevy app.
+///
+/// Galaxies are generated from the config with each candidate seed. Candidates are
+/// generated in parallel, but the result only depends on the seed range.
+pub struct SeedSearch<'a> {
+    cfg: GalaxyGeneratorConfig,
+    distrs: Distributions,
+    constellation_names: &'a ConstellationNames,
+    star_props: &'a StarProperties,
+    substance_assets: &'a SubstanceAssets,
+    predicates: Vec<Predicate>,
+}
+
+impl<'a> SeedSearch<'a> {
+    /// Streaming is turned off, as predicates need the planets of every system.
+    pub fn new(
+        mut config: GalaxyGeneratorConfig,
+        constellation_names: &'a ConstellationNames,
+        star_props: &'a StarProperties,
+        substance_assets: &'a SubstanceAssets,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+        config.streaming = None;
+        let distrs = Distributions::new(&config.distrs)?;
+        Ok(Self {
+            cfg: config,
+            distrs,
+            constellation_names,
+            star_props,
+            substance_assets,
+            predicates: Vec::new(),
+        })
+    }
+
+    pub fn with(mut self, predicate: Predicate) -> Self {
+        self.predicates.push(predicate);
+        self
+    }
+
+    /// Generates the galaxy of `seed`.
+    pub fn blueprint(&self, seed: u64) -> GalaxyBlueprint {
+        let cfg = GalaxyGeneratorConfig {
+            seed,
+            ..self.cfg.clone()
+        };
+        let star_names = StarNames::new(self.constellation_names.iter(), seed);
+        let ctx = SystemContext {
+            cfg: &cfg,
+            star_names: &star_names,
+            star_props: self.star_props,
+            substance_assets: self.substance_assets,
+            distrs: &self.distrs,
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let star_pos_distr = StarPosDistribution::new(cfg.galaxy_radius, cfg.morphology, &mut rng);
+        GalaxyBlueprint {
+            seed,
+            systems: gen_systems(ctx, &star_pos_distr, &mut rng),
+        }
+    }
+
+    /// What `galaxy` matched, if it meets every predicate.
+    pub fn eval(&self, galaxy: &GalaxyBlueprint) -> Option<SeedMatch> {
+        let matches = self
+            .predicates
+            .iter()
+            .map(|predicate| {
+                predicate.eval(galaxy).map(|systems| PredicateMatch {
+                    predicate: predicate.name.clone(),
+                    systems: systems
+                        .into_iter()
+                        .map(|index| MatchedSystem {
+                            index,
+                            name: galaxy.systems[index].primary().name.0.clone(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(SeedMatch {
+            seed: galaxy.seed,
+            matches,
+        })
+    }
+
+    /// Searches `seeds` in order until `limit` of them match, or through all of them.
+    ///
+    /// Seeds are generated in batches of one per thread, so a few past the last match
+    /// may be generated too.
+    pub fn run(&self, seeds: Range<u64>, limit: Option<usize>) -> SearchResult {
+        let start = Instant::now();
+        let batch = rayon::current_num_threads().max(1) as u64;
+        let mut matches = Vec::new();
+        let mut next = seeds.start;
+
+        while next < seeds.end && limit.is_none_or(|limit| matches.len() < limit) {
+            let end = next.saturating_add(batch).min(seeds.end);
+            matches.extend(
+                (next..end)
+                    .into_par_iter()
+                    .filter_map(|seed| self.eval(&self.blueprint(seed)))
+                    .collect::<Vec<_>>(),
+            );
+            next = end;
+        }
+        if let Some(limit) = limit {
+            matches.truncate(limit);
+        }
+
+        SearchResult {
+            searched: (next - seeds.start) as usize,
+            matches,
+            elapsed_ms: PhaseTimings::ms(start.elapsed()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        consts,
+        gen::test::{load_assets, test_config},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_search_config() {
+        let search = SearchConfig::load(format!("../{}", consts::SEED_SEARCH))
+            .unwrap_or_else(|err| panic!("{}", err));
+        assert!(matches!(&search.config, ConfigSource::Preset(name) if name == "debug"));
+        assert!(search.seeds.start < search.seeds.end);
+        let predicates = search
+            .predicates
+            .iter()
+            .map(Predicate::from)
+            .collect::<Vec<_>>();
+        assert_eq!(predicates[0].name, "4+ planets");
+        assert_eq!(predicates[1].name, "Giant planet");
+        assert!(predicates
+            .iter()
+            .all(|predicate| predicate.min_systems == 1));
+    }
+
+    #[test]
+    fn test_seed_search() {
+        let (constellation_names, star_props, substance_assets) = load_assets();
+        let search = SeedSearch::new(
+            test_config(),
+            &constellation_names,
+            &star_props,
+            &substance_assets,
+        )
+        .ok()
+        .unwrap()
+        .with(Predicate::min_planets(3).at_least(2));
+
+        let all = search.run(0..16, None);
+        assert_eq!(all.searched, 16);
+        assert!(!all.matches.is_empty());
+        for m in &all.matches {
+            let galaxy = search.blueprint(m.seed);
+            assert!(m.matches[0].systems.len() >= 2);
+            for system in &m.matches[0].systems {
+                assert!(galaxy.systems[system.index].planets().count() >= 3);
+                assert_eq!(system.name, galaxy.systems[system.index].primary().name.0);
+            }
+        }
+
+        let first = search.run(0..16, Some(1));
+        assert_eq!(first.seeds().collect::<Vec<_>>(), [all.matches[0].seed]);
+        assert!(first.searched <= 16);
+
+        let none = search.with(Predicate::min_planets(1000)).run(0..4, Some(1));
+        assert!(none.matches.is_empty() && none.searched == 4);
+    }
+}
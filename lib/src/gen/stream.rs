@@ -0,0 +1,299 @@
+use bevy::{
+    ecs::component::Component,
+    log::info,
+    math::DVec2,
+    sprite::{ColorMaterial, MaterialMesh2dBundle},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{
+    bundles::CelestialBodyBundle,
+    components::CelestialBodyId,
+    resources::{CelestialBody, Galaxy, ViewPlane},
+    SimVec,
+};
+
+use super::{GalaxyGenerator, GalaxyGeneratorConfig, GalaxyLayout, GeneratedSystem};
+
+/// Distances from the nearest [`StreamingAnchor`] in the view plane at which the
+/// planets, moons and belts of a system are generated and dropped again.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct StreamingConfig {
+    pub load_dist: f64,
+    /// Kept above `load_dist` so that systems on the edge don't flicker in and out.
+    pub unload_dist: f64,
+}
+
+/// Marks an entity, like the camera or a player, that systems are loaded around.
+#[derive(Component, Clone, Copy, Default)]
+pub struct StreamingAnchor;
+
+#[derive(Default)]
+pub struct StreamingPlan {
+    pub load: Vec<usize>,
+    pub unload: Vec<usize>,
+}
+
+impl GalaxyLayout {
+    /// Decides which systems to load and unload for anchors at `anchors` in the view
+    /// plane.
+    ///
+    /// Systems are measured from their primary, and left alone once it is gone.
+    pub fn plan_streaming(
+        &self,
+        galaxy: &Galaxy,
+        view: &ViewPlane,
+        anchors: &[DVec2],
+    ) -> StreamingPlan {
+        let mut plan = StreamingPlan::default();
+        let Some(streaming) = self.config.streaming else {
+            return plan;
+        };
+
+        for (index, system) in self.systems.iter().enumerate() {
+            let Some(primary) = galaxy.get_body(system.bodies[0]) else {
+                continue;
+            };
+            let pos = view.project(primary.pos());
+            let dist = anchors
+                .iter()
+                .map(|anchor| anchor.distance(pos))
+                .fold(f64::INFINITY, f64::min);
+
+            if !system.loaded && dist < streaming.load_dist {
+                plan.load.push(index);
+            } else if system.loaded && dist > streaming.unload_dist {
+                plan.unload.push(index);
+            }
+        }
+
+        plan
+    }
+
+    /// Removes everything but the stars of system `index` from `galaxy` and returns the
+    /// ids of the removed bodies, whose meshes and materials are the caller's to drop.
+    pub fn unload_system(&mut self, galaxy: &mut Galaxy, index: usize) -> Vec<CelestialBodyId> {
+        let system = &mut self.systems[index];
+        if !system.loaded {
+            return Vec::new();
+        }
+
+        let removed = system.bodies.split_off(system.num_stars);
+        for &id in &removed {
+            galaxy.remove_body(id);
+        }
+        system.loaded = false;
+        info!("Unloaded system {}", index);
+
+        removed
+    }
+}
+
+impl<'a> GalaxyGenerator<'a> {
+    /// Generates the planets, moons and belts of system `index`, the same as eager
+    /// generation would, and returns their bundles.
+    ///
+    /// They are generated where the system started and pre-simulated on their own, so
+    /// the same bodies are culled, then moved along with the stars they orbit. Nothing
+    /// happens if the system is loaded or its primary is gone.
+    pub fn load_system(
+        &mut self,
+        galaxy: &mut Galaxy,
+        layout: &mut GalaxyLayout,
+        index: usize,
+    ) -> Vec<(CelestialBodyBundle, MaterialMesh2dBundle<ColorMaterial>)> {
+        let system = &layout.systems[index];
+        if system.loaded || galaxy.get_body(system.bodies[0]).is_none() {
+            return Vec::new();
+        }
+
+        let mut generated = self.gen_system(&layout.config, index, &system.overrides, system.pos);
+        let culled = generated.pre_simulate(&layout.config, system.num_stars);
+        // Companions may have left since, which the primary never does here.
+        let (hosts, generated_hosts): (Vec<_>, Vec<_>) = system.bodies[..generated.host_stars]
+            .iter()
+            .zip(&generated.bodies)
+            .filter_map(|(&id, &body)| Some((galaxy.get_body(id)?, body)))
+            .unzip();
+        let (host_pos, host_vel) = barycentre(&hosts);
+        let (generated_pos, generated_vel) = barycentre(&generated_hosts);
+        generated.bodies.drain(..system.num_stars);
+        generated.bundles.drain(..system.num_stars);
+        generated.shift(host_pos - generated_pos, host_vel - generated_vel);
+
+        let stars = system.bodies[..system.num_stars].to_vec();
+        let (loaded, bundles) = self.commit_system(galaxy, generated, &stars);
+        let system = &mut layout.systems[index];
+        system.bodies.extend(loaded.bodies);
+        system.loaded = true;
+        info!(
+            "Loaded system {} with {} bodies, {} culled",
+            index,
+            bundles.len(),
+            culled
+        );
+
+        bundles
+    }
+}
+
+impl GeneratedSystem {
+    /// Steps the system on its own for the pre-simulation of `cfg` and drops the bodies
+    /// that collide, returning how many were dropped.
+    ///
+    /// The first `num_stars` bodies are kept either way, as the galaxy already has them.
+    fn pre_simulate(&mut self, cfg: &GalaxyGeneratorConfig, num_stars: usize) -> usize {
+        let mut galaxy = Galaxy::default();
+        galaxy.set_gravity(cfg.gravity);
+        let ids = self
+            .bodies
+            .iter()
+            .map(|&body| galaxy.add_body(body))
+            .collect::<Vec<_>>();
+        for _ in 0..cfg.stability.pre_sim_steps {
+            galaxy.step();
+            for id in galaxy.test_overlapping() {
+                galaxy.remove_body(id);
+            }
+        }
+
+        let num_bodies = self.bodies.len();
+        let (bodies, bundles) = std::mem::take(&mut self.bodies)
+            .into_iter()
+            .zip(std::mem::take(&mut self.bundles))
+            .zip(ids)
+            .enumerate()
+            .filter_map(|(index, ((body, bundle), id))| match galaxy.get_body(id) {
+                Some(body) => Some((body, bundle)),
+                None if index < num_stars => Some((body, bundle)),
+                None => None,
+            })
+            .unzip();
+        (self.bodies, self.bundles) = (bodies, bundles);
+        num_bodies - self.bodies.len()
+    }
+
+    /// Moves all bodies by `pos` and speeds them up by `vel`.
+    pub(super) fn shift(&mut self, pos: SimVec, vel: SimVec) {
+        for body in &mut self.bodies {
+            *body = CelestialBody::new(
+                body.pos() + pos,
+                body.radius(),
+                body.mass(),
+                body.vel() + vel,
+            );
+        }
+    }
+}
+
+/// Mass-weighted position and velocity of `bodies`.
+pub(super) fn barycentre(bodies: &[CelestialBody]) -> (SimVec, SimVec) {
+    let (mass, pos, vel) =
+        bodies
+            .iter()
+            .fold((0., SimVec::ZERO, SimVec::ZERO), |(m, p, v), body| {
+                (
+                    m + body.mass(),
+                    p + body.pos() * body.mass(),
+                    v + body.vel() * body.mass(),
+                )
+            });
+    (pos / mass, vel / mass)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gen::{
+        test::{test_config, with_galaxy},
+        GalaxyGeneratorConfig,
+    };
+
+    use super::*;
+
+    fn streaming_config() -> GalaxyGeneratorConfig {
+        let mut cfg = test_config();
+        cfg.streaming = Some(StreamingConfig {
+            load_dist: 1e9,
+            unload_dist: 2e9,
+        });
+        cfg
+    }
+
+    #[test]
+    fn test_load_matches_eager() {
+        let mut eager_cfg = test_config();
+        eager_cfg.stability.pre_sim_steps = 100;
+        let mut streaming_cfg = streaming_config();
+        streaming_cfg.stability.pre_sim_steps = 100;
+
+        with_galaxy(eager_cfg, |_, eager_galaxy, eager_layout| {
+            with_galaxy(streaming_cfg, |generator, mut galaxy, mut layout| {
+                assert!(layout
+                    .systems
+                    .iter()
+                    .all(|system| !system.loaded && system.bodies.len() == system.num_stars));
+
+                for (index, eager) in eager_layout.systems.iter().enumerate() {
+                    let stars = layout.systems[index].bodies[..eager.num_stars].to_vec();
+                    let bundles = generator.load_system(&mut galaxy, &mut layout, index);
+                    assert!(layout.systems[index].loaded);
+                    let eager_bodies = eager.bodies[eager.num_stars..]
+                        .iter()
+                        .filter_map(|&id| eager_galaxy.get_body(id))
+                        .collect::<Vec<_>>();
+                    assert_eq!(bundles.len(), eager_bodies.len());
+
+                    let primary = galaxy.get_body(stars[0]).unwrap();
+                    let eager_primary = eager_galaxy.get_body(eager.bodies[0]).unwrap();
+                    for ((bundle, _), body) in bundles.iter().zip(&eager_bodies) {
+                        let loaded = galaxy.get_body(bundle.id()).unwrap();
+                        let rel_pos = body.pos() - eager_primary.pos();
+                        let rel_vel = body.vel() - eager_primary.vel();
+                        // Only the pull of the other systems is left out.
+                        assert!(
+                            (loaded.pos() - primary.pos() - rel_pos).length()
+                                <= 1e-6 * rel_pos.length()
+                        );
+                        assert!(
+                            (loaded.vel() - primary.vel() - rel_vel).length()
+                                <= 1e-4 * rel_vel.length()
+                        );
+                        assert_eq!(loaded.mass().to_bits(), body.mass().to_bits());
+                        assert_eq!(loaded.radius().to_bits(), body.radius().to_bits());
+                        let star = bundle.heating().unwrap().star;
+                        assert!(stars.contains(&star));
+                    }
+                }
+            });
+        });
+    }
+
+    #[test]
+    fn test_stream_in_and_out() {
+        with_galaxy(streaming_config(), |generator, mut galaxy, mut layout| {
+            let view = ViewPlane::default();
+            let primary = galaxy.get_body(layout.systems[0].bodies[0]).unwrap();
+            let anchor = view.project(primary.pos());
+            let plan = layout.plan_streaming(&galaxy, &view, &[anchor]);
+            assert!(plan.load.contains(&0));
+            assert!(plan.unload.is_empty());
+
+            let num_bodies = galaxy.num_bodies();
+            let loaded = generator.load_system(&mut galaxy, &mut layout, 0);
+            assert_eq!(galaxy.num_bodies(), num_bodies + loaded.len());
+            assert!(generator
+                .load_system(&mut galaxy, &mut layout, 0)
+                .is_empty());
+
+            let plan = layout.plan_streaming(&galaxy, &view, &[anchor + DVec2::X * 1e12]);
+            assert_eq!(plan.unload, vec![0]);
+            assert!(plan.load.is_empty());
+
+            let removed = layout.unload_system(&mut galaxy, 0);
+            assert_eq!(removed.len(), loaded.len());
+            assert_eq!(galaxy.num_bodies(), num_bodies);
+            let system = &layout.systems[0];
+            assert!(!system.loaded && system.bodies.len() == system.num_stars);
+        });
+    }
+}
@@ -9,6 +9,8 @@ pub mod floating_origin;
 pub mod gen;
 pub mod input;
 pub mod math;
+#[cfg(feature = "physics")]
+pub mod physics;
 pub mod sci;
 pub mod sim;
 pub mod utils;
@@ -23,6 +25,8 @@ impl Plugin for CosmosPlugin {
             core::CosmosGamePlugin,
             sim::CosmosSimPlugin,
             input::CosmosInputPlugin,
+            #[cfg(feature = "physics")]
+            physics::CosmosPhysicsPlugin,
             #[cfg(feature = "debug")]
             debug::CosmosDebugPlugin {
                 inspector: true,
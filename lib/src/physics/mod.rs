@@ -0,0 +1,65 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate, Update},
+    ecs::{component::Component, event::Event, schedule::IntoSystemConfigs},
+};
+use bevy_xpbd_2d::{
+    math::Vector,
+    prelude::{Gravity, PhysicsPlugins, PhysicsSet},
+};
+
+use crate::sim::components::CelestialBodyId;
+
+mod systems;
+
+/// Bridges the N-body simulation and `bevy_xpbd_2d`.
+///
+/// Celestial bodies get kinematic circle colliders that follow `Galaxy`, so xpbd only
+/// detects their contacts. Entities tagged with [`GalaxyGravity`] are regular rigid
+/// bodies that are pulled by every body in `Galaxy`.
+///
+/// xpbd is 2D only, so with the `sim3d` feature everything here happens on the reference plane.
+pub struct CosmosPhysicsPlugin;
+
+impl Plugin for CosmosPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(PhysicsPlugins::default())
+            .insert_resource(Gravity(Vector::ZERO));
+
+        app.add_event::<CelestialBodyMerged>();
+
+        app.add_systems(
+            Update,
+            (
+                systems::celestial_collider_inserter,
+                systems::physics_time_syncer,
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            (
+                systems::galaxy_gravity.before(PhysicsSet::Prepare),
+                systems::celestial_contact.after(PhysicsSet::Sync),
+            ),
+        );
+
+        #[cfg(feature = "debug")]
+        {
+            app.register_type::<GalaxyGravity>();
+        }
+    }
+}
+
+/// Marks a rigid body that should feel the gravity of every body in `Galaxy`.
+///
+/// The body itself doesn't attract anything, so this is meant for small objects
+/// like ships and debris.
+#[derive(Component, Clone, Copy, Default)]
+#[cfg_attr(feature = "debug", derive(bevy::reflect::Reflect, Debug))]
+pub struct GalaxyGravity;
+
+/// Sent when two celestial bodies touch and the lighter one is absorbed.
+#[derive(Event, Clone, Copy)]
+pub struct CelestialBodyMerged {
+    pub into: CelestialBodyId,
+    pub from: CelestialBodyId,
+}
@@ -0,0 +1,118 @@
+use bevy::{
+    asset::Assets,
+    ecs::{
+        entity::Entity,
+        event::{EventReader, EventWriter},
+        query::{Added, With},
+        system::{Commands, Query, Res, ResMut},
+    },
+    hierarchy::DespawnRecursiveExt,
+    render::mesh::Mesh,
+    sprite::Mesh2dHandle,
+    time::Time,
+};
+use bevy_xpbd_2d::prelude::{
+    Collider, CollisionStarted, ExternalForce, Mass, Physics, PhysicsTime, Position, RigidBody,
+};
+
+use crate::{
+    assets::{MaterialAssets, MeshAssets},
+    sim::{
+        self,
+        components::CelestialBodyId,
+        resources::{Galaxy, SimulationScheduler, SimulationTimeScale},
+    },
+};
+
+use super::{CelestialBodyMerged, GalaxyGravity};
+
+pub(super) fn celestial_collider_inserter(
+    mut commands: Commands,
+    galaxy: Res<Galaxy>,
+    bodies_query: Query<(Entity, &CelestialBodyId), Added<CelestialBodyId>>,
+) {
+    bodies_query.for_each(|(entity, id)| {
+        if let Some(body) = galaxy.get_body(*id) {
+            // Positions are driven by `transform_syncer`, xpbd picks them up from the transforms.
+            commands.entity(entity).insert((
+                RigidBody::Kinematic,
+                Collider::ball(body.radius()),
+                Position(sim::to_plane(body.pos())),
+            ));
+        }
+    });
+}
+
+/// Keeps physics time in line with the simulated time of `Galaxy`.
+pub(super) fn physics_time_syncer(
+    galaxy: Res<Galaxy>,
+    scheduler: Res<SimulationScheduler>,
+    time_scale: Res<SimulationTimeScale>,
+    mut time: ResMut<Time<Physics>>,
+) {
+    let speed = scheduler.steps_per_second * time_scale.0 as f64 * galaxy.time_step();
+    if speed == 0. {
+        time.pause();
+    } else {
+        time.unpause();
+        time.set_relative_speed_f64(speed);
+    }
+}
+
+pub(super) fn galaxy_gravity(
+    galaxy: Res<Galaxy>,
+    mut bodies_query: Query<(&Position, &Mass, &mut ExternalForce), With<GalaxyGravity>>,
+) {
+    bodies_query
+        .par_iter_mut()
+        .for_each(|(pos, mass, mut force)| {
+            force.set_force(sim::to_plane(galaxy.acc_at(sim::from_plane(pos.0))) * mass.0);
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn celestial_contact(
+    mut commands: Commands,
+    mut galaxy: ResMut<Galaxy>,
+    mut events: EventReader<CollisionStarted>,
+    mut merged: EventWriter<CelestialBodyMerged>,
+    mut mesh_assets: ResMut<MeshAssets>,
+    mut material_assets: ResMut<MaterialAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut bodies_query: Query<(&CelestialBodyId, &mut Collider, Option<&mut Mesh2dHandle>)>,
+) {
+    for CollisionStarted(lhs, rhs) in events.read() {
+        let Ok([(lhs_id, ..), (rhs_id, ..)]) = bodies_query.get_many([*lhs, *rhs]) else {
+            continue;
+        };
+        let (Some(lhs_body), Some(rhs_body)) = (galaxy.get_body(*lhs_id), galaxy.get_body(*rhs_id))
+        else {
+            continue;
+        };
+
+        let ((into, into_id), (from, from_id)) = if lhs_body.mass() >= rhs_body.mass() {
+            ((*lhs, *lhs_id), (*rhs, *rhs_id))
+        } else {
+            ((*rhs, *rhs_id), (*lhs, *lhs_id))
+        };
+
+        let Some(body) = galaxy.merge_bodies(into_id, from_id) else {
+            continue;
+        };
+
+        if let Ok((_, mut collider, mesh)) = bodies_query.get_mut(into) {
+            *collider = Collider::ball(body.radius());
+            if let Some(mut mesh) = mesh {
+                *mesh = Mesh2dHandle(mesh_assets.generate(&mut meshes, into_id, body.radius()));
+            }
+        }
+        mesh_assets.remove(from_id);
+        material_assets.remove(from_id);
+        commands.entity(from).despawn_recursive();
+
+        merged.send(CelestialBodyMerged {
+            into: into_id,
+            from: from_id,
+        });
+    }
+}
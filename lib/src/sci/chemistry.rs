@@ -20,6 +20,9 @@ pub enum MatterState {
 pub struct SubstanceProperty {
     pub melting_point: f64,
     pub boiling_point: f64,
+    /// Temperature above which the solid turns straight into gas in a vacuum, where it
+    /// has no liquid phase.
+    pub sublimation_point: f64,
     pub heat_of_vaporization: f64,
     pub vapor: Option<Substance>,
     pub color: Vec<HexRgbaColor>,
@@ -153,14 +156,16 @@ mod test {
 
     #[test]
     fn test_melting_point() {
-        let data = r#"
+        let data = r##"
         {
             "name": "Water",
             "melting_point": 273.15,
             "boiling_point": 373.13,
-            "heat_of_vaporization": 40650
+            "sublimation_point": 152,
+            "heat_of_vaporization": 40650,
+            "color": ["#ffffffff", "#ffffffff", "#ffffffff"]
         }
-        "#;
+        "##;
         let prop: SubstanceProperty = serde_json::from_str(data).unwrap();
         let mp = prop.get_boiling_point_at(1.5 * consts::STANDARD_ATMOSPHERE_DENSITY);
         println!("{}", mp - 273.15);
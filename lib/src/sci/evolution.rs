@@ -0,0 +1,379 @@
+use std::ops::Range;
+
+use crate::{
+    consts,
+    sim::components::{StellarEvolution, StellarPhase, StellarState},
+};
+
+/// Durations of the post-main-sequence phases, as fractions of the main-sequence
+/// lifetime, which they are about for stars of any mass.
+const SUBGIANT_DURATION: f64 = 0.08;
+const GIANT_DURATION: f64 = 0.07;
+
+/// Effective temperature subgiants cool down to before climbing the giant branch.
+const SUBGIANT_END_TEMP: f64 = 5000.;
+/// Effective temperature and luminosity of a low-mass star at the tip of the giant branch.
+/// Massive stars brighten less, and cross over to red supergiants at about constant
+/// luminosity.
+const GIANT_TIP_TEMP: f64 = 3500.;
+const GIANT_TIP_LUMINOSITY: f64 = 3000.;
+const SUPERGIANT_BRIGHTENING: f64 = 2.;
+
+/// Initial masses above which stars end as neutron stars and black holes.
+const NEUTRON_STAR_MIN_MASS: f64 = 8.;
+const BLACK_HOLE_MIN_MASS: f64 = 25.;
+/// Heaviest stars that form, in solar masses.
+const MAX_INITIAL_MASS: f64 = 120.;
+
+const CHANDRASEKHAR_MASS: f64 = 1.4;
+/// Radius of a white dwarf of 0.6 solar masses, which shrinks as the cube root of mass.
+const WHITE_DWARF_RADIUS: f64 = 0.0126;
+const WHITE_DWARF_BIRTH_TEMP: f64 = 1e5;
+const WHITE_DWARF_COOLING_TIME: f64 = 1e6;
+const NEUTRON_STAR_MASS: f64 = 1.4;
+/// 12 km.
+const NEUTRON_STAR_RADIUS: f64 = 1.72e-5;
+const NEUTRON_STAR_BIRTH_TEMP: f64 = 1e6;
+const NEUTRON_STAR_COOLING_TIME: f64 = 1e5;
+/// Share of the initial mass left in a black hole after winds and the supernova.
+pub const BLACK_HOLE_MASS_FRACTION: f64 = 0.3;
+
+/// Main-sequence lifetime in years of a star of `mass` solar masses.
+#[inline]
+pub fn main_sequence_lifetime(mass: f64) -> f64 {
+    consts::SUN_MAIN_SEQUENCE_LIFETIME * mass.powf(-2.5)
+}
+
+/// Luminosity in solar luminosities of a body radiating as a black body.
+#[inline]
+pub fn black_body_luminosity(radius: f64, effective_temp: f64) -> f64 {
+    radius * radius * (effective_temp / consts::SUN_EFFECTIVE_TEMP).powi(4)
+}
+
+/// Kind and mass in solar masses of what a star of `initial_mass` leaves behind.
+///
+/// White dwarf masses follow the initial-final mass relation of Kalirai et al. (2008).
+pub fn remnant(initial_mass: f64) -> (StellarPhase, f64) {
+    if initial_mass < NEUTRON_STAR_MIN_MASS {
+        let mass = (0.109 * initial_mass + 0.394).min(CHANDRASEKHAR_MASS);
+        (StellarPhase::WhiteDwarf, mass.min(initial_mass))
+    } else if initial_mass < BLACK_HOLE_MIN_MASS {
+        (StellarPhase::NeutronStar, NEUTRON_STAR_MASS)
+    } else {
+        (
+            StellarPhase::BlackHole,
+            initial_mass * BLACK_HOLE_MASS_FRACTION,
+        )
+    }
+}
+
+/// Initial mass of the star that leaves a white dwarf of `mass` behind, inverting
+/// [`remnant`].
+#[inline]
+pub fn progenitor_mass(mass: f64) -> f64 {
+    ((mass - 0.394) / 0.109).max(mass)
+}
+
+/// Initial masses of the stars that leave a neutron star or black hole behind.
+#[inline]
+pub fn progenitor_masses(phase: StellarPhase) -> Range<f64> {
+    match phase {
+        StellarPhase::NeutronStar => NEUTRON_STAR_MIN_MASS..BLACK_HOLE_MIN_MASS,
+        StellarPhase::BlackHole => BLACK_HOLE_MIN_MASS..MAX_INITIAL_MASS,
+        _ => panic!("Only neutron stars and black holes have a range of progenitors"),
+    }
+}
+
+/// Evolution of the remnant of a star of `initial_mass` that has been cooling for
+/// `cooling_age` years, generated as the remnant itself.
+pub fn remnant_evolution(initial_mass: f64, cooling_age: f64) -> StellarEvolution {
+    let lifetime = main_sequence_lifetime(initial_mass);
+    let age = lifetime * (1. + SUBGIANT_DURATION + GIANT_DURATION) + cooling_age;
+    let (phase, mass) = remnant(initial_mass);
+    // Stars generated as remnants evolve from the phase alone.
+    let remnant = StellarState {
+        phase,
+        mass,
+        radius: 0.,
+        luminosity: 0.,
+        effective_temp: 0.,
+    };
+    let mut evolution = StellarEvolution {
+        initial_mass,
+        lifetime,
+        age,
+        generated_age: age,
+        generated: remnant,
+        state: remnant,
+    };
+    evolution.generated = evolve(&evolution, age);
+    evolution.state = evolution.generated;
+    evolution
+}
+
+/// Age at which a star with a main-sequence `lifetime` enters `phase`. White dwarfs are
+/// aged until they have cooled down to `effective_temp`.
+pub fn phase_age(phase: StellarPhase, lifetime: f64, effective_temp: f64) -> f64 {
+    match phase {
+        StellarPhase::MainSequence => 0.,
+        StellarPhase::Subgiant => lifetime,
+        StellarPhase::Giant => lifetime * (1. + SUBGIANT_DURATION),
+        _ => {
+            let cooling_time = WHITE_DWARF_COOLING_TIME
+                * ((WHITE_DWARF_BIRTH_TEMP / effective_temp).powf(1. / 0.35) - 1.).max(0.);
+            lifetime * (1. + SUBGIANT_DURATION + GIANT_DURATION) + cooling_time
+        }
+    }
+}
+
+/// Luminosity on the main sequence relative to the Sun's today, `progress` through it.
+///
+/// Gough (1981), with the Sun 0.457 of the way through.
+#[inline]
+fn brightening(progress: f64) -> f64 {
+    1. / (1. + 0.4 * (1. - progress / 0.457))
+}
+
+/// Properties of a star with `evolution` at `age` years.
+///
+/// Main-sequence stars brighten and swell at about constant effective temperature,
+/// scaled from what they were generated with. They then cool into subgiants and climb
+/// the giant branch, steadily losing mass down to the remnant's. Stars generated as
+/// subgiants or giants start the phase from what they were generated with. Remnants
+/// cool from their birth temperature as `t^-0.35`, after Mestel (1952).
+pub fn evolve(evolution: &StellarEvolution, age: f64) -> StellarState {
+    let generated = &evolution.generated;
+    let progress = age / evolution.lifetime;
+    let generated_progress = evolution.generated_age / evolution.lifetime;
+    // Rounding of the age can't take a star back before the phase it was generated in.
+    let reached = |phase: StellarPhase| generated.phase as u8 >= phase as u8;
+
+    let main_sequence = |progress: f64| {
+        let brightening = brightening(progress) / brightening(generated_progress);
+        StellarState {
+            phase: StellarPhase::MainSequence,
+            luminosity: generated.luminosity * brightening,
+            radius: generated.radius * brightening.sqrt(),
+            ..*generated
+        }
+    };
+    // Radius follows from the luminosity and effective temperature, relative to a
+    // state of the same star so that phases join up.
+    let radiating = |base: &StellarState, luminosity: f64, effective_temp: f64| {
+        base.radius
+            * (luminosity / base.luminosity).sqrt()
+            * (base.effective_temp / effective_temp).powi(2)
+    };
+
+    if progress < 1. && !reached(StellarPhase::Subgiant) {
+        return main_sequence(progress);
+    }
+
+    let terminal = match generated.phase {
+        StellarPhase::MainSequence => main_sequence(1.),
+        _ => *generated,
+    };
+    let subgiant_end_temp = terminal.effective_temp.min(SUBGIANT_END_TEMP);
+    let subgiant = |t: f64| {
+        let luminosity = terminal.luminosity * 1.5f64.powf(t);
+        let effective_temp =
+            terminal.effective_temp * (subgiant_end_temp / terminal.effective_temp).powf(t);
+        StellarState {
+            phase: StellarPhase::Subgiant,
+            radius: radiating(&terminal, luminosity, effective_temp),
+            luminosity,
+            effective_temp,
+            ..terminal
+        }
+    };
+
+    let progress = progress - 1.;
+    if progress < SUBGIANT_DURATION && !reached(StellarPhase::Giant) {
+        return subgiant(progress / SUBGIANT_DURATION);
+    }
+
+    let base = match generated.phase {
+        StellarPhase::Giant => *generated,
+        _ => subgiant(1.),
+    };
+    let (remnant_phase, remnant_mass) = remnant(evolution.initial_mass);
+    let progress = progress - SUBGIANT_DURATION;
+    if progress < GIANT_DURATION && !reached(StellarPhase::WhiteDwarf) {
+        let t = (progress / GIANT_DURATION).max(0.);
+        let tip_luminosity = GIANT_TIP_LUMINOSITY.max(base.luminosity * SUPERGIANT_BRIGHTENING);
+        let luminosity = base.luminosity * (tip_luminosity / base.luminosity).powf(t);
+        let effective_temp = base.effective_temp
+            * (GIANT_TIP_TEMP.min(base.effective_temp) / base.effective_temp).powf(t);
+        return StellarState {
+            phase: StellarPhase::Giant,
+            mass: base.mass + (remnant_mass - base.mass) * t,
+            radius: radiating(&base, luminosity, effective_temp),
+            luminosity,
+            effective_temp,
+        };
+    }
+
+    let cooling_time = ((progress - GIANT_DURATION) * evolution.lifetime).max(0.);
+    let (radius, effective_temp) = match remnant_phase {
+        StellarPhase::WhiteDwarf => (
+            WHITE_DWARF_RADIUS * (remnant_mass / 0.6).cbrt().recip(),
+            WHITE_DWARF_BIRTH_TEMP * (1. + cooling_time / WHITE_DWARF_COOLING_TIME).powf(-0.35),
+        ),
+        StellarPhase::NeutronStar => (
+            NEUTRON_STAR_RADIUS,
+            NEUTRON_STAR_BIRTH_TEMP * (1. + cooling_time / NEUTRON_STAR_COOLING_TIME).powf(-0.35),
+        ),
+        // Nothing radiates from within the event horizon.
+        _ => (
+            2. * consts::G * remnant_mass * consts::SUN_MASS
+                / consts::SPEED_OF_LIGHT.powi(2)
+                / consts::SUN_RADIUS,
+            0.,
+        ),
+    };
+    StellarState {
+        phase: remnant_phase,
+        mass: remnant_mass,
+        radius,
+        luminosity: black_body_luminosity(radius, effective_temp),
+        effective_temp,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sun_like(mass: f64) -> StellarEvolution {
+        let lifetime = main_sequence_lifetime(mass);
+        let state = StellarState {
+            phase: StellarPhase::MainSequence,
+            mass,
+            radius: 1.,
+            luminosity: 1.,
+            effective_temp: 5772.,
+        };
+        StellarEvolution {
+            initial_mass: mass,
+            lifetime,
+            age: 0.457 * lifetime,
+            generated_age: 0.457 * lifetime,
+            generated: state,
+            state,
+        }
+    }
+
+    #[test]
+    fn test_sun_track() {
+        let sun = sun_like(1.);
+        let at = |progress: f64| evolve(&sun, progress * sun.lifetime);
+
+        assert!(at(0.457) == sun.generated);
+        assert!((at(0.).luminosity - 0.714).abs() < 1e-3);
+        assert!(at(0.9).luminosity > 1.5 && at(0.9).phase == StellarPhase::MainSequence);
+
+        // Phases join up without jumps.
+        for boundary in [
+            1.,
+            1. + SUBGIANT_DURATION,
+            1. + SUBGIANT_DURATION + GIANT_DURATION,
+        ] {
+            let (before, after) = (at(boundary - 1e-9), at(boundary + 1e-9));
+            assert!(before.phase != after.phase);
+            if !after.phase.is_remnant() {
+                assert!((before.radius / after.radius - 1.).abs() < 1e-6);
+                assert!((before.luminosity / after.luminosity - 1.).abs() < 1e-6);
+            }
+        }
+
+        let tip = at(1. + SUBGIANT_DURATION + GIANT_DURATION - 1e-9);
+        assert!(tip.phase == StellarPhase::Giant);
+        assert!(tip.radius > 100. && tip.luminosity > 2000.);
+
+        let white_dwarf = at(2.);
+        assert!(white_dwarf.phase == StellarPhase::WhiteDwarf);
+        assert!((white_dwarf.mass - 0.503).abs() < 1e-9);
+        assert!(white_dwarf.radius < 0.02);
+        assert!(white_dwarf.effective_temp < at(1.2).effective_temp);
+    }
+
+    #[test]
+    fn test_remnants() {
+        assert!(remnant(1.).0 == StellarPhase::WhiteDwarf);
+        assert!(remnant(15.).0 == StellarPhase::NeutronStar);
+        assert!(remnant(40.).0 == StellarPhase::BlackHole);
+        assert!(main_sequence_lifetime(10.) < main_sequence_lifetime(1.) / 100.);
+
+        let massive = sun_like(40.);
+        let black_hole = evolve(&massive, 2. * massive.lifetime);
+        assert!(black_hole.luminosity == 0. && black_hole.mass == 12.);
+        // About 3 km per solar mass.
+        assert!((black_hole.radius * consts::SUN_RADIUS / 12. - 2.95e3).abs() < 10.);
+
+        let neutron_star = remnant_evolution(15., 1e5);
+        assert!(neutron_star.generated.phase == StellarPhase::NeutronStar);
+        assert!(neutron_star.state == evolve(&neutron_star, neutron_star.age));
+        assert!(
+            (neutron_star.generated.effective_temp - NEUTRON_STAR_BIRTH_TEMP / 2f64.powf(0.35))
+                .abs()
+                < 1.
+        );
+        let later = evolve(&neutron_star, neutron_star.age + 1e6);
+        assert!(later.phase == StellarPhase::NeutronStar);
+        assert!(later.effective_temp < neutron_star.generated.effective_temp);
+        assert!(remnant_evolution(40., 0.).state == black_hole);
+    }
+
+    #[test]
+    fn test_evolved_generation() {
+        let generated_as = |phase: StellarPhase, state: StellarState| {
+            let initial_mass = match phase {
+                StellarPhase::WhiteDwarf => progenitor_mass(state.mass),
+                _ => state.mass,
+            };
+            let lifetime = main_sequence_lifetime(initial_mass);
+            let age = phase_age(phase, lifetime, state.effective_temp);
+            let generated = StellarState { phase, ..state };
+            StellarEvolution {
+                initial_mass,
+                lifetime,
+                age,
+                generated_age: age,
+                generated,
+                state: generated,
+            }
+        };
+
+        let giant = generated_as(
+            StellarPhase::Giant,
+            StellarState {
+                phase: StellarPhase::Giant,
+                mass: 2.5,
+                radius: 15.,
+                luminosity: 95.6,
+                effective_temp: 4660.,
+            },
+        );
+        let state = evolve(&giant, giant.age);
+        assert!(state.phase == StellarPhase::Giant);
+        assert!((state.radius / giant.generated.radius - 1.).abs() < 1e-9);
+        assert!((state.luminosity / giant.generated.luminosity - 1.).abs() < 1e-9);
+        let later = evolve(&giant, giant.age + 0.5 * GIANT_DURATION * giant.lifetime);
+        assert!(later.phase == StellarPhase::Giant && later.radius > giant.generated.radius);
+
+        let white_dwarf = generated_as(
+            StellarPhase::WhiteDwarf,
+            StellarState {
+                phase: StellarPhase::WhiteDwarf,
+                mass: 0.6,
+                radius: WHITE_DWARF_RADIUS,
+                luminosity: black_body_luminosity(WHITE_DWARF_RADIUS, 8000.),
+                effective_temp: 8000.,
+            },
+        );
+        let state = evolve(&white_dwarf, white_dwarf.age);
+        assert!(state.phase == StellarPhase::WhiteDwarf);
+        assert!((state.mass - 0.6).abs() < 1e-9 && (state.effective_temp - 8000.).abs() < 1e-6);
+        assert!((state.luminosity / white_dwarf.generated.luminosity - 1.).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    consts,
+    sim::components::{
+        CelestialBodyAtmosphere, CelestialBodyCrust, CelestialBodySubstanceProps, Habitability,
+        HabitableZone,
+    },
+};
+
+use super::chemistry::Substance;
+
+/// Effective stellar flux at each habitable zone limit around a Sun-like star and its
+/// dependence on effective temperature, as `[s_eff_sun, a, b, c, d]`.
+///
+/// Kopparapu et al. (2014), for Earth mass planets.
+const RECENT_VENUS: [f64; 5] = [1.776, 2.136e-4, 2.533e-8, -1.332e-11, -3.097e-15];
+const RUNAWAY_GREENHOUSE: [f64; 5] = [1.107, 1.332e-4, 1.580e-8, -8.308e-12, -1.931e-15];
+const MAXIMUM_GREENHOUSE: [f64; 5] = [0.356, 6.171e-5, 1.698e-9, -3.198e-12, -5.575e-16];
+const EARLY_MARS: [f64; 5] = [0.320, 5.547e-5, 1.526e-9, -2.874e-12, -5.011e-16];
+
+/// Effective temperatures the fits above hold for.
+const FIT_TEMP_MIN: f64 = 2600.;
+const FIT_TEMP_MAX: f64 = 7200.;
+
+/// Surface temperature life is scored best at, and how fast the score falls off from it.
+const IDEAL_TEMP: f64 = 288.;
+const TEMP_TOLERANCE: f64 = 40.;
+/// Water content of the crust above which a world counts as fully watered.
+const WATER_CONTENT_FULL: f64 = 0.1;
+
+/// Distance in simulation units equivalent to `au` astronomical units.
+///
+/// Temperatures of planets are computed from the scaled radius of their star and then
+/// scaled themselves, so the stellar flux of a physical distance is found at this one.
+#[inline]
+pub fn au_to_sim_dist(au: f64) -> f64 {
+    au * consts::AU * consts::STAR_RADIUS_SCALE * consts::PLANET_EFFCETIVE_TEMP_SCALE.powi(2)
+}
+
+/// Distance in astronomical units at which a star of `luminosity` in solar luminosities
+/// delivers the effective flux of `limit`.
+fn limit_dist(limit: [f64; 5], luminosity: f64, eff_temp: f64) -> f64 {
+    let t = eff_temp.clamp(FIT_TEMP_MIN, FIT_TEMP_MAX) - 5780.;
+    let [s, a, b, c, d] = limit;
+    let s_eff = s + a * t + b * t.powi(2) + c * t.powi(3) + d * t.powi(4);
+    (luminosity / s_eff).sqrt()
+}
+
+/// Habitable zone of a star of `luminosity` in solar luminosities, in simulation units.
+pub fn habitable_zone(luminosity: f64, eff_temp: f64) -> HabitableZone {
+    let dist = |limit| au_to_sim_dist(limit_dist(limit, luminosity, eff_temp));
+    HabitableZone {
+        optimistic_inner: dist(RECENT_VENUS),
+        conservative_inner: dist(RUNAWAY_GREENHOUSE),
+        conservative_outer: dist(MAXIMUM_GREENHOUSE),
+        optimistic_outer: dist(EARLY_MARS),
+    }
+}
+
+/// 1 within the conservative zone, falling to 0.5 at the edges of the optimistic one and
+/// with the square of the distance ratio beyond.
+pub fn distance_score(zone: &HabitableZone, dist: f64) -> f64 {
+    let score = |edge: f64, optimistic_edge: f64| {
+        let t = (dist - edge) / (optimistic_edge - edge);
+        if t <= 1. {
+            1. - 0.5 * t
+        } else {
+            let ratio = (dist / optimistic_edge).min(optimistic_edge / dist);
+            0.5 * ratio * ratio
+        }
+    };
+
+    if dist < zone.conservative_inner {
+        score(zone.conservative_inner, zone.optimistic_inner)
+    } else if dist > zone.conservative_outer {
+        score(zone.conservative_outer, zone.optimistic_outer)
+    } else {
+        1.
+    }
+}
+
+#[inline]
+pub fn temperature_score(eff_temp: f64) -> f64 {
+    (-((eff_temp - IDEAL_TEMP) / TEMP_TOLERANCE).powi(2) / 2.).exp()
+}
+
+/// Log-normal around the density of Earth's atmosphere, and 0 without one.
+pub fn atmosphere_score(atmo: Option<&CelestialBodyAtmosphere>) -> f64 {
+    atmo.map_or(0., |atmo| {
+        let ln_ratio = (atmo.density / consts::STANDARD_ATMOSPHERE_DENSITY).ln();
+        (-ln_ratio * ln_ratio / 2.).exp()
+    })
+}
+
+/// Water in the crust only counts while it is liquid, which needs an atmosphere.
+pub fn water_score(
+    crust: Option<&CelestialBodyCrust>,
+    atmo: Option<&CelestialBodyAtmosphere>,
+    substance_props: &CelestialBodySubstanceProps,
+    eff_temp: f64,
+) -> f64 {
+    let (Some(crust), Some(_)) = (crust, atmo) else {
+        return 0.;
+    };
+    let Some(water) = substance_props.get(Substance::Water) else {
+        return 0.;
+    };
+    if eff_temp <= water.melting_point || eff_temp >= water.boiling_point {
+        return 0.;
+    }
+
+    let content = crust.content.get_content(Substance::Water).unwrap_or(0.);
+    (content / WATER_CONTENT_FULL).min(1.)
+}
+
+/// Scores a rocky world at `dist` from a star with habitable zone `zone`.
+pub fn habitability(
+    zone: &HabitableZone,
+    dist: f64,
+    eff_temp: f64,
+    crust: Option<&CelestialBodyCrust>,
+    atmo: Option<&CelestialBodyAtmosphere>,
+    substance_props: &CelestialBodySubstanceProps,
+) -> Habitability {
+    let distance = distance_score(zone, dist);
+    let temperature = temperature_score(eff_temp);
+    let atmosphere = atmosphere_score(atmo);
+    let water = water_score(crust, atmo, substance_props, eff_temp);
+    Habitability {
+        distance,
+        temperature,
+        atmosphere,
+        water,
+        overall: (distance * temperature * atmosphere * water).powf(0.25),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_habitable_zone() {
+        let sun = habitable_zone(1., 5780.);
+        let au = au_to_sim_dist(1.);
+        let zone = [
+            sun.optimistic_inner,
+            sun.conservative_inner,
+            sun.conservative_outer,
+            sun.optimistic_outer,
+        ]
+        .map(|dist| dist / au);
+        assert!(zone.windows(2).all(|w| w[0] < w[1]));
+        // Earth and Mars are inside the conservative zone, Venus is too close for either.
+        assert!(zone[1] < 1. && 1.52 < zone[2]);
+        assert!(0.72 < zone[0]);
+
+        let red_dwarf = habitable_zone(0.01, 3000.);
+        assert!(red_dwarf.conservative_outer < sun.conservative_inner);
+    }
+
+    #[test]
+    fn test_scores() {
+        let sun = habitable_zone(1., 5780.);
+        let au = au_to_sim_dist(1.);
+        assert_eq!(distance_score(&sun, au), 1.);
+        assert!((distance_score(&sun, sun.optimistic_outer) - 0.5).abs() < 1e-9);
+        assert!(distance_score(&sun, 5. * au) < 0.1);
+        assert!(distance_score(&sun, 0.1 * au) < 0.1);
+
+        assert_eq!(temperature_score(IDEAL_TEMP), 1.);
+        assert!(temperature_score(200.) < 0.1);
+        assert_eq!(atmosphere_score(None), 0.);
+    }
+}
@@ -1,2 +1,5 @@
 pub mod chemistry;
+pub mod evolution;
+pub mod habitability;
 pub mod physics;
+pub mod stability;
@@ -41,3 +41,78 @@ pub fn planetary_eq_temp_from_lum(luminosity: f64, albedo: f64, dist: f64) -> f6
 pub fn planetary_eq_temp_from_temp(temp: f64, radius: f64, albedo: f64, dist: f64) -> f64 {
     temp * (radius / (2. * dist)).sqrt() * (1. - albedo).powf(0.25)
 }
+
+/// Effective temperature of a planet of `mass` Earth masses that is only warmed by its
+/// internal heat.
+///
+/// Power law through the internal heat fluxes of the Earth (about 35 K) and Jupiter
+/// (about 99 K).
+#[inline]
+pub fn internal_heat_temp(mass: f64) -> f64 {
+    35. * mass.powf(0.18)
+}
+
+/// Largest stable semi-major axis of a planet orbiting one star of a binary (S-type).
+///
+/// `mass_ratio` is the perturbing companion's share of the binary mass. Holman & Wiegert (1999).
+#[inline]
+pub fn s_type_critical_sma(binary_sma: f64, mass_ratio: f64, binary_ecc: f64) -> f64 {
+    let (mu, e) = (mass_ratio, binary_ecc);
+    binary_sma
+        * (0.464 - 0.38 * mu - 0.631 * e + 0.586 * mu * e + 0.15 * e * e - 0.198 * mu * e * e)
+}
+
+/// Smallest stable semi-major axis of a planet orbiting both stars of a binary (P-type).
+///
+/// `mass_ratio` is the secondary's share of the binary mass. Holman & Wiegert (1999).
+#[inline]
+pub fn p_type_critical_sma(binary_sma: f64, mass_ratio: f64, binary_ecc: f64) -> f64 {
+    let (mu, e) = (mass_ratio, binary_ecc);
+    binary_sma
+        * (1.6 + 5.1 * e - 2.22 * e * e + 4.12 * mu - 4.27 * e * mu - 5.09 * mu * mu
+            + 4.61 * e * e * mu * mu)
+}
+
+/// Smallest stable ratio between the outer and inner semi-major axes of a hierarchical triple.
+///
+/// `mass_ratio` is the outer star's mass over the inner binary's. Mardling & Aarseth (2001).
+#[inline]
+pub fn triple_critical_sma_ratio(mass_ratio: f64, outer_ecc: f64) -> f64 {
+    2.8 * ((1. + mass_ratio) * (1. + outer_ecc)).powf(0.4) * (1. - outer_ecc).powf(-1.2)
+}
+
+/// Distance from a body within which a fluid satellite is torn apart by tides.
+///
+/// Densities only need to share a unit.
+#[inline]
+pub fn roche_limit(primary_radius: f64, primary_density: f64, satellite_density: f64) -> f64 {
+    2.44 * primary_radius * (primary_density / satellite_density).cbrt()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binary_critical_sma() {
+        assert!((s_type_critical_sma(1., 0.5, 0.) - 0.274).abs() < 1e-12);
+        assert!((p_type_critical_sma(1., 0.5, 0.) - 2.3875).abs() < 1e-12);
+        assert!(s_type_critical_sma(1., 0.5, 0.5) < s_type_critical_sma(1., 0.5, 0.));
+        assert!(p_type_critical_sma(1., 0.5, 0.5) > p_type_critical_sma(1., 0.5, 0.));
+    }
+
+    #[test]
+    fn test_roche_limit() {
+        // Saturn and water ice, the main rings end around 2.3 Saturn radii.
+        let limit = roche_limit(1., 0.687, 0.917);
+        assert!((limit - 2.216).abs() < 1e-3);
+        assert!(roche_limit(1., 0.687, 3.) < limit);
+    }
+
+    #[test]
+    fn test_internal_heat_temp() {
+        assert_eq!(internal_heat_temp(1.), 35.);
+        // Jupiter.
+        assert!((internal_heat_temp(317.8) - 99.).abs() < 1.);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{consts, sim::resources::CelestialBody, sim::SimVec};
+
+/// Keplerian orbit of a planet around its host, enough to screen its stability.
+#[derive(Clone, Copy)]
+pub struct OrbitElements {
+    pub mass: f64,
+    pub sma: f64,
+    pub ecc: f64,
+}
+
+impl OrbitElements {
+    /// Orbit of a body at `dist` from its host, set off perpendicular to the host with the
+    /// speed of an orbit of semi-major axis `sma`, so that it starts at periapsis.
+    #[inline]
+    pub fn from_periapsis(mass: f64, dist: f64, sma: f64) -> Self {
+        Self {
+            mass,
+            sma,
+            ecc: (1. - dist / sma).max(0.),
+        }
+    }
+
+    #[inline]
+    pub fn periapsis(&self) -> f64 {
+        self.sma * (1. - self.ecc)
+    }
+
+    #[inline]
+    pub fn apoapsis(&self) -> f64 {
+        self.sma * (1. + self.ecc)
+    }
+}
+
+/// Mutual Hill radius of two planets around a host of `host_mass`.
+#[inline]
+pub fn mutual_hill_radius(host_mass: f64, inner: &OrbitElements, outer: &OrbitElements) -> f64 {
+    ((inner.mass + outer.mass) / (3. * host_mass)).cbrt() * (inner.sma + outer.sma) / 2.
+}
+
+/// Separation of two planets in mutual Hill radii.
+///
+/// Systems of several planets last for billions of orbits once neighbours are about 8 to
+/// 10 apart. Chambers et al. (1996).
+#[inline]
+pub fn hill_spacing(host_mass: f64, inner: &OrbitElements, outer: &OrbitElements) -> f64 {
+    (outer.sma - inner.sma) / mutual_hill_radius(host_mass, inner, outer)
+}
+
+/// Whether the orbits of two planets can never cross, no matter how long they interact.
+///
+/// Gladman (1993), which reduces to a separation of `2√3` mutual Hill radii for circular
+/// orbits.
+pub fn is_hill_stable(host_mass: f64, inner: &OrbitElements, outer: &OrbitElements) -> bool {
+    if outer.periapsis() <= inner.apoapsis() {
+        return false;
+    }
+
+    let (mu1, mu2) = (inner.mass / host_mass, outer.mass / host_mass);
+    let alpha = mu1 + mu2;
+    let (gamma1, gamma2) = (
+        (1. - inner.ecc * inner.ecc).sqrt(),
+        (1. - outer.ecc * outer.ecc).sqrt(),
+    );
+    let delta = (outer.sma / inner.sma).sqrt();
+
+    let lhs = (mu1 + mu2 / (delta * delta)) * (mu1 * gamma1 + mu2 * gamma2 * delta).powi(2)
+        / alpha.powi(3);
+    let rhs = 1. + 3f64.powf(4. / 3.) * mu1 * mu2 / alpha.powf(4. / 3.);
+    lhs > rhs
+}
+
+/// Orbital period of a body with semi-major axis `sma` around a host of `host_mass`.
+#[inline]
+pub fn orbital_period(host_mass: f64, sma: f64) -> f64 {
+    std::f64::consts::TAU * (sma.powi(3) / (consts::G * host_mass)).sqrt()
+}
+
+/// Time-averaged MEGNO of `bodies` over `num_steps` steps of `dt`.
+///
+/// Quasi-periodic systems settle at 2, while chaotic ones grow with time. The bodies are
+/// integrated with leapfrog along with a tangent vector, which is renormalised every
+/// step. Cincotta & Simó (2000).
+pub fn megno(bodies: &[CelestialBody], dt: f64, num_steps: usize) -> f64 {
+    let n = bodies.len();
+    let mass = bodies.iter().map(|body| body.mass()).collect::<Vec<_>>();
+    let mut pos = bodies.iter().map(|body| body.pos()).collect::<Vec<_>>();
+    let mut vel = bodies.iter().map(|body| body.vel()).collect::<Vec<_>>();
+
+    // Any tangent vector works as long as it isn't special, so it's fixed for determinism.
+    let mut d_pos = (0..n)
+        .map(|i| SimVec::ONE * if i % 2 == 0 { 1. } else { -1. })
+        .collect::<Vec<_>>();
+    let mut d_vel = vec![SimVec::ZERO; n];
+    let norm = tangent_norm(&d_pos, &d_vel);
+    d_pos.iter_mut().for_each(|d| *d /= norm);
+
+    let (mut acc, mut d_acc) = (vec![SimVec::ZERO; n], vec![SimVec::ZERO; n]);
+    accelerations(&mass, &pos, &d_pos, &mut acc, &mut d_acc);
+
+    let (mut y, mut mean_y) = (0., 0.);
+    for k in 1..=num_steps {
+        for i in 0..n {
+            vel[i] += acc[i] * (dt / 2.);
+            d_vel[i] += d_acc[i] * (dt / 2.);
+            pos[i] += vel[i] * dt;
+            d_pos[i] += d_vel[i] * dt;
+        }
+        accelerations(&mass, &pos, &d_pos, &mut acc, &mut d_acc);
+        for i in 0..n {
+            vel[i] += acc[i] * (dt / 2.);
+            d_vel[i] += d_acc[i] * (dt / 2.);
+        }
+
+        let norm = tangent_norm(&d_pos, &d_vel);
+        let k = k as f64;
+        y = y * (k - 1.) / k + 2. * norm.ln();
+        mean_y = (mean_y * (k - 1.) + y) / k;
+
+        // The tangent equations are linear, so everything along it scales alike.
+        for d in d_pos.iter_mut().chain(&mut d_vel).chain(&mut d_acc) {
+            *d /= norm;
+        }
+    }
+    mean_y
+}
+
+fn tangent_norm(d_pos: &[SimVec], d_vel: &[SimVec]) -> f64 {
+    d_pos
+        .iter()
+        .chain(d_vel)
+        .map(|d| d.length_squared())
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Gravitational accelerations of the bodies, and their variations along the tangent
+/// vector `d_pos`.
+fn accelerations(
+    mass: &[f64],
+    pos: &[SimVec],
+    d_pos: &[SimVec],
+    acc: &mut [SimVec],
+    d_acc: &mut [SimVec],
+) {
+    acc.fill(SimVec::ZERO);
+    d_acc.fill(SimVec::ZERO);
+    for i in 0..pos.len() {
+        for j in i + 1..pos.len() {
+            let r = pos[j] - pos[i];
+            let dr = d_pos[j] - d_pos[i];
+            let dist_sq = r.length_squared();
+            let inv_dist3 = 1. / (dist_sq * dist_sq.sqrt());
+
+            let f = r * (consts::G * inv_dist3);
+            let df = (dr - r * (3. * r.dot(dr) / dist_sq)) * (consts::G * inv_dist3);
+            acc[i] += f * mass[j];
+            acc[j] -= f * mass[i];
+            d_acc[i] += df * mass[j];
+            d_acc[j] -= df * mass[i];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn circular(mass: f64, sma: f64) -> OrbitElements {
+        OrbitElements { mass, sma, ecc: 0. }
+    }
+
+    #[test]
+    fn test_hill_stability() {
+        let (host, mu) = (1., 1e-6);
+        let inner = circular(mu, 1.);
+        // Two-planet Hill stability needs 2√3 mutual Hill radii between circular orbits.
+        let critical = 2. * 3f64.sqrt() * (2. * mu / 3.).cbrt();
+        let stable = circular(mu, 1. + critical * 1.05);
+        let unstable = circular(mu, 1. + critical * 0.95);
+        assert!(is_hill_stable(host, &inner, &stable));
+        assert!(!is_hill_stable(host, &inner, &unstable));
+        assert!((hill_spacing(host, &inner, &stable) - 2. * 3f64.sqrt()).abs() < 0.3);
+
+        let eccentric = OrbitElements { ecc: 0.3, ..stable };
+        assert!(!is_hill_stable(host, &inner, &eccentric));
+        assert!((OrbitElements::from_periapsis(mu, 0.9, 1.).ecc - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_megno() {
+        let host_mass = 1. / consts::G;
+        let planet = |mass: f64, sma: f64| {
+            let speed = (consts::G * host_mass / sma).sqrt();
+            CelestialBody::new(SimVec::X * sma, 0., mass, SimVec::Y * speed)
+        };
+        let host = CelestialBody::new(SimVec::ZERO, 0., host_mass, SimVec::ZERO);
+        let dt = orbital_period(host_mass, 1.) / 100.;
+
+        let regular = megno(&[host, planet(1e-5 * host_mass, 1.)], dt, 2000);
+        assert!((regular - 2.).abs() < 0.2, "{}", regular);
+
+        let pair = |outer_sma: f64| {
+            megno(
+                &[
+                    host,
+                    planet(1e-3 * host_mass, 1.),
+                    planet(1e-3 * host_mass, outer_sma),
+                ],
+                dt,
+                2000,
+            )
+        };
+        let spaced = pair(1.6);
+        assert!((spaced - 2.).abs() < 0.5, "{}", spaced);
+        // Massive neighbours within 2√3 mutual Hill radii scatter each other.
+        let chaotic = pair(1.15);
+        assert!(chaotic > 5., "{}", chaotic);
+    }
+}
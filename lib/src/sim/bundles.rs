@@ -1,23 +1,158 @@
-use bevy::ecs::bundle::Bundle;
+use bevy::{
+    ecs::bundle::Bundle,
+    render::color::Color,
+    sprite::{ColorMaterial, MaterialMesh2dBundle},
+};
 
 use super::components::{
-    CelestialBodyAtmosphere, CelestialBodyColor, CelestialBodyCrust, CelestialBodyEffectiveTemp,
-    CelestialBodyId, CelestialBodyName, CelestialBodySubstanceProps, Moon, Planet, PlanetType,
-    Star, StarClass, StarLuminosity,
+    AccretionDisk, BeltBody, BeltType, BrownDwarf, CelestialBodyAtmosphere, CelestialBodyColor,
+    CelestialBodyCrust, CelestialBodyEffectiveTemp, CelestialBodyId, CelestialBodyName,
+    CelestialBodySubstanceProps, Comet, CompactObject, Habitability, HabitableZone, Interstellar,
+    Moon, Planet, PlanetRing, PlanetType, RoguePlanet, Star, StarClass, StarLuminosity,
+    StellarEvolution, StellarHeating,
 };
 
 pub enum CelestialBodyBundle {
     Star(StarBundle),
+    BrownDwarf(BrownDwarfBundle),
+    CompactObject {
+        object: CompactObjectBundle,
+        /// Only black holes have one.
+        disk: Option<Box<AccretionDiskBundle>>,
+    },
     Planet {
         planet: PlanetBundle,
         crust: Option<CelestialBodyCrust>,
         atmo: Option<CelestialBodyAtmosphere>,
+        ring: Option<Box<RingBundle>>,
+        /// Only rocky planets are scored.
+        habitability: Option<Habitability>,
+    },
+    RoguePlanet {
+        planet: RoguePlanetBundle,
+        crust: Option<CelestialBodyCrust>,
+        atmo: Option<CelestialBodyAtmosphere>,
+        /// Only those injected during the simulation have one.
+        interstellar: Option<Interstellar>,
     },
     Moon {
         moon: MoonBundle,
         crust: CelestialBodyCrust,
         atmo: Option<CelestialBodyAtmosphere>,
+        habitability: Habitability,
+    },
+    BeltBody {
+        body: BeltBodyBundle,
+        crust: CelestialBodyCrust,
     },
+    Comet {
+        comet: CometBundle,
+        crust: CelestialBodyCrust,
+    },
+}
+
+impl CelestialBodyBundle {
+    /// The star of stars, brown dwarfs and compact objects, which all shine on their own.
+    pub fn star(&self) -> Option<&StarBundle> {
+        match self {
+            CelestialBodyBundle::Star(star) => Some(star),
+            CelestialBodyBundle::BrownDwarf(brown_dwarf) => Some(&brown_dwarf.star),
+            CelestialBodyBundle::CompactObject { object, .. } => Some(&object.star),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> CelestialBodyId {
+        match self {
+            CelestialBodyBundle::Star(star) => star.id,
+            CelestialBodyBundle::BrownDwarf(brown_dwarf) => brown_dwarf.star.id,
+            CelestialBodyBundle::CompactObject { object, .. } => object.star.id,
+            CelestialBodyBundle::Planet { planet, .. } => planet.id,
+            CelestialBodyBundle::RoguePlanet { planet, .. } => planet.id,
+            CelestialBodyBundle::Moon { moon, .. } => moon.id,
+            CelestialBodyBundle::BeltBody { body, .. } => body.id,
+            CelestialBodyBundle::Comet { comet, .. } => comet.id,
+        }
+    }
+
+    pub fn set_id(&mut self, id: CelestialBodyId) {
+        match self {
+            CelestialBodyBundle::Star(star) => star.id = id,
+            CelestialBodyBundle::BrownDwarf(brown_dwarf) => brown_dwarf.star.id = id,
+            CelestialBodyBundle::CompactObject { object, .. } => object.star.id = id,
+            CelestialBodyBundle::Planet { planet, .. } => planet.id = id,
+            CelestialBodyBundle::RoguePlanet { planet, .. } => planet.id = id,
+            CelestialBodyBundle::Moon { moon, .. } => moon.id = id,
+            CelestialBodyBundle::BeltBody { body, .. } => body.id = id,
+            CelestialBodyBundle::Comet { comet, .. } => comet.id = id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            CelestialBodyBundle::Star(star) => &star.name.0,
+            CelestialBodyBundle::BrownDwarf(brown_dwarf) => &brown_dwarf.star.name.0,
+            CelestialBodyBundle::CompactObject { object, .. } => &object.star.name.0,
+            CelestialBodyBundle::Planet { planet, .. } => &planet.name.0,
+            CelestialBodyBundle::RoguePlanet { planet, .. } => &planet.name.0,
+            CelestialBodyBundle::Moon { moon, .. } => &moon.name.0,
+            CelestialBodyBundle::BeltBody { body, .. } => &body.name.0,
+            CelestialBodyBundle::Comet { comet, .. } => &comet.name.0,
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        match self {
+            CelestialBodyBundle::Star(star) => star.name.0 = name,
+            CelestialBodyBundle::BrownDwarf(brown_dwarf) => brown_dwarf.star.name.0 = name,
+            CelestialBodyBundle::CompactObject { object, .. } => object.star.name.0 = name,
+            CelestialBodyBundle::Planet { planet, .. } => planet.name.0 = name,
+            CelestialBodyBundle::RoguePlanet { planet, .. } => planet.name.0 = name,
+            CelestialBodyBundle::Moon { moon, .. } => moon.name.0 = name,
+            CelestialBodyBundle::BeltBody { body, .. } => body.name.0 = name,
+            CelestialBodyBundle::Comet { comet, .. } => comet.name.0 = name,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            CelestialBodyBundle::Star(star) => star.color.0,
+            CelestialBodyBundle::BrownDwarf(brown_dwarf) => brown_dwarf.star.color.0,
+            CelestialBodyBundle::CompactObject { object, .. } => object.star.color.0,
+            CelestialBodyBundle::Planet { planet, .. } => planet.color.0,
+            CelestialBodyBundle::RoguePlanet { planet, .. } => planet.color.0,
+            CelestialBodyBundle::Moon { moon, .. } => moon.color.0,
+            CelestialBodyBundle::BeltBody { body, .. } => body.color.0,
+            CelestialBodyBundle::Comet { comet, .. } => comet.color.0,
+        }
+    }
+
+    /// The star that heats the body, which stars and rogue planets don't have.
+    pub fn heating(&self) -> Option<&StellarHeating> {
+        match self {
+            CelestialBodyBundle::Star(_)
+            | CelestialBodyBundle::BrownDwarf(_)
+            | CelestialBodyBundle::CompactObject { .. }
+            | CelestialBodyBundle::RoguePlanet { .. } => None,
+            CelestialBodyBundle::Planet { planet, .. } => Some(&planet.heating),
+            CelestialBodyBundle::Moon { moon, .. } => Some(&moon.heating),
+            CelestialBodyBundle::BeltBody { body, .. } => Some(&body.heating),
+            CelestialBodyBundle::Comet { comet, .. } => Some(&comet.heating),
+        }
+    }
+
+    pub fn heating_mut(&mut self) -> Option<&mut StellarHeating> {
+        match self {
+            CelestialBodyBundle::Star(_)
+            | CelestialBodyBundle::BrownDwarf(_)
+            | CelestialBodyBundle::CompactObject { .. }
+            | CelestialBodyBundle::RoguePlanet { .. } => None,
+            CelestialBodyBundle::Planet { planet, .. } => Some(&mut planet.heating),
+            CelestialBodyBundle::Moon { moon, .. } => Some(&mut moon.heating),
+            CelestialBodyBundle::BeltBody { body, .. } => Some(&mut body.heating),
+            CelestialBodyBundle::Comet { comet, .. } => Some(&mut comet.heating),
+        }
+    }
 }
 
 #[derive(Bundle, Clone)]
@@ -29,11 +164,45 @@ pub struct StarBundle {
     pub composition: CelestialBodyCrust,
     pub effective_temp: CelestialBodyEffectiveTemp,
     pub luminosity: StarLuminosity,
+    pub habitable_zone: HabitableZone,
+    pub evolution: StellarEvolution,
     pub tag: Star,
 }
 
+#[derive(Bundle, Clone)]
+pub struct BrownDwarfBundle {
+    pub star: StarBundle,
+    pub tag: BrownDwarf,
+}
+
+#[derive(Bundle, Clone)]
+pub struct CompactObjectBundle {
+    pub star: StarBundle,
+    pub kind: CompactObject,
+}
+
+/// Spawned as a child of its black hole.
+#[derive(Bundle, Clone)]
+pub struct AccretionDiskBundle {
+    pub disk: AccretionDisk,
+    pub mesh: MaterialMesh2dBundle<ColorMaterial>,
+}
+
 #[derive(Bundle, Clone)]
 pub struct PlanetBundle {
+    pub id: CelestialBodyId,
+    pub color: CelestialBodyColor,
+    pub name: CelestialBodyName,
+    pub effective_temp: CelestialBodyEffectiveTemp,
+    pub substance_props: CelestialBodySubstanceProps,
+    pub heating: StellarHeating,
+    pub ty: PlanetType,
+    pub tag: Planet,
+}
+
+/// A planet without a star, whose effective temperature comes from its internal heat.
+#[derive(Bundle, Clone)]
+pub struct RoguePlanetBundle {
     pub id: CelestialBodyId,
     pub color: CelestialBodyColor,
     pub name: CelestialBodyName,
@@ -41,6 +210,14 @@ pub struct PlanetBundle {
     pub substance_props: CelestialBodySubstanceProps,
     pub ty: PlanetType,
     pub tag: Planet,
+    pub rogue: RoguePlanet,
+}
+
+/// Spawned as a child of its planet.
+#[derive(Bundle, Clone)]
+pub struct RingBundle {
+    pub ring: PlanetRing,
+    pub mesh: MaterialMesh2dBundle<ColorMaterial>,
 }
 
 #[derive(Bundle, Clone)]
@@ -50,5 +227,29 @@ pub struct MoonBundle {
     pub name: CelestialBodyName,
     pub effective_temp: CelestialBodyEffectiveTemp,
     pub substance_props: CelestialBodySubstanceProps,
+    pub heating: StellarHeating,
     pub tag: Moon,
 }
+
+#[derive(Bundle, Clone)]
+pub struct BeltBodyBundle {
+    pub id: CelestialBodyId,
+    pub color: CelestialBodyColor,
+    pub name: CelestialBodyName,
+    pub effective_temp: CelestialBodyEffectiveTemp,
+    pub substance_props: CelestialBodySubstanceProps,
+    pub heating: StellarHeating,
+    pub ty: BeltType,
+    pub tag: BeltBody,
+}
+
+#[derive(Bundle, Clone)]
+pub struct CometBundle {
+    pub id: CelestialBodyId,
+    pub color: CelestialBodyColor,
+    pub name: CelestialBodyName,
+    pub effective_temp: CelestialBodyEffectiveTemp,
+    pub substance_props: CelestialBodySubstanceProps,
+    pub heating: StellarHeating,
+    pub tag: Comet,
+}
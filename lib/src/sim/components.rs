@@ -1,7 +1,10 @@
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Formatter, Result},
+};
 
 use crate::sci::chemistry::{Substance, SubstanceContent, SubstanceProperty};
-use bevy::{ecs::component::Component, render::color::Color};
+use bevy::{ecs::component::Component, math::DVec2, render::color::Color};
 
 #[cfg(feature = "debug")]
 use bevy::reflect::Reflect;
@@ -60,6 +63,8 @@ pub struct Star;
 pub struct StarClass {
     pub ty: SpectralType,
     pub sub_ty: u8,
+    #[serde(default)]
+    pub lum: LuminosityClass,
 }
 
 impl PartialOrd for StarClass {
@@ -70,24 +75,28 @@ impl PartialOrd for StarClass {
 
 impl Ord for StarClass {
     fn cmp(&self, other: &Self) -> Ordering {
-        let t = (self.ty as u8).cmp(&(other.ty as u8));
-        if t == Ordering::Equal {
-            self.sub_ty.cmp(&other.sub_ty)
-        } else {
-            t
-        }
+        (self.ty as u8)
+            .cmp(&(other.ty as u8))
+            .then(self.sub_ty.cmp(&other.sub_ty))
+            .then(self.lum.cmp(&other.lum))
     }
 }
 
 impl StarClass {
+    /// Index of the spectral type and subtype among the tabulated ones, which start at O3.
+    /// It is the same for every luminosity class.
     #[inline]
     pub fn to_index(self) -> usize {
-        (self.ty as usize)
-            .checked_sub(1)
-            .unwrap_or(self.sub_ty as usize + 10 - 3 % 10)
-            * 10
-            + self.sub_ty as usize
-            + 7
+        (self.ty as usize * 10 + self.sub_ty as usize).saturating_sub(3)
+    }
+}
+
+impl Display for StarClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.lum {
+            LuminosityClass::WhiteDwarf => write!(f, "D{}{}", self.ty, self.sub_ty),
+            lum => write!(f, "{}{}{}", self.ty, self.sub_ty, lum),
+        }
     }
 }
 
@@ -101,15 +110,248 @@ pub enum SpectralType {
     G,
     K,
     M,
+    L,
+    T,
+    Y,
+}
+
+impl SpectralType {
+    /// Whether the type is one of brown dwarfs, too light to fuse hydrogen.
+    #[inline]
+    pub fn is_substellar(self) -> bool {
+        matches!(self, SpectralType::L | SpectralType::T | SpectralType::Y)
+    }
+}
+
+impl Display for SpectralType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let letter = match self {
+            SpectralType::O => "O",
+            SpectralType::B => "B",
+            SpectralType::A => "A",
+            SpectralType::F => "F",
+            SpectralType::G => "G",
+            SpectralType::K => "K",
+            SpectralType::M => "M",
+            SpectralType::L => "L",
+            SpectralType::T => "T",
+            SpectralType::Y => "Y",
+        };
+        write!(f, "{}", letter)
+    }
+}
+
+/// Morgan–Keenan luminosity class, which tells apart stars of the same spectral type by
+/// their size. Classes order from the brightest to white dwarfs.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub enum LuminosityClass {
+    #[serde(rename = "I")]
+    Supergiant,
+    #[serde(rename = "II")]
+    BrightGiant,
+    #[serde(rename = "III")]
+    Giant,
+    #[serde(rename = "IV")]
+    Subgiant,
+    #[default]
+    #[serde(rename = "V")]
+    Dwarf,
+    /// Given the spectral type of main-sequence stars of the same effective temperature.
+    #[serde(rename = "D")]
+    WhiteDwarf,
+}
+
+impl LuminosityClass {
+    /// Phase stars of the class are in.
+    #[inline]
+    pub fn phase(self) -> StellarPhase {
+        match self {
+            LuminosityClass::Supergiant | LuminosityClass::BrightGiant | LuminosityClass::Giant => {
+                StellarPhase::Giant
+            }
+            LuminosityClass::Subgiant => StellarPhase::Subgiant,
+            LuminosityClass::Dwarf => StellarPhase::MainSequence,
+            LuminosityClass::WhiteDwarf => StellarPhase::WhiteDwarf,
+        }
+    }
+}
+
+impl Display for LuminosityClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let numeral = match self {
+            LuminosityClass::Supergiant => "I",
+            LuminosityClass::BrightGiant => "II",
+            LuminosityClass::Giant => "III",
+            LuminosityClass::Subgiant => "IV",
+            LuminosityClass::Dwarf => "V",
+            LuminosityClass::WhiteDwarf => "D",
+        };
+        write!(f, "{}", numeral)
+    }
 }
 
 #[derive(Component, Clone, Copy, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "debug", derive(Reflect, Debug))]
 pub struct StarLuminosity(pub f64);
 
+/// Marks a star that is a brown dwarf, which glows from its contraction and cools for
+/// ever instead of evolving.
+#[derive(Component, Clone)]
+pub struct BrownDwarf;
+
+/// What a dead star left behind.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub enum CompactObject {
+    WhiteDwarf,
+    NeutronStar,
+    BlackHole,
+}
+
+impl CompactObject {
+    /// The compact object stars in `phase` are, if they are remnants.
+    #[inline]
+    pub fn of(phase: StellarPhase) -> Option<Self> {
+        match phase {
+            StellarPhase::WhiteDwarf => Some(CompactObject::WhiteDwarf),
+            StellarPhase::NeutronStar => Some(CompactObject::NeutronStar),
+            StellarPhase::BlackHole => Some(CompactObject::BlackHole),
+            _ => None,
+        }
+    }
+}
+
+/// Disk of gas spiralling into a black hole, spawned as a child of it.
+#[derive(Component, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct AccretionDisk {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub color: Color,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub enum StellarPhase {
+    MainSequence,
+    Subgiant,
+    Giant,
+    WhiteDwarf,
+    NeutronStar,
+    BlackHole,
+}
+
+impl StellarPhase {
+    #[inline]
+    pub fn is_remnant(self) -> bool {
+        matches!(
+            self,
+            StellarPhase::WhiteDwarf | StellarPhase::NeutronStar | StellarPhase::BlackHole
+        )
+    }
+}
+
+impl Display for StellarPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let name = match self {
+            StellarPhase::MainSequence => "main-sequence star",
+            StellarPhase::Subgiant => "subgiant",
+            StellarPhase::Giant => "giant",
+            StellarPhase::WhiteDwarf => "white dwarf",
+            StellarPhase::NeutronStar => "neutron star",
+            StellarPhase::BlackHole => "black hole",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Properties of a star at some age, in solar units and kelvins.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct StellarState {
+    pub phase: StellarPhase,
+    pub mass: f64,
+    pub radius: f64,
+    pub luminosity: f64,
+    pub effective_temp: f64,
+}
+
+/// Age of a star and where it is on its evolutionary track.
+#[derive(Component, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct StellarEvolution {
+    /// Mass on the main sequence, in solar masses.
+    pub initial_mass: f64,
+    /// Main-sequence lifetime in years.
+    pub lifetime: f64,
+    /// In years.
+    pub age: f64,
+    /// Age the star was generated at, with the properties interpolated from
+    /// [`StarProperties`](crate::assets::settings::StarProperties).
+    pub generated_age: f64,
+    pub generated: StellarState,
+    pub state: StellarState,
+}
+
+/// The star that heats a planet, moon or belt body.
+///
+/// The body's effective temperature follows the star's luminosity from the one it had
+/// when the body was generated.
+#[derive(Component, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct StellarHeating {
+    pub star: CelestialBodyId,
+    pub luminosity: f64,
+    pub effective_temp: f64,
+}
+
+/// Distances from a star at which a planet can keep liquid water on its surface.
+///
+/// The conservative zone lies between the runaway and maximum greenhouse limits, and the
+/// optimistic one between the recent Venus and early Mars limits.
+#[derive(Component, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct HabitableZone {
+    pub optimistic_inner: f64,
+    pub conservative_inner: f64,
+    pub conservative_outer: f64,
+    pub optimistic_outer: f64,
+}
+
+/// How suitable a rocky planet or moon is for life, each score between 0 and 1.
+///
+/// Rescored as the star it orbits evolves.
+#[derive(Component, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct Habitability {
+    /// Position relative to the habitable zone of the star.
+    pub distance: f64,
+    pub temperature: f64,
+    /// Presence of an atmosphere and how close its pressure is to Earth's.
+    pub atmosphere: f64,
+    /// Amount of water that is liquid on the surface.
+    pub water: f64,
+    /// Geometric mean of the other scores, so a world failing any of them scores 0.
+    pub overall: f64,
+}
+
 #[derive(Component, Clone)]
 pub struct Planet;
 
+/// Marks a planet that belongs to no star system and is only warmed by its internal heat.
+#[derive(Component, Clone)]
+pub struct RoguePlanet;
+
+/// Marks a rogue planet sent on a hyperbolic trajectory through a system during the
+/// simulation.
+#[derive(Component, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct Interstellar {
+    /// Index of the system it was aimed at.
+    pub system: usize,
+}
+
 #[derive(Component, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "debug", derive(Reflect, Debug))]
 pub enum PlanetType {
@@ -118,5 +360,86 @@ pub enum PlanetType {
     Rocky,
 }
 
+/// Rings of a giant planet, lying inside its Roche limit.
+#[derive(Component, Clone)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct PlanetRing {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    /// Normal optical depth, rings let `exp(-optical_depth)` of the light through.
+    pub optical_depth: f64,
+    pub color: Color,
+    pub content: SubstanceContent,
+}
+
 #[derive(Component, Clone)]
 pub struct Moon;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub enum BeltType {
+    /// Rocky belt in a gap between two planets.
+    Asteroid,
+    /// Icy belt beyond the outermost planet.
+    Kuiper,
+}
+
+/// Marks a small body of a belt, which is simulated as a massless test particle.
+#[derive(Component, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct BeltBody {
+    /// Mass the body was generated with, which is left out of the simulation.
+    pub mass: f64,
+}
+
+/// An icy body on an eccentric orbit, which sheds gas and dust while its surface is
+/// warm enough for its ices to sublimate. Simulated as a massless test particle.
+#[derive(Component, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct Comet {
+    /// Mass left, which is left out of the simulation.
+    pub mass: f64,
+    /// Lowest sublimation point among its ices.
+    pub sublimation_point: f64,
+    /// Fraction of its mass lost per unit of simulation time at the sublimation point.
+    pub mass_loss_rate: f64,
+}
+
+impl Comet {
+    /// Whether its ices sublimate at a surface temperature of `temp`.
+    #[inline]
+    pub fn is_active(&self, temp: f64) -> bool {
+        temp > self.sublimation_point
+    }
+
+    /// Mass lost over `duration` of simulation time at a surface temperature of `temp`.
+    ///
+    /// Sublimation is driven by the starlight absorbed, which goes as the fourth power of
+    /// the temperature.
+    pub fn mass_loss(&self, temp: f64, duration: f64) -> f64 {
+        if !self.is_active(temp) {
+            return 0.;
+        }
+        let rate = self.mass_loss_rate * (temp / self.sublimation_point).powi(4);
+        self.mass * (1. - (-rate * duration).exp())
+    }
+}
+
+/// Coma and tail of an active comet, in simulation units.
+#[derive(Component, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct CometActivity {
+    pub coma_radius: f64,
+    /// Points away from the star in the view plane, as long as the tail.
+    pub tail: DVec2,
+}
+
+/// Marks a body that is no longer bound to the body it was orbiting.
+#[derive(Component, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct Escaping {
+    /// The body it escapes from.
+    pub parent: CelestialBodyId,
+    /// Specific orbital energy relative to the parent, always positive.
+    pub specific_energy: f64,
+}
@@ -1,49 +1,150 @@
-use bevy::app::{App, FixedUpdate, Plugin, Update};
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{event::Event, schedule::IntoSystemConfigs},
+    math::DVec2,
+};
 
-use self::resources::{OrbitPredictor, SimulationTimeScale};
+#[cfg(feature = "sim3d")]
+use bevy::math::DVec3;
+
+use crate::assets::GlobalConfig;
+
+use self::{
+    components::{CelestialBodyId, Escaping},
+    resources::{OrbitPredictor, SimulationTimeScale, ViewPlane},
+};
 
 pub mod bundles;
 pub mod components;
 pub mod resources;
+pub mod storage;
 pub mod systems;
 
+/// Vector type of the simulation space.
+///
+/// Bodies move in a single plane unless the `sim3d` feature is enabled.
+#[cfg(not(feature = "sim3d"))]
+pub type SimVec = DVec2;
+#[cfg(feature = "sim3d")]
+pub type SimVec = DVec3;
+
+#[cfg(not(feature = "sim3d"))]
+pub const SIM_DIM: usize = 2;
+#[cfg(feature = "sim3d")]
+pub const SIM_DIM: usize = 3;
+
+/// Lifts a point on the reference plane into simulation space.
+#[inline]
+pub fn from_plane(v: DVec2) -> SimVec {
+    #[cfg(not(feature = "sim3d"))]
+    return v;
+    #[cfg(feature = "sim3d")]
+    return v.extend(0.);
+}
+
+/// Drops the simulation space onto the reference plane.
+#[inline]
+pub fn to_plane(v: SimVec) -> DVec2 {
+    #[cfg(not(feature = "sim3d"))]
+    return v;
+    #[cfg(feature = "sim3d")]
+    return v.truncate();
+}
+
 pub struct CosmosSimPlugin;
 
 impl Plugin for CosmosSimPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            FixedUpdate,
-            (systems::universal_gravitation, systems::transform_syncer),
+            Update,
+            (
+                systems::universal_gravitation,
+                systems::escape_detector,
+                systems::transform_syncer,
+            )
+                .chain(),
         );
 
-        app.add_systems(Update, systems::orbit_drawer);
+        app.add_systems(Update, (systems::orbit_drawer, systems::comet_drawer));
+        app.add_systems(
+            Update,
+            (
+                systems::stellar_evolution.after(systems::universal_gravitation),
+                systems::comet_activity.after(systems::stellar_evolution),
+            ),
+        );
+
+        let config = app.world.resource::<GlobalConfig>();
+        let (scheduler, ejection_policy, stellar_clock) = (
+            config.sim_scheduler.clone(),
+            config.ejection_policy,
+            config.stellar_clock.clone(),
+        );
+        app.insert_resource(scheduler)
+            .insert_resource(ejection_policy)
+            .insert_resource(stellar_clock);
+
+        app.add_event::<CelestialBodyEjected>();
 
         app.init_resource::<OrbitPredictor>()
-            .init_resource::<SimulationTimeScale>();
+            .init_resource::<SimulationTimeScale>()
+            .init_resource::<ViewPlane>();
 
         #[cfg(feature = "debug")]
         {
             use components::*;
             use resources::*;
+            use storage::*;
 
             app.register_type::<CelestialBodyId>()
                 .register_type::<CelestialBodyName>()
                 .register_type::<CelestialBodyColor>()
                 .register_type::<CelestialBodyCrust>()
                 .register_type::<CelestialBodySubstanceProps>()
-                .register_type::<CelestialBodyAtmosphere>();
+                .register_type::<CelestialBodyAtmosphere>()
+                .register_type::<Escaping>();
 
             app.register_type::<SpectralType>()
                 .register_type::<StarClass>()
+                .register_type::<LuminosityClass>()
                 .register_type::<CelestialBodyEffectiveTemp>()
-                .register_type::<StarLuminosity>();
+                .register_type::<StarLuminosity>()
+                .register_type::<HabitableZone>()
+                .register_type::<Habitability>()
+                .register_type::<StellarPhase>()
+                .register_type::<StellarState>()
+                .register_type::<StellarEvolution>()
+                .register_type::<StellarHeating>()
+                .register_type::<CompactObject>()
+                .register_type::<AccretionDisk>();
+
+            app.register_type::<PlanetType>()
+                .register_type::<PlanetRing>()
+                .register_type::<Interstellar>();
 
-            app.register_type::<PlanetType>();
+            app.register_type::<BeltType>()
+                .register_type::<BeltBody>()
+                .register_type::<Comet>()
+                .register_type::<CometActivity>();
 
             app.register_type::<Galaxy>()
                 .register_type::<OrbitPredictor>()
                 .register_type::<CelestialBody>()
-                .register_type::<SimulationTimeScale>();
+                .register_type::<CelestialBodies>()
+                .register_type::<SimulationTimeScale>()
+                .register_type::<SimulationScheduler>()
+                .register_type::<StellarClock>()
+                .register_type::<ViewPlane>()
+                .register_type::<GravityModel>()
+                .register_type::<EjectionPolicy>();
         }
     }
 }
+
+/// Sent when a body becomes unbound from its parent, before the
+/// [`EjectionPolicy`](resources::EjectionPolicy) is applied.
+#[derive(Event, Clone, Copy)]
+pub struct CelestialBodyEjected {
+    pub id: CelestialBodyId,
+    pub escaping: Escaping,
+}
@@ -1,13 +1,21 @@
 use std::collections::VecDeque;
 
-use bevy::{ecs::system::Resource, math::DVec2, render::color::Color, utils::HashSet};
-use rayon::iter::{
-    IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+use bevy::{
+    ecs::system::Resource,
+    math::DVec2,
+    render::color::Color,
+    utils::{HashMap, HashSet},
 };
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
 
 use crate::consts;
 
-use super::components::CelestialBodyId;
+use super::{
+    components::{CelestialBodyId, Escaping},
+    storage::CelestialBodies,
+    SimVec,
+};
 
 #[cfg(feature = "debug")]
 use bevy::{ecs::reflect::ReflectResource, reflect::Reflect};
@@ -23,80 +31,264 @@ impl Default for SimulationTimeScale {
     }
 }
 
+/// Decouples the simulation rate from the frame rate.
+///
+/// Each frame accumulates `steps_per_second * time_scale` worth of steps, runs as many
+/// as the budget allows and keeps the fraction for interpolating the rendered transforms.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(Reflect))]
+#[cfg_attr(feature = "debug", reflect(Resource))]
+pub struct SimulationScheduler {
+    /// Target steps per second when the time scale is 1.
+    pub steps_per_second: f64,
+    /// Maximum number of steps to run in a single frame.
+    pub max_steps_per_frame: u32,
+    /// Maximum number of pending steps to catch up on in later frames.
+    ///
+    /// Steps beyond this are dropped, so a long hitch slows the simulation down
+    /// instead of freezing the following frames.
+    pub max_pending_steps: f64,
+    #[serde(skip)]
+    accumulator: f64,
+}
+
+impl SimulationScheduler {
+    /// Accumulates `delta` seconds of real time and returns the number of steps to run now.
+    pub fn advance(&mut self, delta: f64, time_scale: u32) -> u32 {
+        self.accumulator += delta * self.steps_per_second * time_scale as f64;
+        let steps = (self.accumulator.floor() as u32).min(self.max_steps_per_frame);
+        self.accumulator = (self.accumulator - steps as f64).min(self.max_pending_steps);
+        steps
+    }
+
+    /// Interpolation factor between the last two simulated states.
+    #[inline]
+    pub fn alpha(&self) -> f64 {
+        self.accumulator.clamp(0., 1.)
+    }
+
+    #[inline]
+    pub fn pending_steps(&self) -> f64 {
+        self.accumulator
+    }
+}
+
+/// Converts simulation steps into the years stars evolve over.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(Reflect))]
+#[cfg_attr(feature = "debug", reflect(Resource))]
+pub struct StellarClock {
+    pub years_per_step: f64,
+    /// Years passed since the stars last evolved.
+    #[serde(skip)]
+    pending_years: f64,
+}
+
+impl StellarClock {
+    #[inline]
+    pub fn advance(&mut self, steps: u32) {
+        self.pending_years += steps as f64 * self.years_per_step;
+    }
+
+    /// Returns the years passed since the last call.
+    #[inline]
+    pub fn take(&mut self) -> f64 {
+        std::mem::take(&mut self.pending_years)
+    }
+}
+
+/// What to do with a body once it escapes from its parent.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+#[cfg_attr(feature = "debug", reflect(Resource))]
+pub enum EjectionPolicy {
+    /// Keep simulating it as usual.
+    Keep,
+    /// Move it to the rogue list of `Galaxy`, where it only drifts along its velocity.
+    #[default]
+    Rogue,
+    /// Remove it from the galaxy and despawn it.
+    Despawn,
+}
+
+/// The plane that simulation space is projected onto for rendering.
+///
+/// Spanned by the orthonormal `right` and `up` axes.
+#[derive(Resource, Clone, Copy)]
+#[cfg_attr(feature = "debug", derive(Reflect))]
+#[cfg_attr(feature = "debug", reflect(Resource))]
+pub struct ViewPlane {
+    pub right: SimVec,
+    pub up: SimVec,
+}
+
+impl Default for ViewPlane {
+    fn default() -> Self {
+        Self::face_on()
+    }
+}
+
+impl ViewPlane {
+    /// Looks down onto the reference plane.
+    pub fn face_on() -> Self {
+        Self {
+            right: SimVec::X,
+            up: SimVec::Y,
+        }
+    }
+
+    /// Looks along the reference plane, with the reference plane's normal pointing up.
+    #[cfg(feature = "sim3d")]
+    pub fn edge_on() -> Self {
+        Self {
+            right: SimVec::X,
+            up: SimVec::Z,
+        }
+    }
+
+    #[inline]
+    pub fn project(&self, v: SimVec) -> DVec2 {
+        DVec2::new(v.dot(self.right), v.dot(self.up))
+    }
+}
+
 #[derive(Default, Clone, Copy)]
 #[cfg_attr(feature = "debug", derive(Debug, Reflect))]
 pub struct CelestialBody {
-    pos: DVec2,
+    pos: SimVec,
+    prev_pos: SimVec,
     radius: f64,
     mass: f64,
-    vel: DVec2,
-    acc: DVec2,
+    vel: SimVec,
+    acc: SimVec,
 }
 
 impl CelestialBody {
-    pub fn new(pos: DVec2, radius: f64, mass: f64, vel: DVec2) -> Self {
+    pub fn new(pos: SimVec, radius: f64, mass: f64, vel: SimVec) -> Self {
         CelestialBody {
             pos,
+            prev_pos: pos,
             radius,
             mass,
             vel,
-            acc: DVec2::ZERO,
+            acc: SimVec::ZERO,
         }
     }
 
     #[inline]
-    pub fn pos(&self) -> DVec2 {
+    pub(super) fn from_raw(
+        pos: SimVec,
+        prev_pos: SimVec,
+        radius: f64,
+        mass: f64,
+        vel: SimVec,
+        acc: SimVec,
+    ) -> Self {
+        CelestialBody {
+            pos,
+            prev_pos,
+            radius,
+            mass,
+            vel,
+            acc,
+        }
+    }
+
+    #[inline]
+    pub fn pos(&self) -> SimVec {
         self.pos
     }
 
+    /// Position before the last step.
+    #[inline]
+    pub fn prev_pos(&self) -> SimVec {
+        self.prev_pos
+    }
+
+    /// Position interpolated between the last two steps.
+    #[inline]
+    pub fn lerp_pos(&self, alpha: f64) -> SimVec {
+        self.prev_pos.lerp(self.pos, alpha)
+    }
+
     #[inline]
     pub fn mass(&self) -> f64 {
         self.mass
     }
 
     #[inline]
-    pub fn vel(&self) -> DVec2 {
+    pub fn vel(&self) -> SimVec {
         self.vel
     }
 
+    #[inline]
+    pub fn acc(&self) -> SimVec {
+        self.acc
+    }
+
     #[inline]
     pub fn radius(&self) -> f64 {
         self.radius
     }
 }
 
+/// How the gravitational acceleration between bodies is evaluated.
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "debug", derive(Debug, Reflect))]
+pub enum GravityModel {
+    #[default]
+    Newtonian,
+    /// Newtonian gravity with the first order post-Newtonian (Schwarzschild) correction,
+    /// which makes tight orbits around massive bodies precess.
+    ///
+    /// Every body is treated as a test particle in the field of every other body.
+    PostNewtonian {
+        /// Speed of light in simulation units.
+        speed_of_light: f64,
+    },
+}
+
+impl GravityModel {
+    /// Post-Newtonian gravity with the physical speed of light.
+    pub fn post_newtonian() -> Self {
+        Self::PostNewtonian {
+            speed_of_light: consts::SPEED_OF_LIGHT,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Orbit {
-    verts: VecDeque<DVec2>,
+    verts: VecDeque<SimVec>,
     pub color: Color,
 }
 
 impl Orbit {
     pub fn new(iterations: usize, color: Color) -> Self {
         Orbit {
-            verts: vec![DVec2::ZERO; iterations].into(),
+            verts: vec![SimVec::ZERO; iterations].into(),
             color,
         }
     }
 
     #[inline]
-    pub fn push(&mut self, pos: DVec2) {
+    pub fn push(&mut self, pos: SimVec) {
         self.verts.push_back(pos);
     }
 
     #[inline]
-    pub fn pop(&mut self) -> DVec2 {
+    pub fn pop(&mut self) -> SimVec {
         self.verts.pop_front().unwrap()
     }
 
     #[inline]
-    pub fn update(&mut self, pos: DVec2) -> DVec2 {
+    pub fn update(&mut self, pos: SimVec) -> SimVec {
         self.verts.push_back(pos);
         self.verts.pop_front().unwrap()
     }
 
     #[inline]
-    pub fn vertices(&self) -> &VecDeque<DVec2> {
+    pub fn vertices(&self) -> &VecDeque<SimVec> {
         &self.verts
     }
 }
@@ -106,69 +298,154 @@ impl Orbit {
 #[cfg_attr(feature = "debug", reflect(Resource))]
 pub struct Galaxy {
     time_step: f64,
-    bodies: Vec<CelestialBody>,
+    gravity: GravityModel,
+    bodies: CelestialBodies,
     body_colors: Vec<Color>,
     body_id_to_index: Vec<Option<usize>>,
     body_index_to_id: Vec<Option<CelestialBodyId>>,
+    /// Bodies that escaped and no longer take part in the N-body simulation.
+    #[cfg_attr(feature = "debug", reflect(ignore))]
+    rogues: HashMap<CelestialBodyId, CelestialBody>,
+    /// Steps simulated so far.
+    steps: u64,
 }
 
 impl Default for Galaxy {
     fn default() -> Self {
         Self {
             time_step: consts::CELESTIAL_SIM_STEP,
+            gravity: Default::default(),
             bodies: Default::default(),
             body_colors: Default::default(),
             body_id_to_index: Default::default(),
             body_index_to_id: Default::default(),
+            rogues: Default::default(),
+            steps: 0,
         }
     }
 }
 
-impl<'a> IntoParallelRefIterator<'a> for Galaxy {
-    type Item = &'a CelestialBody;
-    type Iter = rayon::slice::Iter<'a, CelestialBody>;
-
-    fn par_iter(&'a self) -> Self::Iter {
-        self.bodies.par_iter()
-    }
-}
-
 impl Galaxy {
     #[inline]
     pub fn num_bodies(&self) -> usize {
         self.bodies.len()
     }
 
+    #[inline]
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    #[inline]
+    pub fn time_step(&self) -> f64 {
+        self.time_step
+    }
+
+    #[inline]
+    pub fn gravity(&self) -> GravityModel {
+        self.gravity
+    }
+
+    #[inline]
+    pub fn set_gravity(&mut self, gravity: GravityModel) {
+        self.gravity = gravity;
+    }
+
+    /// Number of massless bodies, which are simulated as test particles.
+    #[inline]
+    pub fn num_test_particles(&self) -> usize {
+        self.bodies.len() - self.bodies.num_massive()
+    }
+
+    /// Adds a body to the simulation.
+    ///
+    /// Bodies with zero mass are test particles: they follow the field of the
+    /// massive bodies without contributing to it.
     pub fn add_body(&mut self, body: CelestialBody) -> CelestialBodyId {
-        let id = CelestialBodyId(self.num_bodies());
-        self.body_id_to_index.push(Some(self.bodies.len()));
-        self.body_index_to_id.push(Some(id));
-        self.bodies.push(body);
+        let id = CelestialBodyId(self.body_id_to_index.len());
+        let index = self.bodies.push(body);
+        self.body_id_to_index
+            .iter_mut()
+            .flatten()
+            .filter(|idx| **idx >= index)
+            .for_each(|idx| *idx += 1);
+        self.body_id_to_index.push(Some(index));
+        self.body_index_to_id.insert(index, Some(id));
         id
     }
 
     pub fn remove_body(&mut self, id: CelestialBodyId) {
         if let Some(index) = self.body_id_to_index[id.0] {
-            for i in index + 1..self.body_id_to_index.len() {
-                if let Some(idx) = &mut self.body_id_to_index[i] {
-                    *idx -= 1;
-                }
-            }
+            self.body_id_to_index
+                .iter_mut()
+                .flatten()
+                .filter(|idx| **idx > index)
+                .for_each(|idx| *idx -= 1);
 
-            for i in index..self.body_index_to_id.len() - 1 {
-                if let Some(id) = &mut self.body_index_to_id[i] {
-                    *id = CelestialBodyId(id.0 + 1);
-                }
-            }
-            self.body_index_to_id.pop();
+            self.body_index_to_id.remove(index);
             self.body_id_to_index[id.0] = None;
             self.bodies.remove(index);
         }
+        self.rogues.remove(&id);
+    }
+
+    /// Takes a body out of the N-body simulation and lets it drift along its velocity.
+    ///
+    /// Returns `false` if the body isn't simulated.
+    pub fn make_rogue(&mut self, id: CelestialBodyId) -> bool {
+        if self.is_rogue(id) {
+            return false;
+        }
+        let Some(body) = self.get_body(id) else {
+            return false;
+        };
+        self.remove_body(id);
+        self.rogues.insert(id, body);
+        true
     }
 
     #[inline]
-    pub fn bodies(&self) -> &[CelestialBody] {
-        &self.bodies
+    pub fn is_rogue(&self, id: CelestialBodyId) -> bool {
+        self.rogues.contains_key(&id)
+    }
+
+    #[inline]
+    pub fn num_rogues(&self) -> usize {
+        self.rogues.len()
+    }
+
+    /// Finds the bodies that are unbound from their parent, the body that pulls
+    /// hardest on them, and are moving away from it.
+    ///
+    /// Bodies pulled hardest by a lighter one, like the primary of a binary,
+    /// are the top of their hierarchy and never escape.
+    pub fn escaping_bodies(&self) -> Vec<(CelestialBodyId, Escaping)> {
+        let bodies = &self.bodies;
+        (0..bodies.len())
+            .into_par_iter()
+            .filter_map(|index| {
+                let parent = bodies.dominant_attractor(index)?;
+                if bodies.mass(parent) < bodies.mass(index) {
+                    return None;
+                }
+                let specific_energy = bodies.specific_orbital_energy(index, parent);
+                if specific_energy <= 0. || bodies.radial_spd(index, parent) <= 0. {
+                    return None;
+                }
+                Some((
+                    self.body_index_to_id[index]?,
+                    Escaping {
+                        parent: self.body_index_to_id[parent]?,
+                        specific_energy,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    #[inline]
+    pub fn bodies(&self) -> impl Iterator<Item = CelestialBody> + '_ {
+        self.bodies.iter()
     }
 
     #[inline]
@@ -179,52 +456,122 @@ impl Galaxy {
         self.body_colors[id.0] = color;
     }
 
+    /// Changes the mass and radius of a simulated or rogue body, keeping its motion.
+    ///
+    /// Massive bodies have to stay massive.
+    pub fn set_mass_radius(&mut self, id: CelestialBodyId, mass: f64, radius: f64) {
+        if let Some(index) = self.body_id_to_index.get(id.0).copied().flatten() {
+            let body = self.bodies.get(index);
+            self.bodies.set(
+                index,
+                CelestialBody {
+                    mass,
+                    radius,
+                    ..body
+                },
+            );
+        } else if let Some(body) = self.rogues.get_mut(&id) {
+            body.mass = mass;
+            body.radius = radius;
+        }
+    }
+
+    /// Looks up a simulated or rogue body.
     #[inline]
-    pub fn get_body(&self, id: CelestialBodyId) -> Option<&CelestialBody> {
+    pub fn get_body(&self, id: CelestialBodyId) -> Option<CelestialBody> {
         self.body_id_to_index
             .get(id.0)
-            .map(|&i| i.map(|i| &self.bodies[i]))
+            .map(|&i| i.map(|i| self.bodies.get(i)))
             .flatten()
+            .or_else(|| self.rogues.get(&id).copied())
+    }
+
+    /// Merges `from` into `into`, conserving mass, momentum and volume.
+    ///
+    /// Two test particles have no mass to weight their motion with, so `into` keeps
+    /// its own.
+    ///
+    /// Returns the merged body, or `None` if either body doesn't exist.
+    pub fn merge_bodies(
+        &mut self,
+        into: CelestialBodyId,
+        from: CelestialBodyId,
+    ) -> Option<CelestialBody> {
+        let index = self.body_id_to_index.get(into.0).copied().flatten()?;
+        let lhs = self.get_body(into)?;
+        let rhs = self.get_body(from)?;
+
+        let mass = lhs.mass + rhs.mass;
+        let radius = (lhs.radius.powi(3) + rhs.radius.powi(3)).cbrt();
+        let merged = if mass > 0. {
+            CelestialBody {
+                pos: (lhs.pos * lhs.mass + rhs.pos * rhs.mass) / mass,
+                prev_pos: (lhs.prev_pos * lhs.mass + rhs.prev_pos * rhs.mass) / mass,
+                radius,
+                mass,
+                vel: (lhs.vel * lhs.mass + rhs.vel * rhs.mass) / mass,
+                acc: SimVec::ZERO,
+            }
+        } else {
+            CelestialBody {
+                radius,
+                acc: SimVec::ZERO,
+                ..lhs
+            }
+        };
+        self.bodies.set(index, merged);
+        self.remove_body(from);
+        Some(merged)
+    }
+
+    /// Gravitational acceleration of the whole galaxy at `pos`.
+    #[inline]
+    pub fn acc_at(&self, pos: SimVec) -> SimVec {
+        self.bodies.acc_at(pos)
     }
 
     #[inline]
     pub fn step(&mut self) {
-        calc_acc(&mut self.bodies);
-        update_pos(&mut self.bodies, self.time_step);
+        self.bodies.calc_acc(self.gravity);
+        self.bodies.update_pos(self.time_step);
+        self.rogues.values_mut().for_each(|body| {
+            body.prev_pos = body.pos;
+            body.pos += body.vel * self.time_step;
+        });
+        self.steps += 1;
     }
 
+    /// Finds the lighter body of every overlapping pair.
+    ///
+    /// Test particles only collide with massive bodies.
     #[inline]
     pub fn test_overlapping(&self) -> HashSet<CelestialBodyId> {
-        let mut overlapping = HashSet::default();
-        self.bodies
-            .iter()
-            .enumerate()
-            .for_each(|(i_lhs, body_lhs)| {
-                self.bodies
-                    .iter()
-                    .enumerate()
-                    .for_each(|(i_rhs, body_rhs)| {
-                        if i_lhs == i_rhs {
-                            return;
-                        }
-
-                        if (body_lhs.pos - body_rhs.pos).length_squared()
-                            > (body_lhs.radius + body_rhs.radius).powi(2)
-                        {
-                            return;
-                        }
-
-                        let remove = {
-                            if body_lhs.mass > body_rhs.mass {
-                                i_rhs
-                            } else {
-                                i_lhs
-                            }
-                        };
-                        overlapping.insert(self.body_index_to_id[remove].unwrap());
-                    });
-            });
-        overlapping
+        let bodies = &self.bodies;
+        (0..bodies.len())
+            .into_par_iter()
+            .flat_map_iter(|i_lhs| {
+                (0..bodies.num_massive()).filter_map(move |i_rhs| {
+                    if i_lhs == i_rhs {
+                        return None;
+                    }
+
+                    if (bodies.pos(i_lhs) - bodies.pos(i_rhs)).length_squared()
+                        > (bodies.radius(i_lhs) + bodies.radius(i_rhs)).powi(2)
+                    {
+                        return None;
+                    }
+
+                    if bodies.mass(i_lhs) > bodies.mass(i_rhs) {
+                        Some(i_rhs)
+                    } else {
+                        Some(i_lhs)
+                    }
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|remove| self.body_index_to_id[remove].unwrap())
+            .collect()
     }
 }
 
@@ -234,8 +581,11 @@ impl Galaxy {
 pub struct OrbitPredictor {
     iterations: usize,
     time_step: f64,
+    gravity: GravityModel,
     #[cfg_attr(feature = "debug", reflect(ignore))]
-    parallel_universe: Vec<CelestialBody>,
+    parallel_universe: CelestialBodies,
+    #[cfg_attr(feature = "debug", reflect(ignore))]
+    body_index_to_id: Vec<Option<CelestialBodyId>>,
     #[cfg_attr(feature = "debug", reflect(ignore))]
     orbits: Vec<Orbit>,
 }
@@ -259,7 +609,9 @@ impl OrbitPredictor {
     pub fn update_state(&mut self, iterations: usize, galaxy: &Galaxy) {
         self.iterations = iterations;
         self.parallel_universe = galaxy.bodies.clone();
+        self.body_index_to_id = galaxy.body_index_to_id.clone();
         self.time_step = consts::CELESTIAL_SIM_STEP;
+        self.gravity = galaxy.gravity;
         self.orbits = galaxy
             .body_colors
             .iter()
@@ -272,38 +624,212 @@ impl OrbitPredictor {
 
     pub fn step(&mut self) {
         if self.iterations != 0 {
-            calc_acc(&mut self.parallel_universe);
-            update_pos(&mut self.parallel_universe, self.time_step);
-            self.parallel_universe
-                .par_iter()
-                .zip(self.orbits.par_iter_mut())
-                .for_each(|(body, orbit)| {
-                    orbit.update(body.pos);
-                });
+            self.parallel_universe.calc_acc(self.gravity);
+            self.parallel_universe.update_pos(self.time_step);
+            let bodies = &self.parallel_universe;
+            for (index, id) in self.body_index_to_id.iter().enumerate() {
+                if let Some(orbit) = id.and_then(|id| self.orbits.get_mut(id.0)) {
+                    orbit.update(bodies.pos(index));
+                }
+            }
         }
     }
 }
 
-fn calc_acc(bodies: &mut [CelestialBody]) {
-    bodies.par_iter_mut().for_each(|body| {
-        body.acc = DVec2::ZERO;
-    });
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    for i_lhs in 0..bodies.len() {
-        let lhs = bodies[i_lhs];
-        bodies.par_iter_mut().enumerate().for_each(|(i_rhs, rhs)| {
-            if i_lhs == i_rhs {
-                return;
-            }
-            let dist = (rhs.pos - lhs.pos).length_squared();
-            rhs.acc += consts::G * lhs.mass / dist * (lhs.pos - rhs.pos).normalize();
-        });
+    fn scheduler() -> SimulationScheduler {
+        SimulationScheduler {
+            steps_per_second: 64.,
+            max_steps_per_frame: 4,
+            max_pending_steps: 8.,
+            accumulator: 0.,
+        }
     }
-}
 
-fn update_pos(bodies: &mut [CelestialBody], dt: f64) {
-    bodies.par_iter_mut().for_each(|body| {
-        body.vel += body.acc * dt;
-        body.pos += body.vel * dt;
-    });
+    #[test]
+    fn test_scheduler_rate() {
+        let mut scheduler = scheduler();
+        let steps = (0..128)
+            .map(|_| scheduler.advance(1. / 128., 1))
+            .sum::<u32>();
+        assert_eq!(steps, 64);
+        assert_eq!(scheduler.alpha(), 0.);
+
+        assert_eq!(scheduler.advance(1. / 128., 0), 0);
+        assert_eq!(scheduler.advance(1. / 256., 1), 0);
+        assert_eq!(scheduler.alpha(), 0.25);
+    }
+
+    #[test]
+    fn test_scheduler_budget() {
+        let mut scheduler = scheduler();
+        assert_eq!(scheduler.advance(6. / 64., 1), 4);
+        assert_eq!(scheduler.pending_steps(), 2.);
+        assert_eq!(scheduler.advance(0., 1), 2);
+
+        assert_eq!(scheduler.advance(1., 1), 4);
+        assert_eq!(scheduler.pending_steps(), 8.);
+        assert_eq!(scheduler.alpha(), 1.);
+    }
+
+    #[test]
+    fn test_merge_bodies() {
+        let mut galaxy = Galaxy::default();
+        let lhs = galaxy.add_body(CelestialBody::new(SimVec::ZERO, 3., 3., SimVec::X));
+        let rhs = galaxy.add_body(CelestialBody::new(SimVec::X * 4., 4., 1., SimVec::Y * 3.));
+
+        let merged = galaxy.merge_bodies(lhs, rhs).unwrap();
+        assert_eq!(galaxy.num_bodies(), 1);
+        assert!(galaxy.get_body(rhs).is_none());
+        assert_eq!(merged.mass(), 4.);
+        assert_eq!(merged.pos(), SimVec::X);
+        assert_eq!(merged.vel(), (SimVec::X + SimVec::Y) * 0.75);
+        assert!((merged.radius() - 91f64.cbrt()).abs() < 1e-12);
+        assert_eq!(galaxy.get_body(lhs).unwrap().mass(), 4.);
+
+        let lhs = galaxy.add_body(CelestialBody::new(SimVec::Y, 3., 0., SimVec::X));
+        let rhs = galaxy.add_body(CelestialBody::new(SimVec::NEG_Y, 4., 0., SimVec::Y));
+        let merged = galaxy.merge_bodies(lhs, rhs).unwrap();
+        assert_eq!(galaxy.num_bodies(), 2);
+        assert_eq!(merged.mass(), 0.);
+        assert_eq!(merged.pos(), SimVec::Y);
+        assert_eq!(merged.vel(), SimVec::X);
+    }
+
+    #[test]
+    fn test_test_particles() {
+        let mut galaxy = Galaxy::default();
+        let particle = galaxy.add_body(CelestialBody::new(SimVec::X * 1e3, 1., 0., SimVec::ZERO));
+        let star = galaxy.add_body(CelestialBody::new(SimVec::ZERO, 1., 1e20, SimVec::ZERO));
+        let planet = galaxy.add_body(CelestialBody::new(SimVec::Y * 1e3, 1., 1e10, SimVec::ZERO));
+        assert_eq!((galaxy.num_bodies(), galaxy.num_test_particles()), (3, 1));
+        assert_eq!(galaxy.get_body(particle).unwrap().pos(), SimVec::X * 1e3);
+        assert_eq!(galaxy.get_body(planet).unwrap().pos(), SimVec::Y * 1e3);
+
+        galaxy.remove_body(star);
+        assert_eq!(galaxy.get_body(particle).unwrap().pos(), SimVec::X * 1e3);
+        assert_eq!(galaxy.get_body(planet).unwrap().pos(), SimVec::Y * 1e3);
+
+        galaxy.step();
+        assert_eq!(galaxy.get_body(planet).unwrap().vel(), SimVec::ZERO);
+        assert!(galaxy.get_body(particle).unwrap().vel().length() > 0.);
+    }
+
+    #[test]
+    fn test_escaping_bodies() {
+        let mut galaxy = Galaxy::default();
+        let star = galaxy.add_body(CelestialBody::new(SimVec::ZERO, 1., 1e20, SimVec::ZERO));
+        let escape_spd = (2. * consts::G * 1e20 / 1e3).sqrt();
+        let bound = galaxy.add_body(CelestialBody::new(
+            SimVec::X * 1e3,
+            1.,
+            1.,
+            SimVec::Y * escape_spd * 0.9,
+        ));
+        let unbound = galaxy.add_body(CelestialBody::new(
+            SimVec::Y * -1e3,
+            1.,
+            1.,
+            SimVec::NEG_Y * escape_spd * 1.1,
+        ));
+        let infalling = galaxy.add_body(CelestialBody::new(
+            SimVec::X * -1e3,
+            1.,
+            1.,
+            SimVec::X * escape_spd * 1.1,
+        ));
+
+        let escaping = galaxy.escaping_bodies();
+        assert_eq!(escaping.len(), 1);
+        let (id, escaping) = escaping[0];
+        assert!(id == unbound && escaping.parent == star);
+        assert!(escaping.specific_energy > 0.);
+        assert!(![bound, infalling].contains(&id));
+    }
+
+    #[test]
+    fn test_make_rogue() {
+        let mut galaxy = Galaxy::default();
+        galaxy.add_body(CelestialBody::new(SimVec::ZERO, 1., 1e20, SimVec::ZERO));
+        let rogue = galaxy.add_body(CelestialBody::new(SimVec::X, 1., 1., SimVec::X * 100.));
+
+        assert!(galaxy.make_rogue(rogue));
+        assert!(!galaxy.make_rogue(rogue));
+        assert_eq!((galaxy.num_bodies(), galaxy.num_rogues()), (1, 1));
+
+        galaxy.step();
+        let body = galaxy.get_body(rogue).unwrap();
+        assert_eq!(body.prev_pos(), SimVec::X);
+        assert_eq!(body.pos(), SimVec::X * (1. + 100. * galaxy.time_step()));
+
+        let id = galaxy.add_body(CelestialBody::new(SimVec::Y, 1., 1., SimVec::ZERO));
+        assert!(id != rogue);
+        assert_eq!(galaxy.get_body(id).unwrap().pos(), SimVec::Y);
+
+        galaxy.remove_body(rogue);
+        assert!(galaxy.get_body(rogue).is_none());
+    }
+
+    /// Advances a Mercury-like orbit around the sun and returns the angle of its
+    /// perihelion, from the Laplace-Runge-Lenz vector.
+    fn mercury_perihelion(gravity: GravityModel, orbits: usize, steps_per_orbit: usize) -> f64 {
+        const SUN_MASS: f64 = 1.989e30;
+        const MERCURY_MASS: f64 = 3.3011e23;
+        const SMA: f64 = 5.7909e10;
+        const ECC: f64 = 0.2056;
+
+        let gm = consts::G * (SUN_MASS + MERCURY_MASS);
+        let period = std::f64::consts::TAU * (SMA.powi(3) / gm).sqrt();
+        let peri_dist = SMA * (1. - ECC);
+        let peri_spd = (gm * (1. + ECC) / peri_dist).sqrt();
+
+        let mut galaxy = Galaxy {
+            time_step: period / steps_per_orbit as f64,
+            gravity,
+            ..Default::default()
+        };
+        let sun = galaxy.add_body(CelestialBody::new(SimVec::ZERO, 0., SUN_MASS, SimVec::ZERO));
+        let mercury = galaxy.add_body(CelestialBody::new(
+            SimVec::X * peri_dist,
+            0.,
+            MERCURY_MASS,
+            SimVec::Y * peri_spd,
+        ));
+        for _ in 0..orbits * steps_per_orbit {
+            galaxy.step();
+        }
+
+        let (sun, mercury) = (
+            galaxy.get_body(sun).unwrap(),
+            galaxy.get_body(mercury).unwrap(),
+        );
+        let (r, v) = (mercury.pos() - sun.pos(), mercury.vel() - sun.vel());
+        let ecc = (v.length_squared() - gm / r.length()) * r - r.dot(v) * v;
+        ecc.y.atan2(ecc.x)
+    }
+
+    #[test]
+    fn test_perihelion_precession() {
+        const ORBITS: usize = 4;
+        const STEPS_PER_ORBIT: usize = 20000;
+
+        let newtonian = mercury_perihelion(GravityModel::Newtonian, ORBITS, STEPS_PER_ORBIT);
+        let relativistic =
+            mercury_perihelion(GravityModel::post_newtonian(), ORBITS, STEPS_PER_ORBIT);
+
+        // 6 pi GM / (c^2 a (1 - e^2)), about 43 arcseconds per century.
+        let expected = 6. * std::f64::consts::PI * consts::G * 1.989e30
+            / (consts::SPEED_OF_LIGHT.powi(2) * 5.7909e10 * (1. - 0.2056f64.powi(2)))
+            * ORBITS as f64;
+        let precession = relativistic - newtonian;
+        assert!(
+            (precession - expected).abs() < expected * 0.02,
+            "precession {} rad, expected {} rad",
+            precession,
+            expected
+        );
+    }
 }
@@ -0,0 +1,405 @@
+use rayon::{
+    iter::{
+        IndexedParallelIterator, IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator,
+    },
+    slice::ParallelSliceMut,
+};
+
+use crate::consts;
+
+use super::{
+    resources::{CelestialBody, GravityModel},
+    SimVec, SIM_DIM,
+};
+
+#[cfg(feature = "debug")]
+use bevy::reflect::Reflect;
+
+/// Number of bodies whose accelerations are accumulated together.
+///
+/// Every source body is applied to a whole tile at once, so the inner loop is a
+/// fixed-width loop over plain arrays which the compiler can vectorize.
+pub const FORCE_TILE_SIZE: usize = 8;
+
+/// Structure-of-arrays storage of celestial bodies.
+///
+/// Vector quantities are stored as one array per axis.
+///
+/// Massless bodies are test particles: they are attracted by the massive bodies
+/// but never act as a source themselves. They are kept after all massive bodies,
+/// so the force kernels only have to walk the first [`Self::num_massive`] sources.
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "debug", derive(Reflect, Debug))]
+pub struct CelestialBodies {
+    pos: [Vec<f64>; SIM_DIM],
+    prev_pos: [Vec<f64>; SIM_DIM],
+    vel: [Vec<f64>; SIM_DIM],
+    acc: [Vec<f64>; SIM_DIM],
+    mass: Vec<f64>,
+    radius: Vec<f64>,
+    num_massive: usize,
+    /// Accelerations accumulated per tile, kept between steps to reuse the allocation.
+    #[cfg_attr(feature = "debug", reflect(ignore))]
+    tiles: Vec<[[f64; FORCE_TILE_SIZE]; SIM_DIM]>,
+}
+
+impl CelestialBodies {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.mass.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.mass.is_empty()
+    }
+
+    /// Number of bodies with mass, which occupy the indices `0..num_massive`.
+    #[inline]
+    pub fn num_massive(&self) -> usize {
+        self.num_massive
+    }
+
+    /// Adds a body and returns its index.
+    ///
+    /// Massive bodies are inserted before the test particles, which shifts the
+    /// index of every test particle by one.
+    pub fn push(&mut self, body: CelestialBody) -> usize {
+        let index = if body.mass() > 0. {
+            self.num_massive += 1;
+            self.num_massive - 1
+        } else {
+            self.len()
+        };
+        insert_vec(&mut self.pos, index, body.pos());
+        insert_vec(&mut self.prev_pos, index, body.prev_pos());
+        insert_vec(&mut self.vel, index, body.vel());
+        insert_vec(&mut self.acc, index, body.acc());
+        self.mass.insert(index, body.mass());
+        self.radius.insert(index, body.radius());
+        index
+    }
+
+    pub fn remove(&mut self, index: usize) -> CelestialBody {
+        if index < self.num_massive {
+            self.num_massive -= 1;
+        }
+        let body = self.get(index);
+        for axis in 0..SIM_DIM {
+            self.pos[axis].remove(index);
+            self.prev_pos[axis].remove(index);
+            self.vel[axis].remove(index);
+            self.acc[axis].remove(index);
+        }
+        self.mass.remove(index);
+        self.radius.remove(index);
+        body
+    }
+
+    /// Replaces the body at `index`, which must stay massive or massless.
+    pub fn set(&mut self, index: usize, body: CelestialBody) {
+        debug_assert_eq!(index < self.num_massive, body.mass() > 0.);
+        set_vec(&mut self.pos, index, body.pos());
+        set_vec(&mut self.prev_pos, index, body.prev_pos());
+        set_vec(&mut self.vel, index, body.vel());
+        set_vec(&mut self.acc, index, body.acc());
+        self.mass[index] = body.mass();
+        self.radius[index] = body.radius();
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> CelestialBody {
+        CelestialBody::from_raw(
+            self.pos(index),
+            self.prev_pos(index),
+            self.radius[index],
+            self.mass[index],
+            get_vec(&self.vel, index),
+            get_vec(&self.acc, index),
+        )
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = CelestialBody> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    #[inline]
+    pub fn pos(&self, index: usize) -> SimVec {
+        get_vec(&self.pos, index)
+    }
+
+    #[inline]
+    pub fn prev_pos(&self, index: usize) -> SimVec {
+        get_vec(&self.prev_pos, index)
+    }
+
+    #[inline]
+    pub fn mass(&self, index: usize) -> f64 {
+        self.mass[index]
+    }
+
+    #[inline]
+    pub fn radius(&self, index: usize) -> f64 {
+        self.radius[index]
+    }
+
+    #[inline]
+    pub fn masses(&self) -> &[f64] {
+        &self.mass
+    }
+
+    #[inline]
+    pub fn radii(&self) -> &[f64] {
+        &self.radius
+    }
+
+    /// Evaluates the gravitational acceleration of every body.
+    pub fn calc_acc(&mut self, gravity: GravityModel) {
+        self.calc_newtonian_acc();
+        if let GravityModel::PostNewtonian { speed_of_light } = gravity {
+            self.add_post_newtonian_acc(speed_of_light);
+        }
+    }
+
+    fn calc_newtonian_acc(&mut self) {
+        let (pos, mass) = (&self.pos, &self.mass);
+        let sources = &mass[..self.num_massive];
+        self.tiles.resize(
+            self.len().div_ceil(FORCE_TILE_SIZE),
+            [[0.; FORCE_TILE_SIZE]; SIM_DIM],
+        );
+        self.tiles
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(tile, sum)| {
+                let start = tile * FORCE_TILE_SIZE;
+                let len = FORCE_TILE_SIZE.min(mass.len() - start);
+
+                let mut tile_pos = [[0.; FORCE_TILE_SIZE]; SIM_DIM];
+                for axis in 0..SIM_DIM {
+                    tile_pos[axis][..len].copy_from_slice(&pos[axis][start..start + len]);
+                }
+
+                *sum = [[0.; FORCE_TILE_SIZE]; SIM_DIM];
+                for (src, &src_mass) in sources.iter().enumerate() {
+                    let src_pos: [f64; SIM_DIM] = std::array::from_fn(|axis| pos[axis][src]);
+                    for lane in 0..FORCE_TILE_SIZE {
+                        let delta: [f64; SIM_DIM] =
+                            std::array::from_fn(|axis| src_pos[axis] - tile_pos[axis][lane]);
+                        let dist_sq = delta.iter().map(|d| d * d).sum::<f64>();
+                        // Also skips the body itself, which is the only source at zero distance.
+                        let coeff = if dist_sq > 0. {
+                            src_mass / (dist_sq * dist_sq.sqrt())
+                        } else {
+                            0.
+                        };
+                        for axis in 0..SIM_DIM {
+                            sum[axis][lane] += delta[axis] * coeff;
+                        }
+                    }
+                }
+            });
+
+        for (axis, acc) in self.acc.iter_mut().enumerate() {
+            acc.par_chunks_mut(FORCE_TILE_SIZE)
+                .zip(&self.tiles)
+                .for_each(|(acc, sum)| {
+                    acc.iter_mut()
+                        .zip(sum[axis])
+                        .for_each(|(acc, sum)| *acc = consts::G * sum);
+                });
+        }
+    }
+
+    /// Adds the 1PN correction of a test particle in the Schwarzschild field of every
+    /// other massive body:
+    ///
+    /// `a = GM / (c^2 r^3) * ((4GM / r - v^2) r + 4 (r . v) v)`
+    ///
+    /// where `r` and `v` are relative to the source body.
+    fn add_post_newtonian_acc(&mut self, speed_of_light: f64) {
+        let c_sq = speed_of_light * speed_of_light;
+        let corrections = (0..self.len())
+            .into_par_iter()
+            .map(|dst| {
+                let (pos, vel) = (self.pos(dst), get_vec(&self.vel, dst));
+                (0..self.num_massive).fold(SimVec::ZERO, |sum, src| {
+                    let r = pos - self.pos(src);
+                    let dist = r.length();
+                    // Also skips the body itself, which is the only source at zero distance.
+                    if dist == 0. {
+                        return sum;
+                    }
+                    let v = vel - get_vec(&self.vel, src);
+                    let gm = consts::G * self.mass[src];
+                    sum + gm / (c_sq * dist.powi(3))
+                        * ((4. * gm / dist - v.length_squared()) * r + 4. * r.dot(v) * v)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (index, correction) in corrections.into_iter().enumerate() {
+            let acc = get_vec(&self.acc, index) + correction;
+            set_vec(&mut self.acc, index, acc);
+        }
+    }
+
+    /// Gravitational acceleration that all massive bodies exert at `pos`.
+    ///
+    /// Always Newtonian, as there is no velocity to evaluate the correction with.
+    pub fn acc_at(&self, pos: SimVec) -> SimVec {
+        let pos = pos.to_array();
+        let mut sum = [0.; SIM_DIM];
+        for (src, &src_mass) in self.mass[..self.num_massive].iter().enumerate() {
+            let delta: [f64; SIM_DIM] = std::array::from_fn(|axis| self.pos[axis][src] - pos[axis]);
+            let dist_sq = delta.iter().map(|d| d * d).sum::<f64>();
+            let coeff = if dist_sq > 0. {
+                src_mass / (dist_sq * dist_sq.sqrt())
+            } else {
+                0.
+            };
+            for axis in 0..SIM_DIM {
+                sum[axis] += delta[axis] * coeff;
+            }
+        }
+        SimVec::from_array(sum) * consts::G
+    }
+
+    /// The body that pulls hardest on the body at `index`.
+    pub fn dominant_attractor(&self, index: usize) -> Option<usize> {
+        let pos = self.pos(index);
+        (0..self.num_massive)
+            .filter(|&src| src != index)
+            .map(|src| (src, self.mass[src] / self.pos(src).distance_squared(pos)))
+            .max_by(|(_, lhs), (_, rhs)| lhs.total_cmp(rhs))
+            .map(|(src, _)| src)
+    }
+
+    /// Specific orbital energy of the body at `index` relative to the body at `parent`.
+    ///
+    /// Positive if the body is not bound to the parent.
+    pub fn specific_orbital_energy(&self, index: usize, parent: usize) -> f64 {
+        let dist = self.pos(index).distance(self.pos(parent));
+        let vel = get_vec(&self.vel, index) - get_vec(&self.vel, parent);
+        vel.length_squared() / 2. - consts::G * (self.mass[index] + self.mass[parent]) / dist
+    }
+
+    /// Speed at which the body at `index` moves away from the body at `parent`.
+    pub fn radial_spd(&self, index: usize, parent: usize) -> f64 {
+        let dir = (self.pos(index) - self.pos(parent)).normalize_or_zero();
+        (get_vec(&self.vel, index) - get_vec(&self.vel, parent)).dot(dir)
+    }
+
+    /// Integrates velocities and positions with the current accelerations.
+    pub fn update_pos(&mut self, dt: f64) {
+        for axis in 0..SIM_DIM {
+            integrate(
+                &mut self.pos[axis],
+                &mut self.prev_pos[axis],
+                &mut self.vel[axis],
+                &self.acc[axis],
+                dt,
+            );
+        }
+    }
+}
+
+#[inline]
+fn insert_vec(arrays: &mut [Vec<f64>; SIM_DIM], index: usize, value: SimVec) {
+    arrays
+        .iter_mut()
+        .zip(value.to_array())
+        .for_each(|(array, value)| array.insert(index, value));
+}
+
+#[inline]
+fn set_vec(arrays: &mut [Vec<f64>; SIM_DIM], index: usize, value: SimVec) {
+    arrays
+        .iter_mut()
+        .zip(value.to_array())
+        .for_each(|(array, value)| array[index] = value);
+}
+
+#[inline]
+fn get_vec(arrays: &[Vec<f64>; SIM_DIM], index: usize) -> SimVec {
+    SimVec::from_array(std::array::from_fn(|axis| arrays[axis][index]))
+}
+
+#[inline]
+fn integrate(pos: &mut [f64], prev_pos: &mut [f64], vel: &mut [f64], acc: &[f64], dt: f64) {
+    prev_pos.copy_from_slice(pos);
+    vel.iter_mut()
+        .zip(acc)
+        .for_each(|(vel, acc)| *vel += acc * dt);
+    pos.iter_mut()
+        .zip(vel.iter())
+        .for_each(|(pos, vel)| *pos += vel * dt);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_calc_acc() {
+        let mut bodies = CelestialBodies::default();
+        let mut reference = Vec::new();
+        for i in 0..FORCE_TILE_SIZE * 2 + 3 {
+            let t = i as f64;
+            let body = CelestialBody::new(
+                SimVec::from_array(std::array::from_fn(|axis| {
+                    (t + axis as f64).sin() * 100. + t * axis as f64
+                })),
+                1.,
+                1e10 * (t + 1.),
+                SimVec::ZERO,
+            );
+            bodies.push(body);
+            reference.push(body);
+        }
+        bodies.calc_acc(GravityModel::Newtonian);
+
+        for (i, lhs) in reference.iter().enumerate() {
+            let expected = reference.iter().enumerate().filter(|(j, _)| *j != i).fold(
+                SimVec::ZERO,
+                |acc, (_, rhs)| {
+                    acc + consts::G * rhs.mass() / lhs.pos().distance_squared(rhs.pos())
+                        * (rhs.pos() - lhs.pos()).normalize()
+                },
+            );
+            let acc = bodies.get(i).acc();
+            assert!((acc - expected).length() <= expected.length() * 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_test_particles() {
+        let mut bodies = CelestialBodies::default();
+        assert_eq!(
+            bodies.push(CelestialBody::new(SimVec::X, 1., 0., SimVec::ZERO)),
+            0
+        );
+        assert_eq!(
+            bodies.push(CelestialBody::new(SimVec::ZERO, 1., 1e10, SimVec::ZERO)),
+            0
+        );
+        assert_eq!(
+            bodies.push(CelestialBody::new(SimVec::Y, 1., 0., SimVec::ZERO)),
+            2
+        );
+        assert_eq!((bodies.len(), bodies.num_massive()), (3, 1));
+        bodies.calc_acc(GravityModel::post_newtonian());
+
+        // The massive body doesn't feel the particles, which still feel it.
+        assert_eq!(bodies.get(0).acc(), SimVec::ZERO);
+        for (index, dir) in [(1, SimVec::NEG_X), (2, SimVec::NEG_Y)] {
+            let acc = bodies.get(index).acc();
+            assert!((acc.normalize() - dir).length() < 1e-12);
+            assert!((acc.length() - consts::G * 1e10).abs() <= consts::G * 1e10 * 1e-6);
+        }
+
+        bodies.remove(0);
+        assert_eq!((bodies.len(), bodies.num_massive()), (2, 0));
+    }
+}
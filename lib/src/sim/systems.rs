@@ -1,44 +1,376 @@
 use bevy::{
-    ecs::system::{Query, Res, ResMut},
+    asset::{Assets, Handle},
+    ecs::{
+        entity::Entity,
+        event::EventWriter,
+        query::{With, Without},
+        system::{Commands, Local, Query, Res, ResMut},
+    },
     gizmos::gizmos::Gizmos,
+    hierarchy::DespawnRecursiveExt,
+    log::info,
+    math::Vec3,
+    render::color::Color,
+    sprite::ColorMaterial,
+    time::Time,
     transform::components::Transform,
+    utils::HashMap,
+};
+
+use crate::{
+    assets::{settings::StarProperties, MaterialAssets, MeshAssets},
+    consts,
+    sci::{evolution, habitability, physics},
 };
 
 use super::{
-    components::CelestialBodyId,
-    resources::{Galaxy, OrbitPredictor, SimulationTimeScale},
+    components::{
+        CelestialBodyAtmosphere, CelestialBodyColor, CelestialBodyCrust,
+        CelestialBodyEffectiveTemp, CelestialBodyId, CelestialBodyName,
+        CelestialBodySubstanceProps, Comet, CometActivity, CompactObject, Escaping, Habitability,
+        HabitableZone, LuminosityClass, Star, StarClass, StarLuminosity, StellarEvolution,
+        StellarHeating, StellarPhase, StellarState,
+    },
+    resources::{
+        EjectionPolicy, Galaxy, OrbitPredictor, SimulationScheduler, SimulationTimeScale,
+        StellarClock, ViewPlane,
+    },
+    CelestialBodyEjected,
 };
 
 pub(super) fn universal_gravitation(
     mut galaxy: ResMut<Galaxy>,
     mut predictor: ResMut<OrbitPredictor>,
+    mut scheduler: ResMut<SimulationScheduler>,
+    mut clock: ResMut<StellarClock>,
     time_scale: Res<SimulationTimeScale>,
+    time: Res<Time>,
 ) {
-    for _ in 0..time_scale.0 {
+    let steps = scheduler.advance(time.delta_seconds_f64(), time_scale.0);
+    for _ in 0..steps {
         galaxy.step();
         predictor.step();
     }
+    clock.advance(steps);
+}
+
+type EvolvingStar<'a> = (
+    Entity,
+    &'a CelestialBodyId,
+    &'a CelestialBodyName,
+    &'a mut StellarEvolution,
+    &'a mut StarClass,
+    &'a mut CelestialBodyEffectiveTemp,
+    &'a mut StarLuminosity,
+    &'a mut HabitableZone,
+    &'a mut CelestialBodyColor,
+    &'a mut Transform,
+    Option<&'a Handle<ColorMaterial>>,
+);
+
+type HeatedBody<'a> = (
+    &'a CelestialBodyId,
+    &'a StellarHeating,
+    &'a mut CelestialBodyEffectiveTemp,
+    Option<&'a mut Habitability>,
+    Option<&'a CelestialBodyCrust>,
+    Option<&'a CelestialBodyAtmosphere>,
+    Option<&'a CelestialBodySubstanceProps>,
+);
+
+/// Bodies warmed by a star, whose temperature follows its luminosity. Comets are warmed
+/// by their distance instead, in [`comet_activity`].
+type WarmedByLuminosity = (Without<Star>, Without<Comet>);
+
+/// Ages the stars by the time simulated since the last frame, and updates their bodies,
+/// looks and the temperatures and habitability of the bodies they heat. Stars that die
+/// are marked with the [`CompactObject`] they leave behind.
+pub(super) fn stellar_evolution(
+    mut commands: Commands,
+    mut galaxy: ResMut<Galaxy>,
+    mut clock: ResMut<StellarClock>,
+    star_props: Res<StarProperties>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut stars_query: Query<EvolvingStar, With<Star>>,
+    mut heated_query: Query<HeatedBody, WarmedByLuminosity>,
+) {
+    let years = clock.take();
+    if years <= 0. {
+        return;
+    }
+
+    let mut heating_stars = HashMap::new();
+    for (
+        entity,
+        id,
+        name,
+        mut evolution,
+        mut class,
+        mut effective_temp,
+        mut luminosity,
+        mut habitable_zone,
+        mut color,
+        mut transform,
+        material,
+    ) in stars_query.iter_mut()
+    {
+        evolution.age += years;
+        let state = evolution::evolve(&evolution, evolution.age);
+        if state.phase != evolution.state.phase {
+            info!("{} became a {}", name.0, state.phase);
+            if let Some(object) = CompactObject::of(state.phase) {
+                commands.entity(entity).insert(object);
+            }
+        }
+        evolution.state = state;
+
+        galaxy.set_mass_radius(
+            *id,
+            state.mass * consts::SUN_MASS * consts::STAR_MASS_SCALE,
+            state.radius * consts::SUN_RADIUS * consts::STAR_RADIUS_SCALE,
+        );
+        // Meshes are built for the radius the star was generated with, and compact
+        // objects are drawn no smaller than a minimum.
+        let drawn_radius = |state: &StellarState| match state.phase.is_remnant() {
+            true => state.radius.max(consts::COMPACT_OBJECT_MIN_RADIUS),
+            false => state.radius,
+        };
+        transform.scale =
+            Vec3::splat((drawn_radius(&state) / drawn_radius(&evolution.generated)) as f32);
+        effective_temp.0 = state.effective_temp;
+        luminosity.0 = state.luminosity;
+        *habitable_zone = habitability::habitable_zone(state.luminosity, state.effective_temp);
+        heating_stars.insert(*id, (state.luminosity, *habitable_zone));
+        // Brown dwarfs have a table of their own, and keep what they were generated with.
+        if class.ty.is_substellar() {
+            continue;
+        }
+
+        // Giants keep a brighter class they were generated with. Neutron stars have no
+        // table of their own, and only borrow the colour of the main sequence.
+        let lum = match state.phase {
+            StellarPhase::MainSequence | StellarPhase::NeutronStar => LuminosityClass::Dwarf,
+            StellarPhase::Subgiant => LuminosityClass::Subgiant,
+            StellarPhase::Giant => class.lum.min(LuminosityClass::Giant),
+            _ => LuminosityClass::WhiteDwarf,
+        };
+        let new_color = if state.phase == StellarPhase::BlackHole {
+            Color::BLACK
+        } else {
+            let info =
+                star_props.interpolate(lum, state.effective_temp, |info| info.effective_temp);
+            if state.phase != StellarPhase::NeutronStar {
+                *class = info.class;
+            }
+            info.color.into()
+        };
+        // Writing to the material makes it upload again, so only changes are written.
+        if new_color == color.0 {
+            continue;
+        }
+        color.0 = new_color;
+        galaxy.set_color(*id, color.0);
+        if let Some(material) = material.and_then(|handle| materials.get_mut(handle)) {
+            material.color = color.0;
+        }
+    }
+
+    // Equilibrium temperatures go as the fourth root of the luminosity.
+    let galaxy = &*galaxy;
+    heated_query.par_iter_mut().for_each(
+        |(id, heating, mut effective_temp, habitability, crust, atmo, substance_props)| {
+            let Some(&(luminosity, zone)) = heating_stars.get(&heating.star) else {
+                return;
+            };
+            effective_temp.0 =
+                heating.effective_temp * (luminosity / heating.luminosity).powf(0.25);
+
+            let (Some(mut habitability), Some(substance_props)) = (habitability, substance_props)
+            else {
+                return;
+            };
+            let (Some(body), Some(star)) = (galaxy.get_body(*id), galaxy.get_body(heating.star))
+            else {
+                return;
+            };
+            *habitability = habitability::habitability(
+                &zone,
+                body.pos().distance(star.pos()),
+                effective_temp.0,
+                crust,
+                atmo,
+                substance_props,
+            );
+        },
+    );
+}
+
+type WarmedComet<'a> = (
+    Entity,
+    &'a CelestialBodyId,
+    &'a StellarHeating,
+    &'a mut Comet,
+    &'a mut CelestialBodyEffectiveTemp,
+    Option<&'a mut CometActivity>,
+);
+
+/// Warms the comets by how close they are to their star, and gives those above their
+/// sublimation point a coma and a tail pointing away from it. Active comets lose mass
+/// over the time simulated since the last frame.
+pub(super) fn comet_activity(
+    mut commands: Commands,
+    galaxy: Res<Galaxy>,
+    view: Res<ViewPlane>,
+    mut last_step: Local<u64>,
+    stars_query: Query<(&CelestialBodyId, &CelestialBodyEffectiveTemp), With<Star>>,
+    mut comets_query: Query<WarmedComet, Without<Star>>,
+) {
+    let duration = galaxy.steps().saturating_sub(*last_step) as f64 * galaxy.time_step();
+    *last_step = galaxy.steps();
+    let star_temps = stars_query
+        .iter()
+        .map(|(id, effective_temp)| (*id, effective_temp.0))
+        .collect::<HashMap<_, _>>();
+
+    for (entity, id, heating, mut comet, mut effective_temp, activity) in comets_query.iter_mut() {
+        let (Some(body), Some(star), Some(&star_temp)) = (
+            galaxy.get_body(*id),
+            galaxy.get_body(heating.star),
+            star_temps.get(&heating.star),
+        ) else {
+            continue;
+        };
+        let rel_pos = body.pos() - star.pos();
+        effective_temp.0 =
+            physics::planetary_eq_temp_from_temp(star_temp, star.radius(), 0., rel_pos.length())
+                * consts::PLANET_EFFCETIVE_TEMP_SCALE;
+
+        if !comet.is_active(effective_temp.0) {
+            if activity.is_some() {
+                commands.entity(entity).remove::<CometActivity>();
+            }
+            continue;
+        }
+
+        comet.mass -= comet.mass_loss(effective_temp.0, duration);
+        let coma_radius = body.radius() * consts::COMET_COMA_RADIUS_COEFF;
+        let length = coma_radius
+            * consts::COMET_TAIL_LENGTH_COEFF
+            * (effective_temp.0 / comet.sublimation_point).powi(2);
+        let new_activity = CometActivity {
+            coma_radius,
+            tail: view.project(rel_pos).normalize_or_zero() * length,
+        };
+        match activity {
+            Some(mut activity) => *activity = new_activity,
+            None => {
+                commands.entity(entity).insert(new_activity);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn escape_detector(
+    mut commands: Commands,
+    mut galaxy: ResMut<Galaxy>,
+    mut predictor: ResMut<OrbitPredictor>,
+    mut mesh_assets: ResMut<MeshAssets>,
+    mut material_assets: ResMut<MaterialAssets>,
+    policy: Res<EjectionPolicy>,
+    mut ejected: EventWriter<CelestialBodyEjected>,
+    mut last_check: Local<Option<u64>>,
+    bodies_query: Query<(Entity, &CelestialBodyId, Option<&Escaping>)>,
+) {
+    if last_check.is_some_and(|step| galaxy.steps() < step + consts::ESCAPE_CHECK_INTERVAL) {
+        return;
+    }
+    *last_check = Some(galaxy.steps());
+
+    let escaping = galaxy
+        .escaping_bodies()
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let mut removed = false;
+    bodies_query.for_each(|(entity, id, flag)| match (escaping.get(id), flag) {
+        (Some(&escaping), None) => {
+            ejected.send(CelestialBodyEjected { id: *id, escaping });
+            match *policy {
+                EjectionPolicy::Keep => {
+                    commands.entity(entity).insert(escaping);
+                }
+                EjectionPolicy::Rogue => {
+                    galaxy.make_rogue(*id);
+                    commands.entity(entity).insert(escaping);
+                }
+                EjectionPolicy::Despawn => {
+                    galaxy.remove_body(*id);
+                    mesh_assets.remove(*id);
+                    material_assets.remove(*id);
+                    commands.entity(entity).despawn_recursive();
+                    removed = true;
+                }
+            }
+        }
+        (Some(&escaping), Some(_)) => {
+            commands.entity(entity).insert(escaping);
+        }
+        // Captured again. Rogues are never checked, so they keep their flag.
+        (None, Some(_)) if !galaxy.is_rogue(*id) => {
+            commands.entity(entity).remove::<Escaping>();
+        }
+        _ => {}
+    });
+
+    if removed {
+        let iterations = predictor.iterations();
+        predictor.update_state(iterations, &galaxy);
+    }
 }
 
 pub(super) fn transform_syncer(
     galaxy: Res<Galaxy>,
+    scheduler: Res<SimulationScheduler>,
+    view: Res<ViewPlane>,
     mut bodies_query: Query<(&CelestialBodyId, &mut Transform)>,
 ) {
+    let alpha = scheduler.alpha();
     bodies_query.par_iter_mut().for_each(|(id, mut transform)| {
         if let Some(body) = galaxy.get_body(*id) {
-            transform.translation = body.pos().as_vec2().extend(0.);
+            transform.translation = view.project(body.lerp_pos(alpha)).as_vec2().extend(0.);
         }
     });
 }
 
-pub(super) fn orbit_drawer(predictor: Res<OrbitPredictor>, mut gizmos: Gizmos) {
+pub(super) fn comet_drawer(
+    comets_query: Query<(&Transform, &CelestialBodyColor, &CometActivity)>,
+    mut gizmos: Gizmos,
+) {
+    comets_query.for_each(|(transform, color, activity)| {
+        let pos = transform.translation.truncate();
+        let color = color.0.with_a(0.5);
+        gizmos.circle_2d(pos, activity.coma_radius as f32, color);
+        gizmos.line_2d(pos, pos + activity.tail.as_vec2(), color);
+    });
+}
+
+pub(super) fn orbit_drawer(
+    predictor: Res<OrbitPredictor>,
+    view: Res<ViewPlane>,
+    mut gizmos: Gizmos,
+) {
     predictor.iter().for_each(|orbit| {
         let verts = orbit.vertices();
         if verts.is_empty() {
             return;
         }
         for i in 0..orbit.vertices().len() - 1 {
-            gizmos.line_2d(verts[i].as_vec2(), verts[i + 1].as_vec2(), orbit.color);
+            gizmos.line_2d(
+                view.project(verts[i]).as_vec2(),
+                view.project(verts[i + 1]).as_vec2(),
+                orbit.color,
+            );
         }
     });
 }
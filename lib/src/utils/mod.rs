@@ -1,8 +1,14 @@
 use std::{io::Error, path::Path};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 pub fn deser<T: for<'a> Deserialize<'a>>(path: impl AsRef<Path>) -> Result<T, Error> {
     let raw = std::fs::read_to_string(path)?;
     serde_json::from_str(&raw).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))
 }
+
+pub fn ser<T: Serialize>(path: impl AsRef<Path>, value: &T) -> Result<(), Error> {
+    let raw = serde_json::to_string_pretty(value)
+        .map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+    std::fs::write(path, raw)
+}